@@ -57,6 +57,7 @@ create table if not exists favorite (
 );
 --- scraper_task
 create sequence seq_scraper_task;
+create type schedule_state as enum ('active', 'paused');
 create table scraper_task (
     id bigint primary key default nextval('seq_scraper_task'),
     created timestamp not null,
@@ -66,12 +67,30 @@ create table scraper_task (
     name varchar(60) not null,
     rule jsonb not null,
     data jsonb default null,
-    job_id uuid default null
+    -- 已不再使用：调度ID只保存在各副本内存中
+    job_id uuid default null,
+    schedule_state schedule_state not null default 'active',
+    rule_revision int not null default 0,
+    template_id bigint null
 );
 create index idx_scraper_task_user_id_name_created on scraper_task(user_id, name, created);
+--- task_rule_revision
+-- 规则的不可变修订历史；scraper_task.rule_revision 为当前规则对应的修订号，0 表示尚未修改过
+create sequence if not exists seq_task_rule_revision;
+create table task_rule_revision (
+    id bigint primary key default nextval('seq_task_rule_revision'),
+    task_id bigint not null references scraper_task(id),
+    revision int not null,
+    rule jsonb not null,
+    author_id bigint not null,
+    comment varchar(256) null,
+    created timestamp not null default current_timestamp,
+    unique (task_id, revision)
+);
 --- task_instance
 create sequence if not exists seq_task_instance;
-create type instance_status as enum ('running', 'success', 'failed');
+create type instance_status as enum ('running', 'success', 'failed', 'cancelled');
+create type trigger_source as enum ('cron', 'manual', 'api', 'upstream', 'retry');
 create table task_instance (
     id bigint primary key default nextval('seq_task_instance'),
     task_id bigint not null references scraper_task(id),
@@ -80,9 +99,161 @@ create table task_instance (
     status instance_status not null,
     data_count int not null default 0,
     log_key varchar(500) null,
-    error_message text null
+    error_message text null,
+    trigger_source trigger_source not null default 'cron',
+    rule_revision int null,
+    chain_dispatched boolean not null default false,
+    -- 失败重试：重试实例指向被重试的实例，attempt 为第几次重试（首次执行为0）
+    parent_instance_id bigint null,
+    attempt int not null default 0,
+    retry_checked boolean not null default false,
+    -- worker 运行期间定期上报，为空表示 worker 尚未支持心跳
+    heartbeat timestamp null,
+    -- 通过 worker 接口创建的实例：执行的 worker、队列任务ID和已采集页数
+    worker_id bigint null,
+    job_id varchar(64) null,
+    pages int not null default 0
 );
 create index idx_task_instance_task_id_created on task_instance(task_id, created desc);
+create index idx_task_instance_chain_pending on task_instance(modified) where chain_dispatched = false and status <> 'running';
+create index idx_task_instance_running on task_instance(task_id) where status = 'running';
+create index idx_task_instance_retry_pending on task_instance(modified) where retry_checked = false and status = 'failed';
+create index idx_task_instance_parent_instance_id on task_instance(parent_instance_id) where parent_instance_id is not null;
+--- task_dispatch
+-- 每次入队的派发记录。队列负载只有 task_id，实例由 autowds-instance 写入，
+-- 插入实例时按先进先出认领同一任务最早一条未认领的派发记录，得到该实例的触发来源
+create sequence if not exists seq_task_dispatch;
+create table task_dispatch (
+    id bigint primary key default nextval('seq_task_dispatch'),
+    task_id bigint not null references scraper_task(id),
+    job_id varchar(64) not null,
+    -- 入队的队列名，取消时从该队列移除
+    queue varchar(64) null,
+    trigger_source trigger_source not null,
+    user_id bigint null,
+    instance_id bigint null,
+    -- 上游任务触发时的上游实例；pass_records 为真时 worker 以该实例的采集记录作为输入
+    upstream_instance_id bigint null,
+    pass_records boolean not null default false,
+    -- 失败重试时被重试的实例及重试次数
+    parent_instance_id bigint null,
+    attempt int not null default 0,
+    -- 开始运行前被取消，worker 取到对应队列任务时直接丢弃
+    cancelled boolean not null default false,
+    created timestamp not null default current_timestamp
+);
+create index idx_task_dispatch_upstream_instance_id on task_dispatch(upstream_instance_id) where upstream_instance_id is not null;
+create index idx_task_dispatch_pending on task_dispatch(task_id, id) where instance_id is null and cancelled = false;
+create index idx_task_dispatch_task_id_created on task_dispatch(task_id, created);
+create or replace function task_instance_claim_dispatch() returns trigger as $$
+declare
+    claimed task_dispatch%rowtype;
+begin
+    if new.job_id is not null then
+        -- worker 接口创建实例时带上队列任务ID，精确认领对应的派发记录；
+        -- 不跳过锁定行，与取消操作串行，已取消的派发拒绝创建实例
+        select * into claimed from task_dispatch
+        where task_id = new.task_id and instance_id is null and job_id = new.job_id
+        order by id limit 1
+        for update;
+        if found and claimed.cancelled then
+            raise exception 'task dispatch % cancelled', new.job_id;
+        end if;
+    else
+        select * into claimed from task_dispatch
+        where task_id = new.task_id and instance_id is null and cancelled = false
+        order by id limit 1
+        for update skip locked;
+        -- 未带队列任务ID时无法区分，近一小时（与配额的排队窗口一致）只有已取消的派发则视为已取消
+        if not found and exists (
+            select 1 from task_dispatch
+            where task_id = new.task_id and instance_id is null and cancelled = true
+              and created > current_timestamp - interval '1 hour'
+        ) then
+            raise exception 'task#% dispatch cancelled', new.task_id;
+        end if;
+    end if;
+    if found then
+        update task_dispatch set instance_id = new.id where id = claimed.id;
+        new.trigger_source := claimed.trigger_source;
+        new.parent_instance_id := claimed.parent_instance_id;
+        new.attempt := claimed.attempt;
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+create trigger trg_task_instance_claim_dispatch before insert on task_instance
+for each row execute function task_instance_claim_dispatch();
+-- 实例运行的是插入时任务的当前规则修订
+create or replace function task_instance_fill_rule_revision() returns trigger as $$
+begin
+    if new.rule_revision is null then
+        select rule_revision into new.rule_revision from scraper_task where id = new.task_id;
+    end if;
+    return new;
+end;
+$$ language plpgsql;
+create trigger trg_task_instance_fill_rule_revision before insert on task_instance
+for each row execute function task_instance_fill_rule_revision();
+--- task_dependency
+-- 上游任务实例结束后按 trigger_on 触发下游任务
+create sequence if not exists seq_task_dependency;
+create table task_dependency (
+    id bigint primary key default nextval('seq_task_dependency'),
+    user_id bigint not null,
+    upstream_task_id bigint not null references scraper_task(id),
+    downstream_task_id bigint not null references scraper_task(id),
+    trigger_on instance_status not null,
+    pass_records boolean not null default false,
+    created timestamp not null default current_timestamp,
+    unique (upstream_task_id, downstream_task_id, trigger_on)
+);
+create index idx_task_dependency_downstream on task_dependency(downstream_task_id);
+--- proxy
+-- scraper_task.data.schedule.proxyId 引用代理池，0 表示系统默认；user_id 为空的是平台代理池
+create type proxy_protocol as enum ('http', 'https', 'socks5');
+create type proxy_rotation as enum ('round_robin', 'random', 'sticky');
+create sequence if not exists seq_proxy_pool;
+create table proxy_pool (
+    id int primary key default nextval('seq_proxy_pool'),
+    user_id bigint null,
+    name varchar(64) not null,
+    rotation proxy_rotation not null default 'round_robin',
+    created timestamp not null default current_timestamp,
+    modified timestamp not null default current_timestamp
+);
+create index idx_proxy_pool_user_id on proxy_pool(user_id);
+create sequence if not exists seq_proxy;
+create table proxy (
+    id bigint primary key default nextval('seq_proxy'),
+    pool_id int not null references proxy_pool(id),
+    protocol proxy_protocol not null,
+    host varchar(255) not null,
+    port int not null,
+    username varchar(128) null,
+    -- AES-256-GCM 加密的密码：12 字节 nonce + 密文
+    password_cipher bytea null,
+    enabled boolean not null default true,
+    -- worker 上报的最近一次健康检查结果
+    last_checked timestamp null,
+    last_check_ok boolean null,
+    last_latency_ms int null,
+    last_error text null,
+    created timestamp not null default current_timestamp,
+    modified timestamp not null default current_timestamp
+);
+create index idx_proxy_pool_id on proxy(pool_id);
+--- worker
+-- 执行采集任务的 worker，凭 id + secret 换取访问令牌，只保存 secret 的 SHA-256
+create sequence if not exists seq_worker;
+create table worker (
+    id bigint primary key default nextval('seq_worker'),
+    name varchar(64) not null unique,
+    secret_hash varchar(64) not null,
+    enabled boolean not null default true,
+    last_seen timestamp null,
+    created timestamp not null default current_timestamp
+);
 --- credit_log
 create sequence if not exists seq_credit_log;
 create type credit_operation as enum ('REGISTER', 'INVITE', 'EXPORT', 'ADMIN_ADJUST', 'CHECK_IN');
@@ -102,10 +273,13 @@ create index idx_credit_log_user_id_created on credit_log(user_id, created desc)
 create type order_level as enum ('monthly', 'annual');
 
 -- 创建支付来源枚举类型
-create type pay_from as enum ('alipay', 'wechat', 'paddle');
+create type pay_from as enum ('alipay', 'wechat', 'paddle', 'mock');
 
 -- 创建订单状态枚举类型
-create type order_status as enum ('created', 'paid', 'closed');
+create type order_status as enum ('created', 'paid', 'closed', 'refunded');
+
+-- 创建订单类型枚举类型：新购/续费、版本升级补差价
+create type order_kind as enum ('purchase', 'upgrade');
 
 -- 支付订单表
 create table if not exists pay_order (
@@ -114,10 +288,16 @@ create table if not exists pay_order (
     level order_level not null,
     edition product_edition not null,
     pay_from pay_from not null,
+    kind order_kind not null default 'purchase',
+    amount int not null,
+    upgrade_from product_edition null,
     status order_status not null default 'created',
     created timestamp not null default current_timestamp,
     modified timestamp not null default current_timestamp,
     confirm timestamp null,
+    fulfilled_at timestamp null,
+    refunded_amount int not null default 0,
+    meta jsonb null,
     resp jsonb null
 );
 
@@ -126,6 +306,90 @@ create index idx_pay_order_status on pay_order(status);
 create index idx_pay_order_created on pay_order(created);
 create index idx_pay_order_confirm on pay_order(confirm);
 
+-- 发票申请状态
+create type invoice_status as enum ('pending', 'issued', 'rejected');
+
+-- 发票申请：用户对已支付订单申请开票，管理员开具后通过邮件发送
+create table if not exists invoice_request (
+    id bigserial primary key,
+    user_id bigint not null,
+    order_id bigint not null,
+    title varchar(128) not null,
+    tax_id varchar(32) null,
+    email varchar(128) not null,
+    amount int not null,
+    status invoice_status not null default 'pending',
+    invoice_no varchar(64) null,
+    invoice_url varchar(512) null,
+    remark varchar(256) null,
+    created timestamp not null default current_timestamp,
+    modified timestamp not null default current_timestamp,
+    issued_at timestamp null
+);
+
+create index idx_invoice_request_user_id on invoice_request(user_id);
+create index idx_invoice_request_status on invoice_request(status, created);
+-- 同一订单只能有一张有效的发票申请，驳回后可重新申请
+create unique index uk_invoice_request_order_id on invoice_request(order_id) where status <> 'rejected';
+
+-- 会员变更原因
+create type membership_change_reason as enum ('purchase', 'upgrade', 'expired', 'admin', 'trial');
+
+-- 会员变更历史：每次开通、续费、升级、试用、到期降级、管理员调整都记录一条
+create table if not exists membership_history (
+    id bigserial primary key,
+    user_id bigint not null,
+    from_edition product_edition not null,
+    to_edition product_edition not null,
+    from_expired_at timestamp null,
+    to_expired_at timestamp null,
+    reason membership_change_reason not null,
+    order_id bigint null,
+    remark varchar(256) null,
+    created timestamp not null default current_timestamp
+);
+
+create index idx_membership_history_user_id on membership_history(user_id, created desc);
+
+-- 会员试用：每个账号、邮箱、设备指纹只能试用一次；试用后首次付费记录为转化
+create table if not exists membership_trial (
+    id bigserial primary key,
+    user_id bigint not null unique,
+    email varchar(64) not null unique,
+    fingerprint varchar(128) not null unique,
+    ip inet null,
+    edition product_edition not null,
+    expired_at timestamp not null,
+    converted_order_id bigint null,
+    converted_at timestamp null,
+    created timestamp not null default current_timestamp
+);
+
+create index idx_membership_trial_created on membership_trial(created);
+
+-- 支付回调处理状态
+create type notify_status as enum ('pending', 'processed', 'failed', 'rejected');
+
+-- 支付回调事件日志：原样保存渠道推送，按渠道事件ID去重，支持失败重试与手动重放
+create table if not exists pay_notify_event (
+    id bigserial primary key,
+    provider pay_from not null,
+    event_id varchar(128) not null,
+    order_id bigint null,
+    raw_body text not null,
+    headers jsonb null,
+    signature_valid boolean not null,
+    status notify_status not null default 'pending',
+    attempts int not null default 0,
+    error_message text null,
+    created timestamp not null default current_timestamp,
+    modified timestamp not null default current_timestamp,
+    processed_at timestamp null,
+    unique (provider, event_id)
+);
+create index idx_pay_notify_event_status_modified on pay_notify_event(status, modified);
+create index idx_pay_notify_event_order_id on pay_notify_event(order_id);
+
 --- data_clean_pipeline
 create table if not exists data_clean_pipeline (
     id bigserial primary key,
@@ -6,8 +6,22 @@ use summer::config::Configurable;
 #[derive(Debug, Clone, Configurable, Deserialize)]
 #[config_prefix = "apalis"]
 pub struct ApalisConfig {
+    /// `Fast` 任务（及未配置调度的任务）的队列
     #[serde(default = "default_queue")]
     pub queue: String,
+    /// `Browser` 任务的队列，为空时与 `queue` 共用一个队列
+    #[serde(default)]
+    pub browser_queue: Option<String>,
+}
+
+impl ApalisConfig {
+    /// 单独配置的 `Browser` 队列，未配置或与 `queue` 相同时返回 `None`
+    pub fn browser_queue(&self) -> Option<&str> {
+        self.browser_queue
+            .as_deref()
+            .map(str::trim)
+            .filter(|q| !q.is_empty() && *q != self.queue)
+    }
 }
 
 fn default_queue() -> String {
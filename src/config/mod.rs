@@ -1,5 +1,7 @@
 pub mod apalis;
 pub mod mail;
 pub mod pay;
+pub mod proxy;
 pub mod s3;
 pub mod tencent_ses;
+pub mod trial;
@@ -12,6 +12,18 @@ pub struct PayConfig {
     pub alipay_enable: bool,
     #[serde(default)]
     pub paddle_enable: bool,
+    /// 模拟支付渠道，仅用于开发/测试环境
+    #[serde(default)]
+    pub mock_enable: bool,
+    /// 未支付订单的有效期（分钟），超时后由定时任务调用渠道关单接口并置为 `closed`
+    #[serde(default = "default_order_ttl_minutes")]
+    pub order_ttl_minutes: i64,
+    /// 会员到期提醒邮件中的续费链接
+    #[serde(default)]
+    pub renew_url: String,
+    /// 跳转类支付（支付宝 wap/page、微信 H5）完成后返回的页面
+    #[serde(default)]
+    pub return_url: String,
     pub alipay_api_url: String,
     pub alipay_app_id: String,
     /// 支付宝根证书
@@ -27,3 +39,7 @@ pub struct PayConfig {
     pub paddle_monthly_price_id: String,
     pub paddle_annual_price_id: String,
 }
+
+fn default_order_ttl_minutes() -> i64 {
+    30
+}
@@ -0,0 +1,26 @@
+use anyhow::Context;
+use serde::Deserialize;
+use summer::config::Configurable;
+
+/// 代理凭证加密配置
+#[derive(Debug, Clone, Configurable, Deserialize)]
+#[config_prefix = "proxy"]
+pub struct ProxyConfig {
+    /// AES-256 密钥的十六进制表示
+    #[serde(default)]
+    pub secret_key: String,
+}
+
+impl ProxyConfig {
+    pub fn is_configured(&self) -> bool {
+        !self.secret_key.trim().is_empty()
+    }
+
+    pub fn key(&self) -> anyhow::Result<[u8; 32]> {
+        let bytes =
+            hex::decode(self.secret_key.trim()).context("[proxy] secret_key 不是十六进制")?;
+        bytes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("[proxy] secret_key 须为32字节"))
+    }
+}
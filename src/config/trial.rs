@@ -0,0 +1,25 @@
+use crate::model::sea_orm_active_enums::ProductEdition;
+use serde::Deserialize;
+use summer::config::Configurable;
+
+/// 付费版本免费试用，每个账号、邮箱、设备只能试用一次
+#[derive(Debug, Clone, Configurable, Deserialize)]
+#[config_prefix = "trial"]
+pub struct TrialConfig {
+    #[serde(default)]
+    pub enable: bool,
+    /// 试用的版本
+    #[serde(default = "default_edition")]
+    pub edition: ProductEdition,
+    /// 试用天数
+    #[serde(default = "default_days")]
+    pub days: i64,
+}
+
+fn default_edition() -> ProductEdition {
+    ProductEdition::L1
+}
+
+fn default_days() -> i64 {
+    7
+}
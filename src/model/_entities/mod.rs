@@ -5,13 +5,23 @@ pub mod prelude;
 pub mod account_user;
 pub mod credit_log;
 pub mod favorite;
+pub mod invoice_request;
 pub mod marketing_attribution;
 pub mod marketing_campaign;
 pub mod marketing_delivery;
 pub mod marketing_event;
 pub mod marketing_lead;
+pub mod membership_history;
+pub mod membership_trial;
+pub mod pay_notify_event;
 pub mod pay_order;
+pub mod proxy;
+pub mod proxy_pool;
 pub mod scraper_task;
 pub mod sea_orm_active_enums;
+pub mod task_dependency;
+pub mod task_dispatch;
 pub mod task_instance;
+pub mod task_rule_revision;
 pub mod task_template;
+pub mod worker;
@@ -1,6 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.0.1
 
-use super::sea_orm_active_enums::{OrderLevel, OrderStatus, PayFrom, ProductEdition};
+use super::sea_orm_active_enums::{OrderKind, OrderLevel, OrderStatus, PayFrom, ProductEdition};
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
@@ -13,10 +13,17 @@ pub struct Model {
     pub level: OrderLevel,
     pub edition: ProductEdition,
     pub pay_from: PayFrom,
+    pub kind: OrderKind,
+    pub amount: i32,
+    pub upgrade_from: Option<ProductEdition>,
     pub status: OrderStatus,
     pub created: DateTime,
     pub modified: DateTime,
     pub confirm: Option<DateTime>,
+    pub fulfilled_at: Option<DateTime>,
+    pub refunded_amount: i32,
+    #[sea_orm(column_type = "JsonBinary")]
+    pub meta: Option<Json>,
     #[sea_orm(column_type = "JsonBinary")]
     pub resp: Option<Json>,
 }
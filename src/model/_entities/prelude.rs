@@ -3,12 +3,22 @@
 pub use super::account_user::Entity as AccountUser;
 pub use super::credit_log::Entity as CreditLog;
 pub use super::favorite::Entity as Favorite;
+pub use super::invoice_request::Entity as InvoiceRequest;
 pub use super::marketing_attribution::Entity as MarketingAttribution;
 pub use super::marketing_campaign::Entity as MarketingCampaign;
 pub use super::marketing_delivery::Entity as MarketingDelivery;
 pub use super::marketing_event::Entity as MarketingEvent;
 pub use super::marketing_lead::Entity as MarketingLead;
+pub use super::membership_history::Entity as MembershipHistory;
+pub use super::membership_trial::Entity as MembershipTrial;
+pub use super::pay_notify_event::Entity as PayNotifyEvent;
 pub use super::pay_order::Entity as PayOrder;
+pub use super::proxy::Entity as Proxy;
+pub use super::proxy_pool::Entity as ProxyPool;
 pub use super::scraper_task::Entity as ScraperTask;
+pub use super::task_dependency::Entity as TaskDependency;
+pub use super::task_dispatch::Entity as TaskDispatch;
 pub use super::task_instance::Entity as TaskInstance;
+pub use super::task_rule_revision::Entity as TaskRuleRevision;
 pub use super::task_template::Entity as TaskTemplate;
+pub use super::worker::Entity as Worker;
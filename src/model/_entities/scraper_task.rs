@@ -5,6 +5,7 @@ use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::model::scraper_task::ScraperTaskData;
+use crate::model::sea_orm_active_enums::ScheduleState;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, JsonSchema)]
 #[sea_orm(table_name = "scraper_task")]
@@ -22,6 +23,10 @@ pub struct Model {
     pub rule: Json,
     #[schemars(skip)]
     pub job_id: Option<Uuid>,
+    pub schedule_state: ScheduleState,
+    pub rule_revision: i32,
+    /// 由模板创建时的来源模板
+    pub template_id: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -132,6 +132,7 @@ pub enum OrderLevel {
     Copy,
     PartialEq,
     Eq,
+    Hash,
     EnumIter,
     DeriveActiveEnum,
     Serialize,
@@ -151,6 +152,33 @@ pub enum PayFrom {
     /// # Paddle
     #[sea_orm(string_value = "paddle")]
     Paddle,
+    /// # 模拟支付
+    #[sea_orm(string_value = "mock")]
+    Mock,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "order_kind")]
+pub enum OrderKind {
+    /// # 新购/续费
+    #[sea_orm(string_value = "purchase")]
+    Purchase,
+    /// # 升级
+    #[sea_orm(string_value = "upgrade")]
+    Upgrade,
 }
 
 #[derive(
@@ -178,6 +206,103 @@ pub enum OrderStatus {
     /// # 已关闭
     #[sea_orm(string_value = "closed")]
     Closed,
+    /// # 已退款
+    #[sea_orm(string_value = "refunded")]
+    Refunded,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "invoice_status")]
+pub enum InvoiceStatus {
+    /// # 待开票
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// # 已开票
+    #[sea_orm(string_value = "issued")]
+    Issued,
+    /// # 已驳回
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(
+    rs_type = "String",
+    db_type = "Enum",
+    enum_name = "membership_change_reason"
+)]
+pub enum MembershipChangeReason {
+    /// # 购买/续费
+    #[sea_orm(string_value = "purchase")]
+    Purchase,
+    /// # 升级
+    #[sea_orm(string_value = "upgrade")]
+    Upgrade,
+    /// # 到期降级
+    #[sea_orm(string_value = "expired")]
+    Expired,
+    /// # 管理员调整
+    #[sea_orm(string_value = "admin")]
+    Admin,
+    /// # 免费试用
+    #[sea_orm(string_value = "trial")]
+    Trial,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "notify_status")]
+pub enum NotifyStatus {
+    /// # 待处理
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// # 已处理
+    #[sea_orm(string_value = "processed")]
+    Processed,
+    /// # 处理失败
+    #[sea_orm(string_value = "failed")]
+    Failed,
+    /// # 验签失败
+    #[sea_orm(string_value = "rejected")]
+    Rejected,
 }
 
 #[derive(
@@ -202,4 +327,118 @@ pub enum InstanceStatus {
     Success,
     #[sea_orm(string_value = "failed")]
     Failed,
+    #[sea_orm(string_value = "cancelled")]
+    Cancelled,
+}
+
+/// # 任务触发来源
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "trigger_source")]
+pub enum TriggerSource {
+    /// # 定时调度
+    #[sea_orm(string_value = "cron")]
+    Cron,
+    /// # 页面上手动运行
+    #[sea_orm(string_value = "manual")]
+    Manual,
+    /// # 通过接口调用运行
+    #[sea_orm(string_value = "api")]
+    Api,
+    /// # 上游任务触发
+    #[sea_orm(string_value = "upstream")]
+    Upstream,
+    /// # 失败自动重试
+    #[sea_orm(string_value = "retry")]
+    Retry,
+}
+
+/// # 任务调度状态
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "schedule_state")]
+pub enum ScheduleState {
+    /// # 调度中
+    #[sea_orm(string_value = "active")]
+    Active,
+    /// # 已暂停
+    #[sea_orm(string_value = "paused")]
+    Paused,
+}
+
+/// # 代理协议
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "proxy_protocol")]
+pub enum ProxyProtocol {
+    #[sea_orm(string_value = "http")]
+    Http,
+    #[sea_orm(string_value = "https")]
+    Https,
+    #[sea_orm(string_value = "socks5")]
+    Socks5,
+}
+
+/// # 代理池轮换策略
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+    strum :: EnumString,
+    JsonSchema,
+    Display,
+)]
+#[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "proxy_rotation")]
+pub enum ProxyRotation {
+    /// # 依次轮换
+    #[sea_orm(string_value = "round_robin")]
+    RoundRobin,
+    /// # 随机选择
+    #[sea_orm(string_value = "random")]
+    Random,
+    /// # 同一实例固定使用一个代理
+    #[sea_orm(string_value = "sticky")]
+    Sticky,
 }
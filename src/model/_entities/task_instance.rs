@@ -4,7 +4,7 @@ use schemars::JsonSchema;
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::model::sea_orm_active_enums::InstanceStatus;
+use crate::model::sea_orm_active_enums::{InstanceStatus, TriggerSource};
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq, Serialize, Deserialize, JsonSchema)]
 #[sea_orm(table_name = "task_instance")]
@@ -18,6 +18,16 @@ pub struct Model {
     pub data_count: i32,
     pub log_key: Option<String>,
     pub error_message: Option<String>,
+    pub trigger_source: TriggerSource,
+    pub rule_revision: Option<i32>,
+    pub chain_dispatched: bool,
+    pub parent_instance_id: Option<i64>,
+    pub attempt: i32,
+    pub retry_checked: bool,
+    pub heartbeat: Option<DateTime>,
+    pub worker_id: Option<i64>,
+    pub job_id: Option<String>,
+    pub pages: i32,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -4,6 +4,8 @@ use chrono::Local;
 use sea_orm::{ActiveModelBehavior, ConnectionTrait, DbErr, Set};
 use summer::async_trait;
 
+use super::sea_orm_active_enums::ProductEdition;
+
 #[async_trait]
 impl ActiveModelBehavior for ActiveModel {
     async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
@@ -17,3 +19,13 @@ impl ActiveModelBehavior for ActiveModel {
         Ok(self)
     }
 }
+
+impl Model {
+    /// 按会员到期时间计算的当前版本，只读不写库；到期降级的记录由 `UserService::refresh_user_membership` 完成
+    pub fn current_edition(&self) -> ProductEdition {
+        match self.vip_expired_at {
+            Some(expired_at) if expired_at <= Local::now().naive_local() => ProductEdition::L0,
+            _ => self.edition.clone(),
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{
+    ActiveModelBehavior, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    ExprTrait, QueryFilter, QueryOrder,
+};
+use summer::async_trait;
+
+// 重新导出实体
+pub use super::_entities::invoice_request::*;
+use super::_entities::sea_orm_active_enums::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+            self.status = Set(InvoiceStatus::Pending);
+        }
+        self.modified = Set(Local::now().naive_local());
+        Ok(self)
+    }
+}
+
+impl Entity {
+    /// 订单当前有效（未驳回）的发票申请
+    pub async fn find_active_by_order<C: ConnectionTrait>(
+        db: &C,
+        order_id: i64,
+    ) -> anyhow::Result<Option<Model>> {
+        Entity::find()
+            .filter(
+                Column::OrderId
+                    .eq(order_id)
+                    .and(Column::Status.ne(InvoiceStatus::Rejected)),
+            )
+            .one(db)
+            .await
+            .with_context(|| format!("find_active_invoice_by_order({order_id}) failed"))
+    }
+
+    pub async fn find_by_user<C: ConnectionTrait>(
+        db: &C,
+        user_id: i64,
+    ) -> anyhow::Result<Vec<Model>> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .order_by_desc(Column::Created)
+            .all(db)
+            .await
+            .with_context(|| format!("find_invoice_by_user({user_id}) failed"))
+    }
+}
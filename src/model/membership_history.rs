@@ -0,0 +1,49 @@
+use super::account_user;
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ConnectionTrait, DbErr};
+use summer::async_trait;
+
+// 重新导出实体
+pub use super::_entities::membership_history::*;
+use super::_entities::sea_orm_active_enums::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        Ok(self)
+    }
+}
+
+impl Entity {
+    /// 记录一次会员变更，`before`/`after` 为变更前后的用户
+    pub async fn record<C: ConnectionTrait>(
+        db: &C,
+        before: &account_user::Model,
+        after: &account_user::Model,
+        reason: MembershipChangeReason,
+        order_id: Option<i64>,
+        remark: Option<String>,
+    ) -> anyhow::Result<Model> {
+        ActiveModel {
+            user_id: Set(before.id),
+            from_edition: Set(before.edition.clone()),
+            to_edition: Set(after.edition.clone()),
+            from_expired_at: Set(before.vip_expired_at),
+            to_expired_at: Set(after.vip_expired_at),
+            reason: Set(reason),
+            order_id: Set(order_id),
+            remark: Set(remark),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .with_context(|| format!("record membership history for user({}) failed", before.id))
+    }
+}
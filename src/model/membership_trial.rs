@@ -0,0 +1,77 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{
+    ActiveModelBehavior, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
+    ExprTrait, QueryFilter, Statement,
+};
+use summer::async_trait;
+
+// 重新导出实体
+pub use super::_entities::membership_trial::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        Ok(self)
+    }
+}
+
+impl Entity {
+    /// 该账号、邮箱或设备是否已经试用过
+    pub async fn find_used<C: ConnectionTrait>(
+        db: &C,
+        user_id: i64,
+        email: &str,
+        fingerprint: &str,
+    ) -> anyhow::Result<Option<Model>> {
+        Entity::find()
+            .filter(
+                Column::UserId
+                    .eq(user_id)
+                    .or(Column::Email.eq(email))
+                    .or(Column::Fingerprint.eq(fingerprint)),
+            )
+            .one(db)
+            .await
+            .with_context(|| format!("find_used_trial({user_id}) failed"))
+    }
+
+    pub async fn find_by_user<C: ConnectionTrait>(
+        db: &C,
+        user_id: i64,
+    ) -> anyhow::Result<Option<Model>> {
+        Entity::find()
+            .filter(Column::UserId.eq(user_id))
+            .one(db)
+            .await
+            .with_context(|| format!("find_trial_by_user({user_id}) failed"))
+    }
+
+    /// 试用用户首次付费时记录转化，已转化的不再覆盖
+    pub async fn mark_converted<C: ConnectionTrait>(
+        db: &C,
+        user_id: i64,
+        order_id: i64,
+    ) -> anyhow::Result<bool> {
+        let now = Local::now().naive_local();
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                r#"
+                UPDATE membership_trial
+                SET converted_order_id = $1, converted_at = $2
+                WHERE user_id = $3 AND converted_order_id IS NULL
+                "#,
+                vec![order_id.into(), now.into(), user_id.into()],
+            ))
+            .await
+            .with_context(|| format!("mark_trial_converted({user_id}, {order_id}) failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+}
@@ -6,15 +6,25 @@ mod _entities;
 pub mod account_user;
 pub mod credit_log;
 pub mod favorite;
+pub mod invoice_request;
 pub mod marketing_attribution;
 pub mod marketing_campaign;
 pub mod marketing_delivery;
 pub mod marketing_event;
 pub mod marketing_lead;
+pub mod membership_history;
+pub mod membership_trial;
+pub mod pay_notify_event;
 pub mod pay_order;
+pub mod proxy;
+pub mod proxy_pool;
 pub mod scraper_task;
+pub mod task_dependency;
+pub mod task_dispatch;
 pub mod task_instance;
+pub mod task_rule_revision;
 pub mod task_template;
+pub mod worker;
 
 impl sea_orm_active_enums::ProductEdition {
     /// 获取当前版本的任务数量上限
@@ -26,4 +36,54 @@ impl sea_orm_active_enums::ProductEdition {
             Self::L3 => u64::MAX,
         }
     }
+
+    /// 获取当前版本允许的最小调度间隔（秒）
+    pub fn min_schedule_interval(&self) -> i64 {
+        match self {
+            Self::L0 => 600,
+            Self::L1 => 300,
+            Self::L2 => 60,
+            Self::L3 => 60,
+        }
+    }
+
+    /// 获取当前版本每小时手动运行任务的次数上限
+    pub fn manual_run_limit(&self) -> u64 {
+        match self {
+            Self::L0 => 3,
+            Self::L1 => 30,
+            Self::L2 => 120,
+            Self::L3 => 600,
+        }
+    }
+
+    /// 获取当前版本同时运行的实例数上限
+    pub fn concurrent_run_limit(&self) -> u64 {
+        match self {
+            Self::L0 => 1,
+            Self::L1 => 5,
+            Self::L2 => 20,
+            Self::L3 => 50,
+        }
+    }
+
+    /// 获取当前版本每天运行任务的次数上限
+    pub fn daily_run_limit(&self) -> u64 {
+        match self {
+            Self::L0 => 50,
+            Self::L1 => 1_000,
+            Self::L2 => 10_000,
+            Self::L3 => u64::MAX,
+        }
+    }
+
+    /// 获取当前版本每月采集的记录数上限
+    pub fn monthly_record_limit(&self) -> u64 {
+        match self {
+            Self::L0 => 10_000,
+            Self::L1 => 500_000,
+            Self::L2 => 5_000_000,
+            Self::L3 => u64::MAX,
+        }
+    }
 }
@@ -0,0 +1,106 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{
+    prelude::DateTime, sea_query::OnConflict, ActiveModelBehavior, ActiveValue::Set, ColumnTrait,
+    ConnectionTrait, DbErr, EntityTrait, ExprTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use summer::async_trait;
+
+// 重新导出实体
+pub use super::_entities::pay_notify_event::*;
+use super::_entities::sea_orm_active_enums::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        self.modified = Set(Local::now().naive_local());
+        Ok(self)
+    }
+}
+
+impl Entity {
+    /// 写入回调事件，`(provider, event_id)` 已存在时保留原记录。
+    ///
+    /// 返回值第二项为 `true` 表示本次是首次收到该事件。
+    pub async fn insert_if_absent<C: ConnectionTrait>(
+        db: &C,
+        provider: PayFrom,
+        event_id: &str,
+        raw_body: String,
+        headers: serde_json::Value,
+        signature_valid: bool,
+    ) -> anyhow::Result<(Model, bool)> {
+        let now = Local::now().naive_local();
+        let status = if signature_valid {
+            NotifyStatus::Pending
+        } else {
+            NotifyStatus::Rejected
+        };
+        let event = ActiveModel {
+            provider: Set(provider),
+            event_id: Set(event_id.to_string()),
+            order_id: Set(None),
+            raw_body: Set(raw_body),
+            headers: Set(Some(headers)),
+            signature_valid: Set(signature_valid),
+            status: Set(status),
+            attempts: Set(0),
+            error_message: Set(None),
+            created: Set(now),
+            modified: Set(now),
+            processed_at: Set(None),
+            ..Default::default()
+        };
+        let inserted = Entity::insert(event)
+            .on_conflict(
+                OnConflict::columns([Column::Provider, Column::EventId])
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .exec_without_returning(db)
+            .await
+            .with_context(|| format!("insert pay_notify_event({provider:?},{event_id}) failed"))?;
+
+        let event = Entity::find()
+            .filter(
+                Column::Provider
+                    .eq(provider)
+                    .and(Column::EventId.eq(event_id)),
+            )
+            .one(db)
+            .await
+            .with_context(|| format!("find pay_notify_event({provider:?},{event_id}) failed"))?
+            .ok_or_else(|| {
+                anyhow::anyhow!("pay_notify_event({provider:?},{event_id}) not found")
+            })?;
+        Ok((event, inserted > 0))
+    }
+
+    /// 查询需要重试的失败事件（仅限验签通过的事件）
+    pub async fn find_retryable<C: ConnectionTrait>(
+        db: &C,
+        max_attempts: i32,
+        modified_before: DateTime,
+        limit: u64,
+    ) -> anyhow::Result<Vec<Model>> {
+        Entity::find()
+            .filter(
+                Column::Status
+                    .eq(NotifyStatus::Failed)
+                    .and(Column::SignatureValid.eq(true))
+                    .and(Column::Attempts.lt(max_attempts))
+                    .and(Column::Modified.lt(modified_before)),
+            )
+            .order_by_asc(Column::Modified)
+            .limit(limit)
+            .all(db)
+            .await
+            .context("find retryable pay_notify_event failed")
+    }
+}
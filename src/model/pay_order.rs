@@ -3,7 +3,7 @@ use chrono::Local;
 use chrono::{Days, NaiveDate};
 use sea_orm::{
     prelude::DateTime, ActiveModelBehavior, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
-    EntityTrait, ExprTrait, FromQueryResult, QueryFilter, QuerySelect, Statement,
+    EntityTrait, ExprTrait, FromQueryResult, QueryFilter, QueryOrder, QuerySelect, Statement,
 };
 use serde::Serialize;
 use summer::async_trait;
@@ -59,6 +59,115 @@ impl Entity {
             .with_context(|| format!("find_wait_confirm({time:?}) failed"))
     }
 
+    /// 查询创建时间早于 `before` 且仍未支付的订单，用于超时关单
+    pub async fn find_expired_created<C: ConnectionTrait>(
+        db: &C,
+        before: DateTime,
+    ) -> anyhow::Result<Vec<Model>> {
+        Entity::find()
+            .filter(
+                Column::Status
+                    .eq(OrderStatus::Created)
+                    .and(Column::Created.lt(before)),
+            )
+            .all(db)
+            .await
+            .with_context(|| format!("find_expired_created({before:?}) failed"))
+    }
+
+    /// 仅当订单仍处于 `created` 状态时置为 `closed`。
+    ///
+    /// 返回 `false` 表示订单状态已被支付回调等并发流程修改，调用方需要重新读取订单。
+    pub async fn close_if_created<C: ConnectionTrait>(
+        db: &C,
+        order_id: i64,
+        resp: Option<serde_json::Value>,
+    ) -> anyhow::Result<bool> {
+        let now = Local::now().naive_local();
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                r#"
+                UPDATE pay_order
+                SET status = 'closed', modified = $1, resp = COALESCE($2, resp)
+                WHERE id = $3 AND status = 'created'
+                "#,
+                vec![now.into(), resp.into(), order_id.into()],
+            ))
+            .await
+            .with_context(|| format!("close_if_created({order_id}) failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 标记订单已履约（开通会员），返回 `false` 表示订单此前已经履约过。
+    ///
+    /// 支付回调重试、轮询查单、超时关单都可能观察到同一笔支付，以此保证会员只开通一次；
+    /// 须与会员变更在同一事务中执行。
+    pub async fn mark_fulfilled<C: ConnectionTrait>(db: &C, order_id: i64) -> anyhow::Result<bool> {
+        let now = Local::now().naive_local();
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                r#"
+                UPDATE pay_order
+                SET fulfilled_at = $1, modified = $1
+                WHERE id = $2 AND status = 'paid' AND fulfilled_at IS NULL
+                "#,
+                vec![now.into(), order_id.into()],
+            ))
+            .await
+            .with_context(|| format!("mark_fulfilled({order_id}) failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 累加订单退款金额，以下单时的已退款金额做乐观锁，累计退款达到 `total` 时订单置为已退款
+    pub async fn add_refunded<C: ConnectionTrait>(
+        db: &C,
+        order: &Model,
+        amount: i32,
+        total: i32,
+    ) -> anyhow::Result<bool> {
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                r#"
+                UPDATE pay_order
+                SET refunded_amount = refunded_amount + $1,
+                    status = CASE WHEN refunded_amount + $1 >= $2 THEN 'refunded'::order_status ELSE status END,
+                    modified = $3
+                WHERE id = $4 AND status = 'paid' AND refunded_amount = $5
+                "#,
+                vec![
+                    amount.into(),
+                    total.into(),
+                    Local::now().naive_local().into(),
+                    order.id.into(),
+                    order.refunded_amount.into(),
+                ],
+            ))
+            .await
+            .with_context(|| format!("add_refunded({}) failed", order.id))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 用户最近一笔已履约的订单，升级时据此折算剩余时长的价值
+    pub async fn find_last_fulfilled<C: ConnectionTrait>(
+        db: &C,
+        user_id: i64,
+    ) -> anyhow::Result<Option<Model>> {
+        Entity::find()
+            .filter(
+                Column::UserId
+                    .eq(user_id)
+                    .and(Column::Status.eq(OrderStatus::Paid))
+                    .and(Column::FulfilledAt.is_not_null()),
+            )
+            .order_by_desc(Column::FulfilledAt)
+            .one(db)
+            .await
+            .with_context(|| format!("find_last_fulfilled({user_id}) failed"))
+    }
+
     pub async fn find_order_status<C: ConnectionTrait>(
         db: &C,
         order_id: i64,
@@ -88,11 +197,7 @@ impl Entity {
                 .expect("date subtract overflow")
         });
 
-        let monthly_amount = OrderLevel::Monthly.amount();
-        let annual_amount = OrderLevel::Annual.amount();
-
-        let sql = format!(
-            r#"
+        let sql = r#"
             WITH date_series AS (
                 SELECT generate_series(
                     $1::date,
@@ -104,11 +209,7 @@ impl Entity {
                 SELECT 
                     date_trunc('day', confirm) as day,
                     COUNT(*) as paid_count,
-                    SUM(CASE 
-                        WHEN level = 'monthly' THEN {monthly_amount}
-                        WHEN level = 'annual' THEN {annual_amount}
-                        ELSE 0
-                    END) as paid_amount
+                    SUM(amount) as paid_amount
                 FROM pay_order
                 WHERE status = 'paid' AND confirm IS NOT NULL
                   AND date_trunc('day', confirm) >= $1::date 
@@ -119,11 +220,7 @@ impl Entity {
                 SELECT 
                     date_trunc('day', created) as day,
                     COUNT(*) as pending_count,
-                    SUM(CASE 
-                        WHEN level = 'monthly' THEN {monthly_amount}
-                        WHEN level = 'annual' THEN {annual_amount}
-                        ELSE 0
-                    END) as pending_amount
+                    SUM(amount) as pending_amount
                 FROM pay_order
                 WHERE status = 'created'
                   AND date_trunc('day', created) >= $1::date 
@@ -140,8 +237,7 @@ impl Entity {
             LEFT JOIN paid_stats ON date_series.day = paid_stats.day
             LEFT JOIN pending_stats ON date_series.day = pending_stats.day
             ORDER BY date_series.day
-            "#,
-        );
+            "#;
 
         let stmt = Statement::from_sql_and_values(db_backend, sql, vec![start.into(), end.into()]);
 
@@ -161,16 +257,60 @@ impl OrderLevel {
         }
     }
 
-    pub fn amount(&self) -> i32 {
+    /// 会员时长（天）
+    pub fn days(&self) -> i64 {
+        match self {
+            OrderLevel::Monthly => 30,
+            OrderLevel::Annual => 365,
+        }
+    }
+}
+
+// 产品版本枚举扩展
+impl ProductEdition {
+    /// 版本高低，用于判断升级/降级
+    pub fn rank(&self) -> u8 {
+        match self {
+            ProductEdition::L0 => 0,
+            ProductEdition::L1 => 1,
+            ProductEdition::L2 => 2,
+            ProductEdition::L3 => 3,
+        }
+    }
+
+    /// 版本价格，单位：分。各付费版本目前同价
+    pub fn amount(&self, level: OrderLevel) -> i32 {
+        match (self, level) {
+            (ProductEdition::L0, _) => 0,
+            (_, OrderLevel::Monthly) => 2900, // 29元
+            (_, OrderLevel::Annual) => 26900, // 269元
+        }
+    }
+}
+
+// 支付来源枚举扩展
+impl PayFrom {
+    pub fn title(&self) -> &'static str {
         match self {
-            OrderLevel::Monthly => 2900, // 29元
-            OrderLevel::Annual => 26900, // 269元
+            PayFrom::Alipay => "支付宝",
+            PayFrom::Wechat => "微信支付",
+            PayFrom::Paddle => "Paddle",
+            PayFrom::Mock => "模拟支付",
         }
     }
 }
 
 // 订单状态枚举扩展
 impl OrderStatus {
+    pub fn title(&self) -> &'static str {
+        match self {
+            OrderStatus::Created => "待支付",
+            OrderStatus::Paid => "已支付",
+            OrderStatus::Closed => "已关闭",
+            OrderStatus::Refunded => "已退款",
+        }
+    }
+
     pub fn from_alipay(status: &str) -> Self {
         match status {
             "TRADE_SUCCESS" | "TRADE_FINISHED" => OrderStatus::Paid,
@@ -0,0 +1,20 @@
+use chrono::Local;
+use sea_orm::{ActiveModelBehavior, ActiveValue::Set, ConnectionTrait, DbErr};
+use summer::async_trait;
+
+// 重新导出实体
+pub use super::_entities::proxy::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        self.modified = Set(Local::now().naive_local());
+        Ok(self)
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{
+    sea_query::Expr, ActiveModelBehavior, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    EntityTrait, PaginatorTrait, QueryFilter,
+};
+use summer::async_trait;
+use summer_web::error::{KnownWebError, WebError};
+
+use super::scraper_task;
+
+// 重新导出实体
+pub use super::_entities::proxy_pool::*;
+
+/// `ScheduleData.proxy_id` 为0时使用系统默认代理
+pub const DEFAULT_PROXY_POOL: i32 = 0;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        self.modified = Set(Local::now().naive_local());
+        Ok(self)
+    }
+}
+
+impl Model {
+    /// 平台代理池所有用户可用，由管理员维护
+    pub fn is_platform(&self) -> bool {
+        self.user_id.is_none()
+    }
+}
+
+impl Entity {
+    /// 校验任务可以引用该代理池：系统默认、平台代理池或用户自己的代理池
+    pub async fn check_usable<C: ConnectionTrait>(
+        db: &C,
+        pool_id: i32,
+        user_id: i64,
+    ) -> Result<(), WebError> {
+        if pool_id == DEFAULT_PROXY_POOL {
+            return Ok(());
+        }
+        let pool = Entity::find_by_id(pool_id)
+            .one(db)
+            .await
+            .context("find proxy pool failed")?
+            .ok_or_else(|| KnownWebError::bad_request(format!("代理池{pool_id}不存在")))?;
+        if pool.user_id.is_some_and(|uid| uid != user_id) {
+            Err(KnownWebError::forbidden("无权使用该代理池"))?;
+        }
+        Ok(())
+    }
+
+    /// 查询可修改的代理池：用户自己的，或管理员修改平台代理池
+    pub async fn find_check_pool<C: ConnectionTrait>(
+        db: &C,
+        id: i32,
+        user_id: i64,
+        is_admin: bool,
+    ) -> Result<Model, WebError> {
+        let pool = Entity::find_by_id(id)
+            .one(db)
+            .await
+            .context("find proxy pool failed")?
+            .ok_or_else(|| KnownWebError::not_found("代理池不存在"))?;
+        let editable = match pool.user_id {
+            Some(uid) => uid == user_id,
+            None => is_admin,
+        };
+        if !editable {
+            Err(KnownWebError::forbidden("数据无权访问"))?;
+        }
+        Ok(pool)
+    }
+
+    /// 引用该代理池的未删除任务数
+    pub async fn count_tasks<C: ConnectionTrait>(db: &C, id: i32) -> Result<u64, WebError> {
+        Ok(scraper_task::Entity::find()
+            .filter(scraper_task::Column::Deleted.eq(false))
+            .filter(Expr::cust_with_values(
+                "(data->'schedule'->>'proxyId')::int = $1",
+                [id],
+            ))
+            .count(db)
+            .await
+            .context("count proxy pool tasks failed")?)
+    }
+}
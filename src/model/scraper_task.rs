@@ -1,15 +1,20 @@
 pub use super::_entities::scraper_task::*;
 
 use anyhow::Context;
-use chrono::Local;
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
 use schemars::JsonSchema;
 use sea_orm::{
     ActiveModelBehavior, ConnectionTrait, DbConn, DbErr, EntityTrait, FromJsonQueryResult, Set,
 };
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 use summer::async_trait;
 use summer_web::error::{KnownWebError, WebError};
 
+use super::sea_orm_active_enums::ProductEdition;
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum ScheduleType {
@@ -24,6 +29,97 @@ pub struct ScheduleData {
     pub proxy_id: i32,
     #[serde(rename = "type")]
     pub ty: ScheduleType,
+    /// IANA 时区，如 `Asia/Shanghai`；为空时按服务器本地时间触发
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+/// cron 表达式字段数（秒 分 时 日 月 周），与调度器一致
+const CRON_FIELDS: usize = 6;
+/// 估算最小调度间隔时采样的触发次数
+const SCHEDULE_INTERVAL_SAMPLES: usize = 100;
+
+impl ScheduleData {
+    /// 解析时区，未配置时返回 `None`（服务器本地时间）
+    pub fn tz(&self) -> Result<Option<Tz>, WebError> {
+        match self.timezone.as_deref().map(str::trim) {
+            None | Some("") => Ok(None),
+            Some(tz) => tz
+                .parse::<Tz>()
+                .map(Some)
+                .map_err(|_| KnownWebError::bad_request(format!("无效的时区: {tz}")).into()),
+        }
+    }
+
+    /// 校验并解析 cron 表达式
+    pub fn parse_cron(&self) -> Result<Cron, WebError> {
+        let cron = self.cron.trim();
+        if cron.split_whitespace().count() != CRON_FIELDS {
+            Err(KnownWebError::bad_request(
+                "cron 表达式须为6段：秒 分 时 日 月 周",
+            ))?;
+        }
+        Cron::from_str(cron)
+            .map_err(|e| KnownWebError::bad_request(format!("无效的 cron 表达式: {e}")).into())
+    }
+
+    /// 从 `after` 起按任务时区计算接下来 `count` 次触发时间
+    pub fn next_fire_times(
+        &self,
+        after: DateTime<Utc>,
+        count: usize,
+    ) -> Result<Vec<DateTime<FixedOffset>>, WebError> {
+        let cron = self.parse_cron()?;
+        match self.tz()? {
+            Some(tz) => fire_times(&cron, after.with_timezone(&tz), count),
+            None => fire_times(&cron, after.with_timezone(&Local), count),
+        }
+    }
+
+    /// 按接下来 `samples` 次触发估算相邻两次触发的最小间隔（秒），不足两次触发时返回 `None`
+    pub fn min_interval_seconds(
+        &self,
+        after: DateTime<Utc>,
+        samples: usize,
+    ) -> Result<Option<i64>, WebError> {
+        let times = self.next_fire_times(after, samples)?;
+        Ok(times.windows(2).map(|w| (w[1] - w[0]).num_seconds()).min())
+    }
+    /// 校验触发间隔不小于版本允许的下限
+    pub fn check_interval(&self, edition: ProductEdition) -> Result<(), WebError> {
+        let Some(interval) = self.min_interval_seconds(Utc::now(), SCHEDULE_INTERVAL_SAMPLES)?
+        else {
+            return Ok(());
+        };
+        let min_interval = edition.min_schedule_interval();
+        if interval < min_interval {
+            Err(KnownWebError::forbidden(format!(
+                "当前版本调度间隔不能小于{}分钟，请升级版本或调整 cron",
+                min_interval / 60
+            )))?;
+        }
+        Ok(())
+    }
+}
+
+fn fire_times<Z: TimeZone>(
+    cron: &Cron,
+    start: DateTime<Z>,
+    count: usize,
+) -> Result<Vec<DateTime<FixedOffset>>, WebError> {
+    let mut times = Vec::with_capacity(count);
+    let mut cursor = start;
+    while times.len() < count {
+        match cron.find_next_occurrence(&cursor, false) {
+            Ok(next) => {
+                times.push(next.fixed_offset());
+                cursor = next;
+            }
+            // 没有更多触发时间（如指定了已过去的日期）
+            Err(_) => break,
+        }
+    }
+    Ok(times)
 }
 
 /// 落库在 `scraper_task.data` 一列：调度 + 数据质量等任务级配置（信封结构）。
@@ -37,6 +133,126 @@ pub struct ScraperTaskData {
     pub schedule: Option<ScheduleData>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub data_quality: Option<DataQualityConfig>,
+    /// 实例失败后的自动重试策略，为空时不重试
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry: Option<RetryPolicy>,
+    /// 单个实例最长运行秒数，超时后标记为失败；为空时使用默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_seconds: Option<u32>,
+}
+
+/// 实例默认的最长运行时间（秒）
+pub const DEFAULT_INSTANCE_TIMEOUT: u32 = 6 * 3600;
+/// 可配置的实例运行时间范围（秒）
+const MIN_INSTANCE_TIMEOUT: u32 = 60;
+const MAX_INSTANCE_TIMEOUT: u32 = 24 * 3600;
+
+/// 单个任务最多重试次数
+const MAX_RETRY_ATTEMPTS: u32 = 5;
+/// 重试等待时间范围（秒）
+const MIN_RETRY_BACKOFF: u32 = 10;
+const MAX_RETRY_BACKOFF: u32 = 6 * 3600;
+const MAX_RETRY_ON: usize = 20;
+const MAX_RETRY_ON_LEN: usize = 100;
+
+/// 失败重试策略：按指数退避重新派发失败的实例
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, FromJsonQueryResult, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RetryPolicy {
+    /// 最多重试次数（不含首次执行）
+    pub max_attempts: u32,
+    /// 第一次重试前等待的秒数，之后每次翻倍
+    #[serde(default = "default_retry_backoff")]
+    pub backoff_seconds: u32,
+    /// 单次等待的上限（秒）
+    #[serde(default = "default_retry_max_backoff")]
+    pub max_backoff_seconds: u32,
+    /// 只有 `error_message` 包含其中任一片段（不区分大小写）时才重试，为空时重试所有失败
+    #[serde(default)]
+    pub retry_on: Vec<String>,
+}
+
+fn default_retry_backoff() -> u32 {
+    60
+}
+
+fn default_retry_max_backoff() -> u32 {
+    3600
+}
+
+impl RetryPolicy {
+    pub fn validate(&self) -> Result<(), WebError> {
+        if self.max_attempts == 0 || self.max_attempts > MAX_RETRY_ATTEMPTS {
+            Err(KnownWebError::bad_request(format!(
+                "重试次数须在1-{MAX_RETRY_ATTEMPTS}之间"
+            )))?;
+        }
+        if self.backoff_seconds < MIN_RETRY_BACKOFF
+            || self.max_backoff_seconds < self.backoff_seconds
+            || self.max_backoff_seconds > MAX_RETRY_BACKOFF
+        {
+            Err(KnownWebError::bad_request(format!(
+                "重试等待时间须在{MIN_RETRY_BACKOFF}-{MAX_RETRY_BACKOFF}秒之间，且上限不小于初始等待"
+            )))?;
+        }
+        if self.retry_on.len() > MAX_RETRY_ON
+            || self
+                .retry_on
+                .iter()
+                .any(|s| s.trim().is_empty() || s.chars().count() > MAX_RETRY_ON_LEN)
+        {
+            Err(KnownWebError::bad_request(format!(
+                "重试错误类型最多{MAX_RETRY_ON}个，每个1-{MAX_RETRY_ON_LEN}字符"
+            )))?;
+        }
+        Ok(())
+    }
+
+    /// 失败信息是否属于需要重试的错误类型
+    pub fn matches(&self, error_message: Option<&str>) -> bool {
+        if self.retry_on.is_empty() {
+            return true;
+        }
+        let Some(message) = error_message else {
+            return false;
+        };
+        let message = message.to_lowercase();
+        self.retry_on
+            .iter()
+            .any(|s| message.contains(&s.trim().to_lowercase()))
+    }
+
+    /// 第 `attempt` 次重试（从1开始）前等待的秒数
+    pub fn backoff(&self, attempt: u32) -> u32 {
+        let factor = 1u32
+            .checked_shl(attempt.saturating_sub(1))
+            .unwrap_or(u32::MAX);
+        self.backoff_seconds
+            .saturating_mul(factor)
+            .min(self.max_backoff_seconds)
+    }
+}
+
+impl ScraperTaskData {
+    /// 校验任务级配置中需要服务端检查的部分
+    pub fn validate(&self) -> Result<(), WebError> {
+        if let Some(retry) = &self.retry {
+            retry.validate()?;
+        }
+        if let Some(timeout) = self.timeout_seconds {
+            if !(MIN_INSTANCE_TIMEOUT..=MAX_INSTANCE_TIMEOUT).contains(&timeout) {
+                Err(KnownWebError::bad_request(format!(
+                    "运行超时时间须在{MIN_INSTANCE_TIMEOUT}-{MAX_INSTANCE_TIMEOUT}秒之间"
+                )))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 实例最长运行时间（秒）
+    pub fn instance_timeout(&self) -> u32 {
+        self.timeout_seconds.unwrap_or(DEFAULT_INSTANCE_TIMEOUT)
+    }
 }
 
 /// 数据质量 / 去重等与调度无关的配置；随 `dedupe_rule_version` 变更可换规则而不污染历史行语义。
@@ -83,6 +299,16 @@ pub fn apply_data_quality_dedupe_version(
     }
 }
 
+impl Model {
+    /// 运行时使用的调度类型，未配置调度的任务按 `Fast` 运行
+    pub fn schedule_type(&self) -> ScheduleType {
+        self.data
+            .as_ref()
+            .and_then(|d| d.schedule.as_ref())
+            .map_or(ScheduleType::Fast, |s| s.ty.clone())
+    }
+}
+
 #[async_trait]
 impl ActiveModelBehavior for ActiveModel {
     async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
@@ -99,7 +325,7 @@ impl ActiveModelBehavior for ActiveModel {
 }
 
 #[cfg(test)]
-mod dedupe_version_tests {
+mod tests {
     use super::*;
 
     fn dq(paths: &[&str], ver: u32) -> DataQualityConfig {
@@ -113,6 +339,8 @@ mod dedupe_version_tests {
         ScraperTaskData {
             schedule: None,
             data_quality: Some(dq(paths, ver)),
+            retry: None,
+            timeout_seconds: None,
         }
     }
 
@@ -145,6 +373,105 @@ mod dedupe_version_tests {
         apply_data_quality_dedupe_version(None, &mut incoming);
         assert_eq!(incoming.dedupe_rule_version, 0);
     }
+
+    fn schedule(cron: &str, timezone: Option<&str>) -> ScheduleData {
+        ScheduleData {
+            cron: cron.to_string(),
+            proxy_id: 0,
+            ty: ScheduleType::Fast,
+            timezone: timezone.map(str::to_string),
+        }
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn rejects_five_field_cron() {
+        assert!(schedule("0 * * * *", None).parse_cron().is_err());
+        assert!(schedule("0 0 * * * *", None).parse_cron().is_ok());
+    }
+
+    #[test]
+    fn rejects_unknown_timezone() {
+        assert!(schedule("0 0 * * * *", Some("Mars/Olympus")).tz().is_err());
+        assert!(schedule("0 0 * * * *", Some("Asia/Shanghai")).tz().is_ok());
+    }
+
+    #[test]
+    fn fire_times_follow_timezone() {
+        // 上海时间每天 9 点 = UTC 1 点
+        let s = schedule("0 0 9 * * *", Some("Asia/Shanghai"));
+        let times = s.next_fire_times(utc("2024-01-01T00:00:00Z"), 2).unwrap();
+        assert_eq!(times.len(), 2);
+        assert_eq!(times[0].with_timezone(&Utc), utc("2024-01-01T01:00:00Z"));
+        assert_eq!(times[1].with_timezone(&Utc), utc("2024-01-02T01:00:00Z"));
+    }
+
+    #[test]
+    fn min_interval_uses_closest_fires() {
+        let s = schedule("0 0,5 * * * *", Some("UTC"));
+        let min = s
+            .min_interval_seconds(utc("2024-01-01T00:00:00Z"), 10)
+            .unwrap();
+        assert_eq!(min, Some(300));
+    }
+
+    fn policy(retry_on: &[&str]) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            backoff_seconds: 60,
+            max_backoff_seconds: 200,
+            retry_on: retry_on.iter().map(|s| (*s).to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_until_cap() {
+        let p = policy(&[]);
+        assert_eq!(p.backoff(1), 60);
+        assert_eq!(p.backoff(2), 120);
+        assert_eq!(p.backoff(3), 200);
+        assert_eq!(p.backoff(64), 200);
+    }
+
+    #[test]
+    fn empty_retry_on_matches_all_failures() {
+        let p = policy(&[]);
+        assert!(p.matches(None));
+        assert!(p.matches(Some("anything")));
+    }
+
+    #[test]
+    fn retry_on_matches_case_insensitive_fragment() {
+        let p = policy(&["Timeout", "net::ERR_"]);
+        assert!(p.matches(Some("navigation TIMEOUT after 30s")));
+        assert!(p.matches(Some("net::err_connection_reset")));
+        assert!(!p.matches(Some("selector not found")));
+        assert!(!p.matches(None));
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range() {
+        assert!(policy(&[]).validate().is_ok());
+        let mut p = policy(&[]);
+        p.max_attempts = 0;
+        assert!(p.validate().is_err());
+        let mut p = policy(&[]);
+        p.max_backoff_seconds = 30;
+        assert!(p.validate().is_err());
+        assert!(policy(&[" "]).validate().is_err());
+    }
+
+    #[test]
+    fn deserialize_with_defaults() {
+        let data: ScraperTaskData = serde_json::from_str(r#"{"retry":{"maxAttempts":2}}"#).unwrap();
+        let retry = data.retry.unwrap();
+        assert_eq!(retry.backoff_seconds, 60);
+        assert_eq!(retry.max_backoff_seconds, 3600);
+        assert!(retry.retry_on.is_empty());
+    }
 }
 
 impl Entity {
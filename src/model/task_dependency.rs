@@ -0,0 +1,83 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{
+    ActiveModelBehavior, ActiveValue::Set, ConnectionTrait, DbErr, FromQueryResult, Statement,
+};
+use summer::async_trait;
+
+// 重新导出实体
+pub use super::_entities::task_dependency::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        Ok(self)
+    }
+}
+
+impl Entity {
+    /// 新增 `upstream -> downstream` 后是否成环：从下游出发沿已有依赖能否回到上游
+    pub async fn creates_cycle<C: ConnectionTrait>(
+        db: &C,
+        upstream_task_id: i64,
+        downstream_task_id: i64,
+    ) -> anyhow::Result<bool> {
+        if upstream_task_id == downstream_task_id {
+            return Ok(true);
+        }
+        let sql = r#"
+            WITH RECURSIVE reach(task_id) AS (
+                SELECT $1::bigint
+                UNION
+                SELECT d.downstream_task_id
+                FROM task_dependency d JOIN reach r ON d.upstream_task_id = r.task_id
+            )
+            SELECT EXISTS(SELECT 1 FROM reach WHERE task_id = $2) AS cycle
+        "#;
+        let cycle = db
+            .query_one_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                sql,
+                [downstream_task_id.into(), upstream_task_id.into()],
+            ))
+            .await
+            .context("check task dependency cycle failed")?
+            .and_then(|row| row.try_get::<bool>("", "cycle").ok())
+            .unwrap_or(false);
+        Ok(cycle)
+    }
+
+    /// 任务所在依赖链上的全部依赖（上下游双向展开）
+    pub async fn find_chain<C: ConnectionTrait>(
+        db: &C,
+        task_id: i64,
+    ) -> anyhow::Result<Vec<Model>> {
+        let sql = r#"
+            WITH RECURSIVE reach(task_id) AS (
+                SELECT $1::bigint
+                UNION
+                SELECT CASE WHEN d.upstream_task_id = r.task_id
+                            THEN d.downstream_task_id ELSE d.upstream_task_id END
+                FROM task_dependency d
+                JOIN reach r ON d.upstream_task_id = r.task_id OR d.downstream_task_id = r.task_id
+            )
+            SELECT * FROM task_dependency
+            WHERE upstream_task_id IN (SELECT task_id FROM reach)
+            ORDER BY id
+        "#;
+        Model::find_by_statement(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            sql,
+            [task_id.into()],
+        ))
+        .all(db)
+        .await
+        .with_context(|| format!("find task#{task_id} dependency chain failed"))
+    }
+}
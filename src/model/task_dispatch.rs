@@ -0,0 +1,45 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{
+    ActiveModelBehavior, ActiveValue::Set, ConnectionTrait, DbErr, FromQueryResult, Statement,
+};
+use summer::async_trait;
+
+// 重新导出实体
+pub use super::_entities::task_dispatch::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        Ok(self)
+    }
+}
+
+impl Entity {
+    /// 取消尚未被实例认领的派发，返回被取消的记录
+    pub async fn cancel_pending<C: ConnectionTrait>(
+        db: &C,
+        task_id: i64,
+        job_id: &str,
+    ) -> anyhow::Result<Option<Model>> {
+        let sql = r#"
+            UPDATE task_dispatch SET cancelled = true
+            WHERE task_id = $1 AND job_id = $2 AND instance_id IS NULL AND cancelled = false
+            RETURNING *
+        "#;
+        Model::find_by_statement(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            sql,
+            [task_id.into(), job_id.into()],
+        ))
+        .one(db)
+        .await
+        .with_context(|| format!("cancel task#{task_id} dispatch {job_id} failed"))
+    }
+}
@@ -1,7 +1,13 @@
 pub use super::_entities::task_instance::*;
 
-use chrono::Local;
-use sea_orm::{ActiveModelBehavior, ConnectionTrait, DbErr, Set};
+use anyhow::Context;
+use chrono::{Local, NaiveDateTime};
+use sea_orm::{
+    ActiveModelBehavior, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, FromQueryResult,
+    QueryFilter, QueryOrder, QuerySelect, Set, Statement,
+};
+
+use super::sea_orm_active_enums::InstanceStatus;
 use summer::async_trait;
 
 #[async_trait]
@@ -17,3 +23,187 @@ impl ActiveModelBehavior for ActiveModel {
         Ok(self)
     }
 }
+
+impl Entity {
+    /// 认领 `after` 之后结束且尚未处理下游触发的实例，多实例部署时同一时间只有一方处理
+    pub async fn claim_finished<C: ConnectionTrait>(
+        db: &C,
+        after: NaiveDateTime,
+        limit: u64,
+    ) -> anyhow::Result<Vec<Model>> {
+        let sql = r#"
+            UPDATE task_instance SET chain_dispatched = true
+            WHERE id IN (
+                SELECT id FROM task_instance
+                WHERE chain_dispatched = false AND status <> 'running' AND modified > $1
+                ORDER BY modified
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING *
+        "#;
+        Model::find_by_statement(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            sql,
+            [after.into(), (limit as i64).into()],
+        ))
+        .all(db)
+        .await
+        .context("claim finished task instances failed")
+    }
+
+    /// 撤销下游触发的认领，部分下游触发失败时由下一轮重新处理
+    pub async fn release_chain<C: ConnectionTrait>(db: &C, id: i64) -> anyhow::Result<()> {
+        db.execute_raw(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            "UPDATE task_instance SET chain_dispatched = false WHERE id = $1",
+            [id.into()],
+        ))
+        .await
+        .with_context(|| format!("release task instance#{id} chain failed"))?;
+        Ok(())
+    }
+
+    /// 查询 `after` 之后失败且尚未判定是否重试的实例，最早失败的在前
+    pub async fn find_retry_pending<C: ConnectionTrait>(
+        db: &C,
+        after: NaiveDateTime,
+        limit: u64,
+    ) -> anyhow::Result<Vec<Model>> {
+        Entity::find()
+            .filter(Column::Status.eq(InstanceStatus::Failed))
+            .filter(Column::RetryChecked.eq(false))
+            .filter(Column::Modified.gt(after))
+            .order_by_asc(Column::Modified)
+            .limit(limit)
+            .all(db)
+            .await
+            .context("query retry pending task instances failed")
+    }
+
+    /// 标记实例已判定重试，返回是否由本次调用标记（多实例部署时只有一方成功）
+    pub async fn mark_retry_checked<C: ConnectionTrait>(db: &C, id: i64) -> anyhow::Result<bool> {
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "UPDATE task_instance SET retry_checked = true WHERE id = $1 AND retry_checked = false",
+                [id.into()],
+            ))
+            .await
+            .with_context(|| format!("mark task instance#{id} retry checked failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 撤销重试判定标记，重试派发失败时由下一轮重新判定
+    pub async fn release_retry_checked<C: ConnectionTrait>(db: &C, id: i64) -> anyhow::Result<()> {
+        db.execute_raw(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            "UPDATE task_instance SET retry_checked = false WHERE id = $1",
+            [id.into()],
+        ))
+        .await
+        .with_context(|| format!("release task instance#{id} retry check failed"))?;
+        Ok(())
+    }
+
+    pub async fn find_running<C: ConnectionTrait>(db: &C) -> anyhow::Result<Vec<Model>> {
+        Entity::find()
+            .filter(Column::Status.eq(InstanceStatus::Running))
+            .all(db)
+            .await
+            .context("query running task instances failed")
+    }
+
+    /// 把仍在运行的实例标记为失败，返回是否由本次调用标记
+    pub async fn fail_if_running<C: ConnectionTrait>(
+        db: &C,
+        id: i64,
+        error_message: &str,
+    ) -> anyhow::Result<bool> {
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "UPDATE task_instance SET status = 'failed', error_message = $1, modified = $2 WHERE id = $3 AND status = 'running'",
+                [
+                    error_message.into(),
+                    Local::now().naive_local().into(),
+                    id.into(),
+                ],
+            ))
+            .await
+            .with_context(|| format!("mark task instance#{id} failed failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 取消仍在运行的实例，返回是否由本次调用取消
+    pub async fn cancel_if_running<C: ConnectionTrait>(db: &C, id: i64) -> anyhow::Result<bool> {
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "UPDATE task_instance SET status = 'cancelled', error_message = $1, modified = $2 WHERE id = $3 AND status = 'running'",
+                [
+                    "用户取消运行".into(),
+                    Local::now().naive_local().into(),
+                    id.into(),
+                ],
+            ))
+            .await
+            .with_context(|| format!("cancel task instance#{id} failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// worker 上报进度并刷新心跳，实例已结束或不属于该 worker 时返回 `false`
+    pub async fn heartbeat<C: ConnectionTrait>(
+        db: &C,
+        id: i64,
+        worker_id: i64,
+        pages: i32,
+        records: i32,
+    ) -> anyhow::Result<bool> {
+        let now = Local::now().naive_local();
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "UPDATE task_instance SET heartbeat = $1, modified = $1, pages = $2, data_count = $3 WHERE id = $4 AND worker_id = $5 AND status = 'running'",
+                [
+                    now.into(),
+                    pages.into(),
+                    records.into(),
+                    id.into(),
+                    worker_id.into(),
+                ],
+            ))
+            .await
+            .with_context(|| format!("update task instance#{id} heartbeat failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// worker 结束实例，实例已结束或不属于该 worker 时返回 `false`
+    pub async fn finish<C: ConnectionTrait>(
+        db: &C,
+        id: i64,
+        worker_id: i64,
+        status: InstanceStatus,
+        data_count: i32,
+        log_key: Option<String>,
+        error_message: Option<String>,
+    ) -> anyhow::Result<bool> {
+        let result = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "UPDATE task_instance SET status = CAST($1 AS instance_status), data_count = $2, log_key = $3, error_message = $4, modified = $5 WHERE id = $6 AND worker_id = $7 AND status = 'running'",
+                [
+                    status.into(),
+                    data_count.into(),
+                    log_key.into(),
+                    error_message.into(),
+                    Local::now().naive_local().into(),
+                    id.into(),
+                    worker_id.into(),
+                ],
+            ))
+            .await
+            .with_context(|| format!("finish task instance#{id} failed"))?;
+        Ok(result.rows_affected() > 0)
+    }
+}
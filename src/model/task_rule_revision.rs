@@ -0,0 +1,108 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait, DbErr,
+    EntityTrait, QueryFilter, Statement,
+};
+use serde_json::Value;
+use summer::async_trait;
+use summer_web::error::{KnownWebError, WebError};
+
+use super::scraper_task;
+
+// 重新导出实体
+pub use super::_entities::task_rule_revision::*;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        Ok(self)
+    }
+}
+
+impl Entity {
+    /// 保存任务的新规则并记录修订，规则未变化时返回 `None`。
+    ///
+    /// 从未修改过规则的任务（`rule_revision = 0`）先把当前规则补记为修订1。
+    /// 以 `rule_revision` 做乐观锁，并发修改时后提交的一方失败。
+    pub async fn save_rule<C: ConnectionTrait>(
+        db: &C,
+        task: &scraper_task::Model,
+        rule: Value,
+        author_id: i64,
+        comment: Option<String>,
+    ) -> Result<Option<Model>, WebError> {
+        if task.rule == rule {
+            return Ok(None);
+        }
+
+        let mut revision = task.rule_revision;
+        let updated = db
+            .execute_raw(Statement::from_sql_and_values(
+                db.get_database_backend(),
+                "UPDATE scraper_task SET rule = $1, rule_revision = $2, modified = $3 WHERE id = $4 AND rule_revision = $5",
+                vec![
+                    rule.clone().into(),
+                    (revision.max(1) + 1).into(),
+                    Local::now().naive_local().into(),
+                    task.id.into(),
+                    revision.into(),
+                ],
+            ))
+            .await
+            .with_context(|| format!("update task#{} rule failed", task.id))?;
+        if updated.rows_affected() == 0 {
+            Err(KnownWebError::bad_request(
+                "规则已被其他人修改，请刷新后重试",
+            ))?;
+        }
+
+        if revision == 0 {
+            ActiveModel {
+                task_id: Set(task.id),
+                revision: Set(1),
+                rule: Set(task.rule.clone()),
+                author_id: Set(task.user_id),
+                comment: Set(Some("初始版本".to_string())),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+            .context("save initial rule revision failed")?;
+            revision = 1;
+        }
+
+        let model = ActiveModel {
+            task_id: Set(task.id),
+            revision: Set(revision + 1),
+            rule: Set(rule),
+            author_id: Set(author_id),
+            comment: Set(comment),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .context("save rule revision failed")?;
+        Ok(Some(model))
+    }
+
+    pub async fn find_revision<C: ConnectionTrait>(
+        db: &C,
+        task_id: i64,
+        revision: i32,
+    ) -> Result<Model, WebError> {
+        Ok(Entity::find()
+            .filter(Column::TaskId.eq(task_id))
+            .filter(Column::Revision.eq(revision))
+            .one(db)
+            .await
+            .context("find rule revision failed")?
+            .ok_or_else(|| KnownWebError::not_found(format!("修订{revision}不存在")))?)
+    }
+}
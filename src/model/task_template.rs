@@ -1,8 +1,13 @@
 pub use super::_entities::task_template::*;
 
 use chrono::Local;
+use schemars::JsonSchema;
 use sea_orm::{ActiveModelBehavior, ConnectionTrait, DbErr, Set, Statement};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashSet;
 use summer::async_trait;
+use summer_web::error::{KnownWebError, WebError};
 
 #[async_trait]
 impl ActiveModelBehavior for ActiveModel {
@@ -47,3 +52,211 @@ impl Entity {
         Ok(result.rows_affected())
     }
 }
+
+/// 模板参数类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateParamType {
+    #[default]
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Url,
+}
+
+/// 模板参数定义，`task_template.params` 为该结构的数组。
+///
+/// 规则中的字符串通过 `{{name}}` 引用参数：整个字符串就是占位符时替换为参数的 JSON 值（保留数字/布尔类型），
+/// 否则按文本插值。
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateParam {
+    pub name: String,
+    /// 展示名称
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(rename = "type", default)]
+    pub ty: TemplateParamType,
+    #[serde(default)]
+    pub required: bool,
+    #[serde(default)]
+    pub default: Option<Value>,
+    /// 可选值，为空时不限制
+    #[serde(default)]
+    pub options: Vec<Value>,
+}
+
+impl TemplateParam {
+    fn check(&self, value: &Value) -> Result<(), WebError> {
+        let ok = match self.ty {
+            TemplateParamType::String => value.is_string(),
+            TemplateParamType::Integer => value.is_i64() || value.is_u64(),
+            TemplateParamType::Number => value.is_number(),
+            TemplateParamType::Boolean => value.is_boolean(),
+            TemplateParamType::Url => value
+                .as_str()
+                .is_some_and(|s| s.starts_with("http://") || s.starts_with("https://")),
+        };
+        if !ok {
+            Err(KnownWebError::bad_request(format!(
+                "参数 {} 的类型应为 {:?}",
+                self.name, self.ty
+            )))?;
+        }
+        if !self.options.is_empty() && !self.options.contains(value) {
+            Err(KnownWebError::bad_request(format!(
+                "参数 {} 的取值不在可选范围内",
+                self.name
+            )))?;
+        }
+        Ok(())
+    }
+}
+
+impl Model {
+    /// 解析模板的参数定义
+    pub fn param_defs(&self) -> Result<Vec<TemplateParam>, WebError> {
+        match &self.params {
+            None | Some(Value::Null) => Ok(vec![]),
+            Some(params) => Ok(serde_json::from_value(params.clone()).map_err(|e| {
+                KnownWebError::internal_server_error(format!("模板参数定义无效: {e}"))
+            })?),
+        }
+    }
+}
+
+/// 按参数定义校验传入的参数值，补齐默认值
+pub fn resolve_params(
+    defs: &[TemplateParam],
+    mut values: Map<String, Value>,
+) -> Result<Map<String, Value>, WebError> {
+    let names: HashSet<&str> = defs.iter().map(|d| d.name.as_str()).collect();
+    if let Some(unknown) = values.keys().find(|k| !names.contains(k.as_str())) {
+        Err(KnownWebError::bad_request(format!("未知参数: {unknown}")))?;
+    }
+
+    let mut resolved = Map::new();
+    for def in defs {
+        let value = match values.remove(&def.name) {
+            Some(Value::Null) | None => def.default.clone(),
+            Some(value) => Some(value),
+        };
+        match value {
+            Some(value) => {
+                def.check(&value)?;
+                resolved.insert(def.name.clone(), value);
+            }
+            None if def.required => {
+                Err(KnownWebError::bad_request(format!(
+                    "缺少参数: {}",
+                    def.name
+                )))?;
+            }
+            None => {}
+        }
+    }
+    Ok(resolved)
+}
+
+/// 把参数代入规则中的 `{{name}}` 占位符，未提供值的占位符保持原样
+pub fn substitute_params(rule: &Value, params: &Map<String, Value>) -> Value {
+    match rule {
+        Value::String(s) => substitute_str(s, params),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| substitute_params(item, params))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), substitute_params(v, params)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn substitute_str(s: &str, params: &Map<String, Value>) -> Value {
+    if let Some(name) = s
+        .strip_prefix("{{")
+        .and_then(|rest| rest.strip_suffix("}}"))
+        .map(str::trim)
+    {
+        if let Some(value) = params.get(name) {
+            return value.clone();
+        }
+    }
+
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let name = rest[start + 2..start + 2 + len].trim();
+        out.push_str(&rest[..start]);
+        match params.get(name) {
+            Some(Value::String(v)) => out.push_str(v),
+            Some(v) => out.push_str(&v.to_string()),
+            None => out.push_str(&rest[start..start + 4 + len]),
+        }
+        rest = &rest[start + 4 + len..];
+    }
+    out.push_str(rest);
+    Value::String(out)
+}
+
+#[cfg(test)]
+mod param_tests {
+    use super::*;
+    use serde_json::json;
+
+    fn defs() -> Vec<TemplateParam> {
+        serde_json::from_value(json!([
+            {"name": "keyword", "type": "string", "required": true},
+            {"name": "pages", "type": "integer", "default": 3},
+            {"name": "site", "type": "string", "options": ["cn", "com"], "default": "cn"}
+        ]))
+        .unwrap()
+    }
+
+    fn values(v: Value) -> Map<String, Value> {
+        v.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn fills_defaults_and_rejects_missing() {
+        let resolved = resolve_params(&defs(), values(json!({"keyword": "手机"}))).unwrap();
+        assert_eq!(resolved["pages"], json!(3));
+        assert_eq!(resolved["site"], json!("cn"));
+        assert!(resolve_params(&defs(), Map::new()).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_wrong_type_and_option() {
+        let d = defs();
+        assert!(resolve_params(&d, values(json!({"keyword": "a", "foo": 1}))).is_err());
+        assert!(resolve_params(&d, values(json!({"keyword": "a", "pages": "3"}))).is_err());
+        assert!(resolve_params(&d, values(json!({"keyword": "a", "site": "de"}))).is_err());
+    }
+
+    #[test]
+    fn substitutes_typed_and_inline_placeholders() {
+        let params = values(json!({"keyword": "手机", "pages": 5}));
+        let rule = json!({
+            "url": "https://example.{{site}}/s?q={{keyword}}",
+            "maxPages": "{{ pages }}",
+            "steps": [{"text": "{{keyword}}"}]
+        });
+        assert_eq!(
+            substitute_params(&rule, &params),
+            json!({
+                "url": "https://example.{{site}}/s?q=手机",
+                "maxPages": 5,
+                "steps": [{"text": "手机"}]
+            })
+        );
+    }
+}
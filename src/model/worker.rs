@@ -0,0 +1,59 @@
+use anyhow::Context;
+use chrono::Local;
+use sea_orm::{ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ConnectionTrait, DbErr};
+use sha2::{Digest, Sha256};
+use summer::async_trait;
+
+use crate::utils::rand::rand_alphanumeric;
+
+// 重新导出实体
+pub use super::_entities::worker::*;
+
+const WORKER_SECRET_LEN: usize = 40;
+
+#[async_trait]
+impl ActiveModelBehavior for ActiveModel {
+    async fn before_save<C>(mut self, _db: &C, insert: bool) -> Result<Self, DbErr>
+    where
+        C: ConnectionTrait,
+    {
+        if insert {
+            self.created = Set(Local::now().naive_local());
+        }
+        Ok(self)
+    }
+}
+
+/// 生成 worker 密钥，明文只在创建时返回一次
+pub fn gen_secret() -> String {
+    rand_alphanumeric(WORKER_SECRET_LEN)
+}
+
+pub fn hash_secret(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+impl Model {
+    pub fn verify_secret(&self, secret: &str) -> bool {
+        self.secret_hash == hash_secret(secret)
+    }
+
+    /// 密钥指纹，写入 worker 令牌用于在重置密钥后吊销旧令牌
+    pub fn secret_version(&self) -> String {
+        self.secret_hash.chars().take(16).collect()
+    }
+}
+
+impl Entity {
+    pub async fn touch<C: ConnectionTrait>(db: &C, id: i64) -> anyhow::Result<()> {
+        ActiveModel {
+            id: Set(id),
+            last_seen: Set(Some(Local::now().naive_local())),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+        .with_context(|| format!("update worker#{id} last seen failed"))?;
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
-use crate::config::pay::PayConfig;
-use alipay_sdk_rust::pay::{PayClient, Payer};
-use derive_more::derive::Deref;
+use crate::{
+    config::pay::PayConfig,
+    service::pay::{
+        AlipayProvider, MockProvider, PaddleProvider, PaymentProvider, PaymentProviders,
+        WechatProvider,
+    },
+};
+use alipay_sdk_rust::pay::PayClient;
 use reqwest::Client;
 use std::sync::Arc;
 use summer::{
@@ -8,6 +13,7 @@ use summer::{
     async_trait,
     config::ConfigRegistry,
     plugin::{MutableComponentRegistry, Plugin},
+    tracing,
 };
 use wechat_pay_rust_sdk::pay::WechatPay;
 
@@ -17,6 +23,7 @@ pub struct PayPlugin;
 impl Plugin for PayPlugin {
     async fn build(&self, app: &mut AppBuilder) {
         let conf = app.get_config::<PayConfig>().expect("支付配置获取失败");
+        let mut providers: Vec<Arc<dyn PaymentProvider>> = vec![];
 
         if conf.alipay_enable {
             let alipay = PayClient::builder()
@@ -26,6 +33,7 @@ impl Plugin for PayPlugin {
                 .alipay_public_key(&conf.alipay_public_key)
                 .app_cert_sn(&conf.alipay_app_cert_sn)
                 .notify_url(&conf.alipay_callback_url)
+                .return_url(&conf.return_url)
                 .charset_utf8()
                 .format_json()
                 .private_key(&conf.alipay_app_private_key)
@@ -35,23 +43,26 @@ impl Plugin for PayPlugin {
                 .build()
                 .expect("build alipay client failed");
 
-            app.add_component(Alipay(Arc::new(alipay)));
+            providers.push(Arc::new(AlipayProvider::new(alipay)));
         }
 
         if conf.wechat_pay_enable {
             let wechat_pay = WechatPay::from_env();
-            app.add_component(WechatPayClient(Arc::new(wechat_pay)));
+            providers.push(Arc::new(WechatProvider::new(
+                wechat_pay,
+                conf.return_url.clone(),
+            )));
         }
 
-        app.add_component(PaddleClient(Client::new()));
-    }
-}
-
-#[derive(Clone, Deref)]
-pub struct Alipay(Arc<dyn Payer + Send + Sync>);
+        if conf.paddle_enable {
+            providers.push(Arc::new(PaddleProvider::new(Client::new(), conf.clone())));
+        }
 
-#[derive(Clone, Deref)]
-pub struct WechatPayClient(Arc<WechatPay>);
+        if conf.mock_enable {
+            tracing::warn!("模拟支付渠道已启用，请勿在生产环境开启");
+            providers.push(Arc::new(MockProvider));
+        }
 
-#[derive(Clone, Deref)]
-pub struct PaddleClient(Client);
+        app.add_component(PaymentProviders::new(providers));
+    }
+}
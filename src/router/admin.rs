@@ -1,13 +1,16 @@
 use crate::{
     config::mail::Email,
     model::{
-        account_user,
+        account_user, membership_history,
         prelude::*,
         scraper_task,
-        sea_orm_active_enums::{CreditOperation, ProductEdition},
-        task_template,
+        sea_orm_active_enums::{
+            CreditOperation, MembershipChangeReason, ProductEdition, ScheduleState,
+        },
+        task_template, worker,
     },
     service::credit::CreditService,
+    task::{pause_schedule, resume_schedule},
     utils::{
         jwt::{self, AdminClaims},
         mail,
@@ -16,20 +19,23 @@ use crate::{
 };
 
 pub(crate) mod marketing;
+mod pay;
 use anyhow::Context;
 use axum_valid::Valid;
+use chrono::Local;
 use sea_orm::{
     ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, DbConn, EntityTrait, ExprTrait,
-    QueryFilter, Set,
+    QueryFilter, QueryOrder, Set,
 };
 use std::collections::HashSet;
+use summer_job::JobScheduler;
 use summer_mail::Mailer;
 use summer_sea_orm::pagination::{Page, Pagination, PaginationExt};
 use summer_web::{
     axum::Json,
     delete,
     error::{KnownWebError, Result},
-    extractor::{Component, Config, Path, Query},
+    extractor::{AppRef, Component, Config, Path, Query},
     get, post, put,
 };
 
@@ -249,10 +255,22 @@ async fn update_user_edition(
     let old_edition = user.edition.clone();
     let new_edition = req.edition.clone();
 
-    // 更新用户版本等级
+    // 会员已到期时必须指定新的到期时间，否则会被立即降级
+    let now = Local::now().naive_local();
+    if req.vip_expired_at.is_some_and(|t| t <= now) {
+        return Err(KnownWebError::bad_request("到期时间必须晚于当前时间"))?;
+    }
+    let lapsed = user.vip_expired_at.is_some_and(|t| t <= now);
+    if new_edition != ProductEdition::L0 && lapsed && req.vip_expired_at.is_none() {
+        return Err(KnownWebError::bad_request("会员已到期，请指定到期时间"))?;
+    }
     let updated_user = account_user::ActiveModel {
         id: Set(user.id),
         edition: Set(new_edition.clone()),
+        vip_expired_at: match req.vip_expired_at {
+            Some(t) => Set(Some(t)),
+            None => NotSet,
+        },
         ..Default::default()
     }
     .update(&db)
@@ -276,9 +294,36 @@ async fn update_user_edition(
     .await
     .context("记录版本等级变更日志失败")?;
 
+    MembershipHistory::record(
+        &db,
+        &user,
+        &updated_user,
+        MembershipChangeReason::Admin,
+        None,
+        Some(req.description),
+    )
+    .await
+    .context("记录会员变更历史失败")?;
+
     Ok(Json(UserResp::from(updated_user)))
 }
 
+/// 用户会员变更历史
+#[get("/admin/user/{id}/membership-history")]
+async fn membership_history(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<Vec<membership_history::Model>>> {
+    let history = MembershipHistory::find()
+        .filter(membership_history::Column::UserId.eq(id))
+        .order_by_desc(membership_history::Column::Created)
+        .all(&db)
+        .await
+        .context("查询会员变更历史失败")?;
+    Ok(Json(history))
+}
+
 /// 发送营销邮件（HTML，仅发给仍订阅的用户）
 #[post("/admin/user/send-marketing-email")]
 async fn send_marketing_email(
@@ -370,7 +415,13 @@ async fn get_task_list(
             "running" => filter.and(
                 scraper_task::Column::Data
                     .is_not_null()
-                    .and(scraper_task::Column::Deleted.eq(false)),
+                    .and(scraper_task::Column::Deleted.eq(false))
+                    .and(scraper_task::Column::ScheduleState.eq(ScheduleState::Active)),
+            ),
+            "paused" => filter.and(
+                scraper_task::Column::Deleted
+                    .eq(false)
+                    .and(scraper_task::Column::ScheduleState.eq(ScheduleState::Paused)),
             ),
             "completed" => filter.and(scraper_task::Column::Deleted.eq(true)),
             "deleted" => filter.and(scraper_task::Column::Deleted.eq(true)),
@@ -458,12 +509,14 @@ async fn delete_task(
     Ok(Json(true))
 }
 
-/// 启动任务
+/// 启动任务：恢复已删除或已暂停任务的调度
 #[post("/admin/task/{id}/start")]
 async fn start_task(
     _admin: AdminClaims,
     Path(id): Path<i64>,
     Component(db): Component<DbConn>,
+    Component(sched): Component<JobScheduler>,
+    AppRef(app): AppRef,
 ) -> Result<Json<bool>> {
     let task = ScraperTask::find_by_id(id)
         .one(&db)
@@ -471,24 +524,30 @@ async fn start_task(
         .context("find task failed")?
         .ok_or_else(|| KnownWebError::not_found("任务不存在"))?;
 
-    scraper_task::ActiveModel {
-        id: Set(task.id),
-        deleted: Set(false),
-        ..Default::default()
+    if task.deleted {
+        scraper_task::ActiveModel {
+            id: Set(task.id),
+            deleted: Set(false),
+            ..Default::default()
+        }
+        .update(&db)
+        .await
+        .context("start task failed")?;
+    }
+    if task.deleted || task.schedule_state == ScheduleState::Paused {
+        resume_schedule(&db, &sched, app, &task).await?;
     }
-    .update(&db)
-    .await
-    .context("start task failed")?;
 
     Ok(Json(true))
 }
 
-/// 停止任务
+/// 停止任务：暂停调度，不再软删除任务
 #[post("/admin/task/{id}/stop")]
 async fn stop_task(
     _admin: AdminClaims,
     Path(id): Path<i64>,
     Component(db): Component<DbConn>,
+    Component(sched): Component<JobScheduler>,
 ) -> Result<Json<bool>> {
     let task = ScraperTask::find_by_id(id)
         .one(&db)
@@ -496,14 +555,7 @@ async fn stop_task(
         .context("find task failed")?
         .ok_or_else(|| KnownWebError::not_found("任务不存在"))?;
 
-    scraper_task::ActiveModel {
-        id: Set(task.id),
-        deleted: Set(true),
-        ..Default::default()
-    }
-    .update(&db)
-    .await
-    .context("stop task failed")?;
+    pause_schedule(&db, &sched, &task).await?;
 
     Ok(Json(true))
 }
@@ -634,7 +686,7 @@ async fn get_task_statistics(
 
     // 统计运行中任务
     let running_sql =
-        "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE data IS NOT NULL AND deleted = false";
+        "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE data IS NOT NULL AND deleted = false AND schedule_state = 'active'";
     let running: i64 = db
         .query_one_raw(Statement::from_sql_and_values(
             sea_orm::DatabaseBackend::Postgres,
@@ -646,6 +698,19 @@ async fn get_task_statistics(
         .and_then(|row| row.try_get("", "count").ok())
         .unwrap_or(0);
 
+    // 统计已暂停任务
+    let paused_sql = "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE deleted = false AND schedule_state = 'paused'";
+    let paused: i64 = db
+        .query_one_raw(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Postgres,
+            paused_sql,
+            [],
+        ))
+        .await
+        .context("统计已暂停任务失败")?
+        .and_then(|row| row.try_get("", "count").ok())
+        .unwrap_or(0);
+
     // 统计已完成任务
     let completed_sql = "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE deleted = true";
     let completed: i64 = db
@@ -678,12 +743,58 @@ async fn get_task_statistics(
     Ok(Json(TaskStatisticsResp {
         pending,
         running,
+        paused,
         completed,
         failed,
         deleted,
     }))
 }
 
+/// 试用转化统计（管理员），默认统计最近30天开通的试用
+#[get("/admin/statistics/trials")]
+async fn get_trial_statistics(
+    _admin: AdminClaims,
+    Component(db): Component<DbConn>,
+    Query(q): Query<TrialStatisticsQuery>,
+) -> Result<Json<TrialStatisticsResp>> {
+    use sea_orm::{ConnectionTrait, FromQueryResult, Statement};
+
+    let today = Local::now().date_naive();
+    let end_date = q.end_date.unwrap_or(today);
+    let start_date = q
+        .start_date
+        .unwrap_or(end_date - chrono::Duration::days(30));
+    let start = start_date.and_hms_opt(0, 0, 0).expect("invalid start time");
+    let end = (end_date + chrono::Duration::days(1))
+        .and_hms_opt(0, 0, 0)
+        .expect("invalid end time");
+
+    let stats = TrialStatisticsResp::find_by_statement(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        r#"
+        SELECT
+            COUNT(*)::bigint AS started,
+            COUNT(*) FILTER (WHERE converted_order_id IS NULL AND expired_at > $3)::bigint AS active,
+            COUNT(*) FILTER (WHERE converted_order_id IS NOT NULL)::bigint AS converted,
+            COALESCE(
+                COUNT(*) FILTER (WHERE converted_order_id IS NOT NULL)::float8
+                    / NULLIF(COUNT(*) FILTER (WHERE converted_order_id IS NOT NULL OR expired_at <= $3), 0),
+                0
+            ) AS conversion_rate,
+            (AVG(EXTRACT(EPOCH FROM converted_at - created)) / 86400)::float8 AS avg_days_to_convert
+        FROM membership_trial
+        WHERE created >= $1 AND created < $2
+        "#,
+        vec![start.into(), end.into(), Local::now().naive_local().into()],
+    ))
+    .one(&db)
+    .await
+    .context("统计试用转化失败")?
+    .ok_or_else(|| KnownWebError::internal_server_error("统计试用转化失败"))?;
+
+    Ok(Json(stats))
+}
+
 /// 获取统计概览（管理员）
 #[get("/admin/statistics/overview")]
 async fn get_statistics_overview(
@@ -737,3 +848,120 @@ async fn get_statistics_overview(
         template_count,
     }))
 }
+
+// ==================== Worker 管理接口 ====================
+
+/// 获取 worker 列表
+#[get("/admin/worker/list")]
+async fn get_worker_list(
+    _admin: AdminClaims,
+    Component(db): Component<DbConn>,
+) -> Result<Json<Vec<WorkerResp>>> {
+    let workers = Worker::find()
+        .order_by_asc(worker::Column::Id)
+        .all(&db)
+        .await
+        .context("query worker list failed")?;
+    Ok(Json(workers.into_iter().map(WorkerResp::from).collect()))
+}
+
+/// 注册 worker，返回的密钥只展示一次
+#[post("/admin/worker/create")]
+async fn create_worker(
+    _admin: AdminClaims,
+    Component(db): Component<DbConn>,
+    Valid(Json(req)): Valid<Json<CreateWorkerReq>>,
+) -> Result<Json<WorkerCredentialResp>> {
+    let exists = Worker::find()
+        .filter(worker::Column::Name.eq(&req.name))
+        .one(&db)
+        .await
+        .context("find worker failed")?;
+    if exists.is_some() {
+        return Err(KnownWebError::bad_request("worker名称已存在"))?;
+    }
+
+    let secret = worker::gen_secret();
+    let model = worker::ActiveModel {
+        name: Set(req.name),
+        secret_hash: Set(worker::hash_secret(&secret)),
+        enabled: Set(true),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .context("create worker failed")?;
+
+    Ok(Json(WorkerCredentialResp {
+        id: model.id,
+        name: model.name,
+        secret,
+    }))
+}
+
+/// 重置 worker 密钥，旧密钥立即不能再换取令牌
+#[post("/admin/worker/{id}/reset-secret")]
+async fn reset_worker_secret(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<WorkerCredentialResp>> {
+    let model = find_worker(&db, id).await?;
+    let secret = worker::gen_secret();
+    let model = worker::ActiveModel {
+        id: Set(model.id),
+        secret_hash: Set(worker::hash_secret(&secret)),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .context("reset worker secret failed")?;
+
+    Ok(Json(WorkerCredentialResp {
+        id: model.id,
+        name: model.name,
+        secret,
+    }))
+}
+
+/// 启用 worker
+#[post("/admin/worker/{id}/enable")]
+async fn enable_worker(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<WorkerResp>> {
+    set_worker_enabled(&db, id, true).await
+}
+
+/// 禁用 worker，已签发的令牌在有效期结束后失效
+#[post("/admin/worker/{id}/disable")]
+async fn disable_worker(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<WorkerResp>> {
+    set_worker_enabled(&db, id, false).await
+}
+
+async fn find_worker(db: &DbConn, id: i64) -> Result<worker::Model> {
+    Ok(Worker::find_by_id(id)
+        .one(db)
+        .await
+        .context("find worker failed")?
+        .ok_or_else(|| KnownWebError::not_found("worker不存在"))?)
+}
+
+async fn set_worker_enabled(db: &DbConn, id: i64, enabled: bool) -> Result<Json<WorkerResp>> {
+    let model = find_worker(db, id).await?;
+    let model = worker::ActiveModel {
+        id: Set(model.id),
+        enabled: Set(enabled),
+        ..Default::default()
+    }
+    .update(db)
+    .await
+    .context("update worker failed")?;
+
+    Ok(Json(WorkerResp::from(model)))
+}
@@ -0,0 +1,287 @@
+use crate::{
+    config::mail::Email,
+    model::{
+        invoice_request, pay_notify_event, pay_order,
+        prelude::{InvoiceRequest, PayNotifyEvent},
+        sea_orm_active_enums::InvoiceStatus,
+    },
+    service::{
+        finance::{self, FinanceService},
+        pay::PayOrderService,
+        pay_notify::PayNotifyService,
+        user::UserService,
+    },
+    utils::{jwt::AdminClaims, mail},
+    views::pay::{
+        format_amount, FinanceReport, FinanceReportQuery, InvoiceEmailTemplate,
+        InvoiceRequestQuery, IssueInvoiceReq, PayNotifyEventQuery, RefundOrderReq,
+        RejectInvoiceReq,
+    },
+};
+use anyhow::Context;
+use axum_valid::Valid;
+use chrono::Local;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, Condition, DbConn, EntityTrait, QueryFilter,
+    QueryOrder,
+};
+use summer::tracing;
+use summer_mail::Mailer;
+use summer_sea_orm::pagination::{Page, Pagination, PaginationExt};
+use summer_web::{
+    axum::{
+        http::header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+        response::{IntoResponse, Response},
+        Json,
+    },
+    error::{KnownWebError, Result},
+    extractor::{Component, Config, Path, Query},
+    get, post,
+};
+
+/// 支付回调事件列表
+#[get("/admin/pay/notify-events")]
+async fn query_notify_events(
+    _admin: AdminClaims,
+    Component(db): Component<DbConn>,
+    Query(q): Query<PayNotifyEventQuery>,
+    pagination: Pagination,
+) -> Result<Json<Page<pay_notify_event::Model>>> {
+    let mut filter = Condition::all();
+    if let Some(provider) = q.provider {
+        filter = filter.add(pay_notify_event::Column::Provider.eq(provider));
+    }
+    if let Some(status) = q.status {
+        filter = filter.add(pay_notify_event::Column::Status.eq(status));
+    }
+    if let Some(order_id) = q.order_id {
+        filter = filter.add(pay_notify_event::Column::OrderId.eq(order_id));
+    }
+
+    let page = PayNotifyEvent::find()
+        .filter(filter)
+        .order_by_desc(pay_notify_event::Column::Created)
+        .page(&db, &pagination)
+        .await
+        .context("查询支付回调事件失败")?;
+
+    Ok(Json(page))
+}
+
+/// 支付回调事件详情
+#[get("/admin/pay/notify-events/{id}")]
+async fn get_notify_event(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<pay_notify_event::Model>> {
+    let event = PayNotifyEvent::find_by_id(id)
+        .one(&db)
+        .await
+        .context("查询支付回调事件失败")?
+        .ok_or_else(|| KnownWebError::not_found("回调事件不存在"))?;
+    Ok(Json(event))
+}
+
+/// 重放支付回调事件
+#[post("/admin/pay/notify-events/{id}/replay")]
+async fn replay_notify_event(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Component(pns): Component<PayNotifyService>,
+    Component(ps): Component<PayOrderService>,
+    Component(us): Component<UserService>,
+) -> Result<Json<pay_notify_event::Model>> {
+    let event = PayNotifyEvent::find_by_id(id)
+        .one(&db)
+        .await
+        .context("查询支付回调事件失败")?
+        .ok_or_else(|| KnownWebError::not_found("回调事件不存在"))?;
+    if !event.signature_valid {
+        return Err(KnownWebError::bad_request("验签失败的事件不能重放"))?;
+    }
+
+    let event = pns
+        .process(event, &ps, &us)
+        .await
+        .context("重放支付回调事件失败")?;
+    Ok(Json(event))
+}
+
+/// 订单原路退款
+#[post("/admin/pay/orders/{id}/refund")]
+async fn refund_order(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(ps): Component<PayOrderService>,
+    Valid(Json(req)): Valid<Json<RefundOrderReq>>,
+) -> Result<Json<pay_order::Model>> {
+    let order = ps
+        .refund_order(id, req.amount, &req.reason)
+        .await
+        .map_err(|e| KnownWebError::bad_request(format!("退款失败: {e:#}")))?;
+    Ok(Json(order))
+}
+
+/// 发票申请列表
+#[get("/admin/pay/invoices")]
+async fn query_invoices(
+    _admin: AdminClaims,
+    Component(db): Component<DbConn>,
+    Query(q): Query<InvoiceRequestQuery>,
+    pagination: Pagination,
+) -> Result<Json<Page<invoice_request::Model>>> {
+    let mut filter = Condition::all();
+    if let Some(status) = q.status {
+        filter = filter.add(invoice_request::Column::Status.eq(status));
+    }
+    if let Some(user_id) = q.user_id {
+        filter = filter.add(invoice_request::Column::UserId.eq(user_id));
+    }
+
+    let page = InvoiceRequest::find()
+        .filter(filter)
+        .order_by_desc(invoice_request::Column::Created)
+        .page(&db, &pagination)
+        .await
+        .context("查询发票申请失败")?;
+
+    Ok(Json(page))
+}
+
+/// 开具发票，并将发票信息发送到申请邮箱
+#[post("/admin/pay/invoices/{id}/issue")]
+async fn issue_invoice(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Component(mailer): Component<Mailer>,
+    Config(email): Config<Email>,
+    Valid(Json(req)): Valid<Json<IssueInvoiceReq>>,
+) -> Result<Json<invoice_request::Model>> {
+    let invoice = find_pending_invoice(&db, id).await?;
+
+    let template = InvoiceEmailTemplate {
+        tip: "您申请的发票已开具，详细信息如下：",
+        title: &invoice.title,
+        amount: format_amount(invoice.amount),
+        invoice_no: Some(&req.invoice_no),
+        invoice_url: req.invoice_url.as_deref(),
+        remark: None,
+    };
+    mail::send_mail(
+        &mailer,
+        &email.from,
+        &invoice.email,
+        "发票已开具",
+        &template,
+    )
+    .await?;
+
+    let invoice = invoice_request::ActiveModel {
+        id: Set(invoice.id),
+        status: Set(InvoiceStatus::Issued),
+        invoice_no: Set(Some(req.invoice_no)),
+        invoice_url: Set(req.invoice_url),
+        issued_at: Set(Some(Local::now().naive_local())),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .context("更新发票申请失败")?;
+    Ok(Json(invoice))
+}
+
+/// 驳回发票申请，用户可修改信息后重新申请
+#[post("/admin/pay/invoices/{id}/reject")]
+async fn reject_invoice(
+    _admin: AdminClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Component(mailer): Component<Mailer>,
+    Config(email): Config<Email>,
+    Valid(Json(req)): Valid<Json<RejectInvoiceReq>>,
+) -> Result<Json<invoice_request::Model>> {
+    let invoice = find_pending_invoice(&db, id).await?;
+
+    let template = InvoiceEmailTemplate {
+        tip: "很抱歉，您的发票申请未通过审核，请修改后重新申请：",
+        title: &invoice.title,
+        amount: format_amount(invoice.amount),
+        invoice_no: None,
+        invoice_url: None,
+        remark: Some(&req.reason),
+    };
+    if let Err(e) = mail::send_mail(
+        &mailer,
+        &email.from,
+        &invoice.email,
+        "发票申请未通过",
+        &template,
+    )
+    .await
+    {
+        tracing::warn!("发送发票驳回邮件失败: {e:?}");
+    }
+
+    let invoice = invoice_request::ActiveModel {
+        id: Set(invoice.id),
+        status: Set(InvoiceStatus::Rejected),
+        remark: Set(Some(req.reason)),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .context("更新发票申请失败")?;
+    Ok(Json(invoice))
+}
+
+async fn find_pending_invoice(db: &DbConn, id: i64) -> Result<invoice_request::Model> {
+    let invoice = InvoiceRequest::find_by_id(id)
+        .one(db)
+        .await
+        .context("查询发票申请失败")?
+        .ok_or_else(|| KnownWebError::not_found("发票申请不存在"))?;
+    if invoice.status != InvoiceStatus::Pending {
+        return Err(KnownWebError::bad_request("发票申请已处理"))?;
+    }
+    Ok(invoice)
+}
+
+/// 收入分析：MRR/ARR、新购/续费/升级收入、流失会员、渠道与版本收入分布
+#[get("/admin/pay/finance")]
+async fn finance_report(
+    _admin: AdminClaims,
+    Component(fs): Component<FinanceService>,
+    Query(q): Query<FinanceReportQuery>,
+) -> Result<Json<FinanceReport>> {
+    let report = fs.report(q.start_date, q.end_date, q.granularity).await?;
+    Ok(Json(report))
+}
+
+/// 导出收入分析明细（CSV）
+#[get("/admin/pay/finance/export")]
+async fn export_finance_report(
+    _admin: AdminClaims,
+    Component(fs): Component<FinanceService>,
+    Query(q): Query<FinanceReportQuery>,
+) -> Result<Response> {
+    let report = fs.report(q.start_date, q.end_date, q.granularity).await?;
+    let filename = format!(
+        "finance-{}-{}.csv",
+        report.start_date.format("%Y%m%d"),
+        report.end_date.format("%Y%m%d")
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        finance::report_to_csv(&report),
+    )
+        .into_response())
+}
@@ -1,5 +1,7 @@
 use crate::model::prelude::ScraperTask;
-use crate::service::data_clean::{export_pipeline, preview_pipeline, validate_pipeline};
+use crate::service::data_clean::{
+    ensure_pipeline_table, export_pipeline, preview_pipeline, validate_pipeline,
+};
 use crate::utils::jwt::Claims;
 use crate::views::data_clean::{
     CleanExportReq, CleanExportResp, CleanPipelineReq, CleanPipelineSummary, CleanPreviewReq,
@@ -149,32 +151,6 @@ fn parse_store_id(store_id: &str) -> Result<i64> {
         .map_err(|_| KnownWebError::bad_request("数据集 ID 不正确").into())
 }
 
-async fn ensure_pipeline_table(pool: &ConnectPool) -> Result<()> {
-    sqlx::query(
-        "CREATE TABLE IF NOT EXISTS data_clean_pipeline (
-            id bigserial PRIMARY KEY,
-            user_id bigint NOT NULL,
-            store_id varchar(64) NOT NULL,
-            name varchar(80) NOT NULL,
-            definition jsonb NOT NULL,
-            created_at timestamp NOT NULL,
-            modified_at timestamp NOT NULL
-        )",
-    )
-    .execute(pool)
-    .await
-    .context("初始化数据清洗流程表失败")?;
-
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_data_clean_pipeline_user_store_modified \
-         ON data_clean_pipeline(user_id, store_id, modified_at DESC)",
-    )
-    .execute(pool)
-    .await
-    .context("初始化数据清洗流程索引失败")?;
-    Ok(())
-}
-
 fn row_to_pipeline_summary(
     row: summer_sqlx::sqlx::postgres::PgRow,
 ) -> anyhow::Result<CleanPipelineSummary> {
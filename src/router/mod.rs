@@ -1,12 +1,14 @@
 mod admin;
 mod data_clean;
 mod pay;
+mod proxy;
 mod statistics;
 mod store;
 mod task;
 mod template;
 mod token;
 mod user;
+mod worker;
 
 use axum_client_ip::ClientIpSource;
 use summer::config::env::Env;
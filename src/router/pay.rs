@@ -1,24 +1,41 @@
 use crate::{
     model::{
+        invoice_request,
         pay_order::{self, Entity as PayOrder},
-        sea_orm_active_enums::{OrderLevel, OrderStatus, PayFrom, ProductEdition},
+        prelude::{AccountUser, InvoiceRequest},
+        sea_orm_active_enums::{NotifyStatus, OrderLevel, OrderStatus, PayFrom, ProductEdition},
+    },
+    router::ClientIp,
+    service::{
+        pay::{build_pay_out_trade_no, Checkout, MockProvider, PayOrderService},
+        pay_notify::PayNotifyService,
+        pay_status::{PayStatusService, PayStatusSse},
+        user::UserService,
+    },
+    utils::jwt::{Claims, OptionalClaims},
+    views::pay::{
+        InvoiceApplyReq, PayMode, PayOrderQuery, PayOrderResp, PayReceiptTemplate, PaymentAction,
+        UpgradeQuote, UpgradeQuoteQuery,
     },
-    router::admin::marketing as marketing_router,
-    service::{pay::PayOrderService, user::UserService},
-    utils::jwt::Claims,
 };
+use askama::Template;
 use axum_extra::headers::HeaderMap;
+use axum_valid::Valid;
 use chrono::NaiveDate;
 use schemars::JsonSchema;
-use sea_orm::DbConn;
+use sea_orm::{
+    ActiveModelTrait, ActiveValue::Set, ColumnTrait, DbConn, EntityTrait, ExprTrait, QueryFilter,
+    QueryOrder,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use summer::tracing;
+use summer_sea_orm::pagination::{Page, Pagination, PaginationExt};
 use summer_web::{
     axum::{
         self,
         http::StatusCode,
-        response::{IntoResponse, Response},
+        response::{Html, IntoResponse, Response},
         Form, Json,
     },
     extractor::{Component, Path, Query},
@@ -31,31 +48,102 @@ pub struct TradeCreateQuery {
     pub level: OrderLevel,
     pub edition: ProductEdition,
     pub pay_from: PayFrom,
+    /// 支付方式，默认扫码支付
+    #[serde(default)]
+    pub mode: PayMode,
+    /// 微信 JSAPI 支付时用户在公众号下的 openid
+    pub openid: Option<String>,
+}
+
+impl TradeCreateQuery {
+    fn checkout(&self, client_ip: &ClientIp) -> Checkout {
+        Checkout {
+            mode: self.mode,
+            client_ip: Some(client_ip.0 .0.to_string()),
+            openid: self.openid.clone(),
+        }
+    }
+}
+
+/// 下单结果。`qrcode_url` 兼容旧版前端，取值为二维码内容或跳转地址
+fn trade_created(
+    order_id: i64,
+    trade: &TradeCreateQuery,
+    action: Option<PaymentAction>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let action = action
+        .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "支付信息生成失败").into_response())?;
+    Ok(Json(json!({
+        "order_id": order_id,
+        "qrcode_url": action.url(),
+        "pay_from": trade.pay_from,
+        "mode": trade.mode,
+        "action": action,
+    })))
 }
 
 /// 创建支付订单（表单提交）
 #[post("/pay/create")]
 async fn create_trade(
     claims: Claims,
+    client_ip: ClientIp,
     Component(ps): Component<PayOrderService>,
     Form(trade): Form<TradeCreateQuery>,
 ) -> Result<Json<serde_json::Value>, Response> {
-    let (order_id, qrcode_url) = ps
-        .create_order(claims.uid, trade.level, trade.edition, trade.pay_from)
+    let (order_id, action) = ps
+        .create_order(
+            claims.uid,
+            trade.level,
+            trade.edition.clone(),
+            trade.pay_from,
+            trade.checkout(&client_ip),
+        )
         .await
         .map_err(|e| {
             tracing::error!("创建订单失败: {e:?}");
-            (StatusCode::INTERNAL_SERVER_ERROR, "创建订单失败").into_response()
+            e.into_response()
         })?;
 
-    let qrcode_url = qrcode_url
-        .ok_or_else(|| (StatusCode::INTERNAL_SERVER_ERROR, "二维码生成失败").into_response())?;
+    trade_created(order_id, &trade, action)
+}
 
-    Ok(Json(json!({
-        "order_id": order_id,
-        "qrcode_url": qrcode_url,
-        "pay_from": trade.pay_from,
-    })))
+/// 查询升级到更高版本的报价
+#[get("/pay/upgrade/quote")]
+async fn upgrade_quote(
+    claims: Claims,
+    Component(ps): Component<PayOrderService>,
+    Query(q): Query<UpgradeQuoteQuery>,
+) -> Result<Json<UpgradeQuote>, Response> {
+    let quote = ps
+        .quote_upgrade(claims.uid, q.edition, q.level)
+        .await
+        .map_err(|e| e.into_response())?;
+    Ok(Json(quote))
+}
+
+/// 创建升级补差价订单（表单提交）
+#[post("/pay/upgrade")]
+async fn create_upgrade_trade(
+    claims: Claims,
+    client_ip: ClientIp,
+    Component(ps): Component<PayOrderService>,
+    Form(trade): Form<TradeCreateQuery>,
+) -> Result<Json<serde_json::Value>, Response> {
+    let (order_id, action) = ps
+        .create_upgrade_order(
+            claims.uid,
+            trade.level,
+            trade.edition.clone(),
+            trade.pay_from,
+            trade.checkout(&client_ip),
+        )
+        .await
+        .map_err(|e| {
+            tracing::error!("创建升级订单失败: {e:?}");
+            e.into_response()
+        })?;
+
+    trade_created(order_id, &trade, action)
 }
 
 /// 查询订单支付状态
@@ -76,73 +164,166 @@ async fn pay_status(
     Ok(Json(status))
 }
 
+/// 订阅订单支付状态（SSE）
+///
+/// 先推送一次当前状态，之后在回调处理完成时推送 `paid`/`closed` 等终态并结束连接；
+/// 回调迟迟未到时服务端会主动向支付渠道查单。EventSource 无法设置请求头，可通过 `token` 查询参数鉴权。
+#[get("/pay/{order_id}/status/stream")]
+async fn pay_status_stream(
+    opt_claims: OptionalClaims,
+    Path(order_id): Path<i64>,
+    Component(pss): Component<PayStatusService>,
+    Component(ps): Component<PayOrderService>,
+    Component(us): Component<UserService>,
+) -> Result<PayStatusSse, Response> {
+    let claims = opt_claims.get().map_err(|e| e.into_response())?;
+    pss.open_order_status(claims.uid, order_id, ps, us)
+        .await
+        .map_err(|e| e.into_response())
+}
+
+/// 当前用户的订单列表
+#[get("/pay/orders")]
+async fn list_orders(
+    claims: Claims,
+    Component(db): Component<DbConn>,
+    Query(q): Query<PayOrderQuery>,
+    pagination: Pagination,
+) -> Result<Json<Page<PayOrderResp>>, Response> {
+    let mut filter = pay_order::Column::UserId.eq(claims.uid);
+    if let Some(status) = q.status {
+        filter = filter.and(pay_order::Column::Status.eq(status));
+    }
+    let page = PayOrder::find()
+        .filter(filter)
+        .order_by_desc(pay_order::Column::Created)
+        .page(&db, &pagination)
+        .await
+        .map_err(|e| {
+            tracing::error!("查询订单列表失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "查询失败").into_response()
+        })?;
+    Ok(Json(page.map(PayOrderResp::from)))
+}
+
+/// 订单支付收据（HTML，可打印为 PDF）
+#[get("/pay/orders/{order_id}/receipt")]
+async fn order_receipt(
+    claims: Claims,
+    Path(order_id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Html<String>, Response> {
+    let order = find_user_order(&db, order_id, claims.uid).await?;
+    if !matches!(order.status, OrderStatus::Paid | OrderStatus::Refunded) {
+        return Err((StatusCode::BAD_REQUEST, "订单未支付").into_response());
+    }
+    let user = AccountUser::find_by_id(claims.uid)
+        .one(&db)
+        .await
+        .map_err(|e| {
+            tracing::error!("查询用户失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "查询失败").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "用户不存在").into_response())?;
+
+    let receipt_no = build_pay_out_trade_no(order.id, order.created);
+    let html = PayReceiptTemplate::new(receipt_no, &order, &user)
+        .render()
+        .map_err(|e| {
+            tracing::error!("渲染收据失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "渲染收据失败").into_response()
+        })?;
+    Ok(Html(html))
+}
+
+/// 申请开具发票
+#[post("/pay/orders/{order_id}/invoice")]
+async fn apply_invoice(
+    claims: Claims,
+    Path(order_id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Valid(Json(req)): Valid<Json<InvoiceApplyReq>>,
+) -> Result<Json<invoice_request::Model>, Response> {
+    let order = find_user_order(&db, order_id, claims.uid).await?;
+    if order.status != OrderStatus::Paid {
+        return Err((StatusCode::BAD_REQUEST, "只有已支付的订单可以申请发票").into_response());
+    }
+    let existing = InvoiceRequest::find_active_by_order(&db, order_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("查询发票申请失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "查询失败").into_response()
+        })?;
+    if existing.is_some() {
+        return Err((StatusCode::BAD_REQUEST, "该订单已申请过发票").into_response());
+    }
+
+    let invoice = invoice_request::ActiveModel {
+        user_id: Set(claims.uid),
+        order_id: Set(order_id),
+        title: Set(req.title),
+        tax_id: Set(req.tax_id),
+        email: Set(req.email),
+        amount: Set(order.amount),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .map_err(|e| {
+        tracing::error!("创建发票申请失败: {e:?}");
+        (StatusCode::INTERNAL_SERVER_ERROR, "申请失败").into_response()
+    })?;
+    Ok(Json(invoice))
+}
+
+/// 当前用户的发票申请记录
+#[get("/pay/invoices")]
+async fn list_invoices(
+    claims: Claims,
+    Component(db): Component<DbConn>,
+) -> Result<Json<Vec<invoice_request::Model>>, Response> {
+    let invoices = InvoiceRequest::find_by_user(&db, claims.uid)
+        .await
+        .map_err(|e| {
+            tracing::error!("查询发票申请失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "查询失败").into_response()
+        })?;
+    Ok(Json(invoices))
+}
+
+async fn find_user_order(
+    db: &DbConn,
+    order_id: i64,
+    user_id: i64,
+) -> Result<pay_order::Model, Response> {
+    PayOrder::find_by_id(order_id)
+        .filter(pay_order::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .map_err(|e| {
+            tracing::error!("查询订单失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "查询失败").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "订单不存在").into_response())
+}
+
 /// 微信支付回调
 #[post("/pay/notify/wechat")]
 async fn wechat_pay_callback(
     Component(ps): Component<PayOrderService>,
     Component(us): Component<UserService>,
-    Component(db): Component<DbConn>,
+    Component(pns): Component<PayNotifyService>,
     headers: HeaderMap,
-    body: String,
+    body: axum::body::Bytes,
 ) -> Result<Json<serde_json::Value>, Response> {
-    let serial = headers
-        .get("Wechatpay-Serial")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or_default();
-    let signature = headers
-        .get("Wechatpay-Signature")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or_default();
-    let timestamp = headers
-        .get("Wechatpay-Timestamp")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or_default();
-    let nonce = headers
-        .get("Wechatpay-Nonce")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or_default();
-
-    let notify = match ps
-        .wechat_verify_signature(serial, timestamp, nonce, signature, &body)
-        .await
-    {
-        Err(e) => {
-            tracing::error!(
-                serial = serial,
-                signature = signature,
-                timestamp = timestamp,
-                nonce = nonce,
-                "微信支付回调验签失败: {e:#}"
-            );
-            return Ok(Json(json!({"code": "FAIL", "message": "验签失败"})));
-        }
-        Ok(notify) => notify,
-    };
-
-    let model = match ps.notify_wechat_pay(&notify).await {
-        Err(e) => {
-            tracing::error!("处理微信支付回调失败: {e:#}");
-            return Ok(Json(json!({"code": "FAIL", "message": "处理失败"})));
-        }
-        Ok(model) => model,
-    };
+    let (signature_valid, processed) =
+        handle_notify(PayFrom::Wechat, &ps, &us, &pns, &headers, &body).await;
 
-    let pay_order::Model {
-        user_id,
-        level,
-        edition,
-        ..
-    } = model;
-    match us.confirm_user(user_id, level, edition).await {
-        Err(e) => {
-            tracing::error!("confirm_user({user_id},{level:?}) failed>>>{e:?}");
-        }
-        Ok(u) => {
-            tracing::info!("confirm_user({user_id},{level:?}) success>>>{u:?}");
-            if let Err(e) = marketing_router::record_purchase_by_user(&db, user_id).await {
-                tracing::warn!("record marketing purchase failed: {e:#}");
-            }
-        }
+    if !signature_valid {
+        return Ok(Json(json!({"code": "FAIL", "message": "验签失败"})));
+    }
+    if !processed {
+        return Ok(Json(json!({"code": "FAIL", "message": "处理失败"})));
     }
 
     Ok(Json(json!({"code": "SUCCESS"})))
@@ -153,38 +334,15 @@ async fn wechat_pay_callback(
 async fn alipay_callback(
     Component(ps): Component<PayOrderService>,
     Component(us): Component<UserService>,
-    Component(db): Component<DbConn>,
+    Component(pns): Component<PayNotifyService>,
+    headers: HeaderMap,
     body: axum::body::Bytes,
 ) -> Result<&'static str, Response> {
-    if let Err(e) = ps.alipay_verify_sign(&body).await {
-        tracing::error!("支付宝验签失败:{e:#}");
-        return Ok("fail");
-    }
-
-    let model = match ps.notify_alipay(&body).await {
-        Err(e) => {
-            tracing::error!("处理支付宝回调失败: {e:#}");
-            return Ok("fail");
-        }
-        Ok(model) => model,
-    };
+    let (signature_valid, processed) =
+        handle_notify(PayFrom::Alipay, &ps, &us, &pns, &headers, &body).await;
 
-    let pay_order::Model {
-        user_id,
-        level,
-        edition,
-        ..
-    } = model;
-    match us.confirm_user(user_id, level, edition).await {
-        Err(e) => {
-            tracing::error!("confirm_user({user_id},{level:?}) failed>>>{e:?}");
-        }
-        Ok(u) => {
-            tracing::info!("confirm_user({user_id},{level:?}) success>>>{u:?}");
-            if let Err(e) = marketing_router::record_purchase_by_user(&db, user_id).await {
-                tracing::warn!("record marketing purchase failed: {e:#}");
-            }
-        }
+    if !signature_valid || !processed {
+        return Ok("fail");
     }
 
     Ok("success")
@@ -195,52 +353,91 @@ async fn alipay_callback(
 async fn paddle_callback(
     Component(ps): Component<PayOrderService>,
     Component(us): Component<UserService>,
-    Component(db): Component<DbConn>,
+    Component(pns): Component<PayNotifyService>,
     headers: HeaderMap,
     body: axum::body::Bytes,
 ) -> Result<Json<serde_json::Value>, Response> {
-    let signature = headers
-        .get("Paddle-Signature")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or_default();
+    let (signature_valid, processed) =
+        handle_notify(PayFrom::Paddle, &ps, &us, &pns, &headers, &body).await;
+
+    if !signature_valid {
+        return Err((StatusCode::UNAUTHORIZED, "验签失败").into_response());
+    }
+    if !processed {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "处理失败").into_response());
+    }
+
+    Ok(Json(json!({"ok": true})))
+}
 
-    let event = match ps.paddle_verify_signature(signature, &body) {
+/// 模拟支付成功（仅开启 `pay.mock_enable` 时可用）
+#[post("/pay/mock/{order_id}/pay")]
+async fn mock_pay(
+    claims: Claims,
+    Path(order_id): Path<i64>,
+    Component(ps): Component<PayOrderService>,
+    Component(us): Component<UserService>,
+    Component(pns): Component<PayNotifyService>,
+    headers: HeaderMap,
+) -> Result<Json<OrderStatus>, Response> {
+    if !ps.is_enabled(PayFrom::Mock) {
+        return Err((StatusCode::NOT_FOUND, "模拟支付未启用").into_response());
+    }
+    let order = PayOrder::find_by_id(order_id)
+        .one(&ps.db)
+        .await
+        .map_err(|e| {
+            tracing::error!("查询订单失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "查询失败").into_response()
+        })?
+        .filter(|order| order.user_id == claims.uid && order.pay_from == PayFrom::Mock)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "订单不存在").into_response())?;
+
+    let body = MockProvider::paid_notification(order.id);
+    let (_, processed) = handle_notify(PayFrom::Mock, &ps, &us, &pns, &headers, &body).await;
+    if !processed {
+        return Err((StatusCode::INTERNAL_SERVER_ERROR, "处理失败").into_response());
+    }
+
+    let status = pay_order::Entity::find_order_status(&ps.db, order_id, claims.uid)
+        .await
+        .map_err(|e| {
+            tracing::error!("查询订单状态失败: {e:?}");
+            (StatusCode::INTERNAL_SERVER_ERROR, "查询失败").into_response()
+        })?
+        .ok_or_else(|| (StatusCode::NOT_FOUND, "订单不存在").into_response())?;
+    Ok(Json(status))
+}
+
+/// 验签后将回调写入事件日志并处理，返回 `(验签是否通过, 是否处理成功)`
+async fn handle_notify(
+    from: PayFrom,
+    ps: &PayOrderService,
+    us: &UserService,
+    pns: &PayNotifyService,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> (bool, bool) {
+    let signature_valid = match ps.verify_notification(from, headers, body).await {
         Err(e) => {
-            tracing::error!("Paddle 验签失败: {e:#}");
-            return Err((StatusCode::UNAUTHORIZED, "验签失败").into_response());
+            tracing::error!("{from}支付回调验签失败: {e:#}");
+            false
         }
-        Ok(event) => event,
+        Ok(()) => true,
     };
 
-    let model = match ps.notify_paddle(&event).await {
+    let processed = match pns
+        .receive(from, body, headers, signature_valid, ps, us)
+        .await
+    {
         Err(e) => {
-            tracing::error!("处理 Paddle 回调失败: {e:#}");
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, "处理失败").into_response());
+            tracing::error!("记录{from}支付回调失败: {e:#}");
+            false
         }
-        Ok(model) => model,
+        Ok(event) => event.status == NotifyStatus::Processed,
     };
 
-    if model.status == OrderStatus::Paid {
-        let pay_order::Model {
-            user_id,
-            level,
-            edition,
-            ..
-        } = model;
-        match us.confirm_user(user_id, level, edition).await {
-            Err(e) => {
-                tracing::error!("confirm_user({user_id},{level:?}) failed>>>{e:?}");
-            }
-            Ok(u) => {
-                tracing::info!("confirm_user({user_id},{level:?}) success>>>{u:?}");
-                if let Err(e) = marketing_router::record_purchase_by_user(&db, user_id).await {
-                    tracing::warn!("record marketing purchase failed: {e:#}");
-                }
-            }
-        }
-    }
-
-    Ok(Json(json!({"ok": true})))
+    (signature_valid, processed)
 }
 
 #[derive(Deserialize)]
@@ -0,0 +1,245 @@
+use crate::model::prelude::{Proxy, ProxyPool};
+use crate::model::{proxy, proxy_pool};
+use crate::service::proxy::ProxyService;
+use crate::utils::jwt::Claims;
+use crate::views::proxy::{ProxyPoolReq, ProxyPoolResp, ProxyReq, ProxyResp};
+use anyhow::Context;
+use axum_valid::Valid;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DbConn, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set, TransactionTrait,
+};
+use summer_web::axum::Json;
+use summer_web::error::{KnownWebError, Result};
+use summer_web::extractor::{Component, Path};
+use summer_web::{delete_api, get_api, post_api, put_api};
+
+/// 单个代理池最多包含的代理数
+const MAX_POOL_PROXIES: u64 = 500;
+
+/// # 代理池列表
+/// @tag proxy
+///
+/// 返回当前用户的代理池和平台代理池。
+#[get_api("/proxy/pools")]
+async fn list_pools(
+    claims: Claims,
+    Component(db): Component<DbConn>,
+) -> Result<Json<Vec<ProxyPoolResp>>> {
+    let pools = ProxyPool::find()
+        .filter(
+            Condition::any()
+                .add(proxy_pool::Column::UserId.eq(claims.uid))
+                .add(proxy_pool::Column::UserId.is_null()),
+        )
+        .order_by_asc(proxy_pool::Column::Id)
+        .all(&db)
+        .await
+        .context("query proxy pools failed")?;
+    Ok(Json(pools.into_iter().map(ProxyPoolResp::from).collect()))
+}
+
+/// # 新增代理池
+/// @tag proxy
+#[post_api("/proxy/pools")]
+async fn add_pool(
+    claims: Claims,
+    Component(db): Component<DbConn>,
+    Valid(Json(body)): Valid<Json<ProxyPoolReq>>,
+) -> Result<Json<ProxyPoolResp>> {
+    if body.platform && !claims.is_admin {
+        Err(KnownWebError::forbidden("仅管理员可创建平台代理池"))?;
+    }
+    let pool = proxy_pool::ActiveModel {
+        user_id: Set((!body.platform).then_some(claims.uid)),
+        name: Set(body.name),
+        rotation: Set(body.rotation),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .context("save proxy pool failed")?;
+    Ok(Json(pool.into()))
+}
+
+/// # 修改代理池
+/// @tag proxy
+///
+/// 不能修改代理池的归属（`platform`）。
+#[put_api("/proxy/pools/{id}")]
+async fn update_pool(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Component(db): Component<DbConn>,
+    Valid(Json(body)): Valid<Json<ProxyPoolReq>>,
+) -> Result<Json<ProxyPoolResp>> {
+    let pool = ProxyPool::find_check_pool(&db, id, claims.uid, claims.is_admin).await?;
+    let pool = proxy_pool::ActiveModel {
+        id: Set(pool.id),
+        name: Set(body.name),
+        rotation: Set(body.rotation),
+        ..Default::default()
+    }
+    .update(&db)
+    .await
+    .context("update proxy pool failed")?;
+    Ok(Json(pool.into()))
+}
+
+/// # 删除代理池
+/// @tag proxy
+///
+/// 仍被任务引用的代理池不能删除，池内代理一并删除。
+#[delete_api("/proxy/pools/{id}")]
+async fn delete_pool(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<i32>> {
+    let pool = ProxyPool::find_check_pool(&db, id, claims.uid, claims.is_admin).await?;
+    let tasks = ProxyPool::count_tasks(&db, pool.id).await?;
+    if tasks > 0 {
+        Err(KnownWebError::bad_request(format!(
+            "代理池仍被{tasks}个任务使用，请先修改任务调度配置"
+        )))?;
+    }
+
+    let txn = db.begin().await.context("begin transaction failed")?;
+    Proxy::delete_many()
+        .filter(proxy::Column::PoolId.eq(pool.id))
+        .exec(&txn)
+        .await
+        .context("delete proxies failed")?;
+    ProxyPool::delete_by_id(pool.id)
+        .exec(&txn)
+        .await
+        .context("delete proxy pool failed")?;
+    txn.commit().await.context("commit transaction failed")?;
+
+    Ok(Json(pool.id))
+}
+
+/// # 代理池中的代理
+/// @tag proxy
+#[get_api("/proxy/pools/{id}/proxies")]
+async fn list_proxies(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<Vec<ProxyResp>>> {
+    let pool = ProxyPool::find_check_pool(&db, id, claims.uid, claims.is_admin).await?;
+    let proxies = Proxy::find()
+        .filter(proxy::Column::PoolId.eq(pool.id))
+        .order_by_asc(proxy::Column::Id)
+        .all(&db)
+        .await
+        .context("query proxies failed")?;
+    Ok(Json(proxies.into_iter().map(ProxyResp::from).collect()))
+}
+
+/// # 新增代理
+/// @tag proxy
+#[post_api("/proxy/pools/{id}/proxies")]
+async fn add_proxy(
+    claims: Claims,
+    Path(id): Path<i32>,
+    Component(db): Component<DbConn>,
+    Component(ps): Component<ProxyService>,
+    Valid(Json(body)): Valid<Json<ProxyReq>>,
+) -> Result<Json<ProxyResp>> {
+    let pool = ProxyPool::find_check_pool(&db, id, claims.uid, claims.is_admin).await?;
+    let count = Proxy::find()
+        .filter(proxy::Column::PoolId.eq(pool.id))
+        .count(&db)
+        .await
+        .context("count proxies failed")?;
+    if count >= MAX_POOL_PROXIES {
+        Err(KnownWebError::bad_request(format!(
+            "单个代理池最多{MAX_POOL_PROXIES}个代理"
+        )))?;
+    }
+
+    let password_cipher = match body.password.as_deref() {
+        Some(password) if !password.is_empty() => Some(ps.encrypt_password(password)?),
+        _ => None,
+    };
+    let proxy = proxy::ActiveModel {
+        pool_id: Set(pool.id),
+        protocol: Set(body.protocol),
+        host: Set(body.host),
+        port: Set(body.port),
+        username: Set(body.username.filter(|u| !u.is_empty())),
+        password_cipher: Set(password_cipher),
+        enabled: Set(body.enabled.unwrap_or(true)),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .context("save proxy failed")?;
+    Ok(Json(proxy.into()))
+}
+
+/// # 修改代理
+/// @tag proxy
+///
+/// `password` 为空时保留原密码；修改地址后清空健康检查结果。
+#[put_api("/proxy/proxies/{id}")]
+async fn update_proxy(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Component(ps): Component<ProxyService>,
+    Valid(Json(body)): Valid<Json<ProxyReq>>,
+) -> Result<Json<ProxyResp>> {
+    let proxy = find_check_proxy(&db, id, &claims).await?;
+
+    let mut model = proxy::ActiveModel {
+        id: Set(proxy.id),
+        protocol: Set(body.protocol),
+        host: Set(body.host.clone()),
+        port: Set(body.port),
+        username: Set(body.username.filter(|u| !u.is_empty())),
+        ..Default::default()
+    };
+    if let Some(password) = body.password.as_deref().filter(|p| !p.is_empty()) {
+        model.password_cipher = Set(Some(ps.encrypt_password(password)?));
+    }
+    if let Some(enabled) = body.enabled {
+        model.enabled = Set(enabled);
+    }
+    if proxy.protocol != body.protocol || proxy.host != body.host || proxy.port != body.port {
+        model.last_checked = Set(None);
+        model.last_check_ok = Set(None);
+        model.last_latency_ms = Set(None);
+        model.last_error = Set(None);
+    }
+    let proxy = model.update(&db).await.context("update proxy failed")?;
+    Ok(Json(proxy.into()))
+}
+
+/// # 删除代理
+/// @tag proxy
+#[delete_api("/proxy/proxies/{id}")]
+async fn delete_proxy(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<i64>> {
+    let proxy = find_check_proxy(&db, id, &claims).await?;
+    Proxy::delete_by_id(proxy.id)
+        .exec(&db)
+        .await
+        .context("delete proxy failed")?;
+    Ok(Json(proxy.id))
+}
+
+/// 查询代理并校验对所属代理池的修改权限
+async fn find_check_proxy(db: &DbConn, id: i64, claims: &Claims) -> Result<proxy::Model> {
+    let proxy = Proxy::find_by_id(id)
+        .one(db)
+        .await
+        .context("find proxy failed")?
+        .ok_or_else(|| KnownWebError::not_found("代理不存在"))?;
+    ProxyPool::find_check_pool(db, proxy.pool_id, claims.uid, claims.is_admin).await?;
+    Ok(proxy)
+}
@@ -44,7 +44,7 @@ async fn get_statistics(
         .and_then(|row| row.try_get("", "count").ok())
         .ok_or_else(|| anyhow::anyhow!("获取未部署任务数失败"))?;
 
-    let scheduled_sql = "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE user_id = $1 AND data IS NOT NULL AND deleted = false";
+    let scheduled_sql = "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE user_id = $1 AND data IS NOT NULL AND deleted = false AND schedule_state = 'active'";
     let scheduled: i64 = db
         .query_one_raw(Statement::from_sql_and_values(
             sea_orm::DatabaseBackend::Postgres,
@@ -56,6 +56,18 @@ async fn get_statistics(
         .and_then(|row| row.try_get("", "count").ok())
         .ok_or_else(|| anyhow::anyhow!("获取调度中任务数失败"))?;
 
+    let paused_sql = "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE user_id = $1 AND deleted = false AND schedule_state = 'paused'";
+    let paused: i64 = db
+        .query_one_raw(Statement::from_sql_and_values(
+            sea_orm::DatabaseBackend::Postgres,
+            paused_sql,
+            [user_id.into()],
+        ))
+        .await
+        .context("统计已暂停任务数失败")?
+        .and_then(|row| row.try_get("", "count").ok())
+        .ok_or_else(|| anyhow::anyhow!("获取已暂停任务数失败"))?;
+
     let completed_sql =
         "SELECT COUNT(*)::bigint as count FROM scraper_task WHERE user_id = $1 AND deleted = true";
     let completed: i64 = db
@@ -95,6 +107,7 @@ async fn get_statistics(
             total,
             undeployed,
             scheduled,
+            paused,
             completed,
         },
         instance_stats: InstanceStatistics {
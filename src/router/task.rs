@@ -1,25 +1,40 @@
-use crate::model::prelude::{AccountUser, ScraperTask, TaskInstance};
-use crate::model::scraper_task::{self, ScheduleData, ScraperTaskData};
-use crate::model::sea_orm_active_enums::ProductEdition;
-use crate::model::task_instance;
+use crate::model::prelude::{
+    AccountUser, ProxyPool, ScraperTask, TaskDependency, TaskDispatch, TaskInstance,
+    TaskRuleRevision,
+};
+use crate::model::scraper_task::{self, ScheduleData, ScheduleType, ScraperTaskData};
+use crate::model::sea_orm_active_enums::{
+    InstanceStatus, ProductEdition, ScheduleState, TriggerSource,
+};
+use crate::model::{task_dependency, task_dispatch, task_instance, task_rule_revision};
+use crate::service::quota::{QuotaExceeded, QuotaService};
+use crate::service::task_bundle::TaskBundleService;
 use crate::service::task_log::{TaskLogService, TaskLogSse};
 use crate::service::user::UserService;
+use crate::task::{
+    pause_schedule, resume_schedule, signal_cancel, sync_schedule, unschedule, TaskPublisher,
+};
 use crate::utils::jwt::{Claims, OptionalClaims};
-use crate::views::task::{ScraperTaskQuery, ScraperTaskReq, ScraperUpdateTaskReq};
+use crate::utils::rate_limit;
+use crate::views::task::{
+    AddDependencyReq, ChainTask, InstanceChainResp, RollbackRuleReq, RuleDiffQuery, RuleDiffResp,
+    RunTaskQuery, RunTaskResp, SchedulePreviewQuery, SchedulePreviewResp, ScraperTaskQuery,
+    ScraperTaskReq, ScraperUpdateTaskReq, TaskChainResp, UpdateRuleQuery,
+};
+use crate::views::task_bundle::{TaskBundle, TaskExportReq, TaskImportReq, TaskImportResp};
 use crate::views::task_instance_capture::TaskInstanceCaptureItem;
 use anyhow::Context;
 use axum_valid::Valid;
-use chrono::Local;
+use chrono::{Local, Utc};
 use itertools::Itertools;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, DbConn, EntityTrait, PaginatorTrait, QueryFilter,
-    QueryOrder, Set,
+    QueryOrder, Set, TransactionTrait,
 };
 use serde::Deserialize;
 use serde_json::Value;
-use std::sync::Arc;
-use summer_job::job::Job;
 use summer_job::JobScheduler;
+use summer_redis::Redis;
 use summer_sea_orm::pagination::{Page, Pagination, PaginationExt};
 use summer_sqlx::sqlx;
 use summer_sqlx::ConnectPool;
@@ -29,7 +44,7 @@ use summer_web::extractor::{AppRef, Component, Path, Query};
 use summer_web::{delete_api, get, get_api, patch_api, post_api, put_api};
 
 /// 检查用户任务数量限制
-async fn check_task_limit(
+pub(crate) async fn check_task_limit(
     db: &DbConn,
     user_id: i64,
     user_edition: Option<ProductEdition>,
@@ -117,6 +132,10 @@ async fn add_task(
 
     let mut body = body;
     if let Some(ref mut d) = body.data {
+        d.validate()?;
+        if let Some(schedule) = &d.schedule {
+            check_schedule(&db, claims.uid, &user.edition, schedule).await?;
+        }
         if let Some(ref mut dq) = d.data_quality {
             scraper_task::apply_data_quality_dedupe_version(None, dq);
         }
@@ -178,6 +197,15 @@ async fn add_batch_task(
         return Err(KnownWebError::forbidden(message))?;
     }
 
+    for m in &batch {
+        if let Some(ref d) = m.data {
+            d.validate()?;
+            if let Some(schedule) = &d.schedule {
+                check_schedule(&db, claims.uid, &user.edition, schedule).await?;
+            }
+        }
+    }
+
     let now = Local::now().naive_local();
     let batch = batch
         .into_iter()
@@ -206,6 +234,48 @@ async fn add_batch_task(
     Ok(Json(r.last_insert_id.unwrap_or_default()))
 }
 
+/// # 导出任务
+/// @tag task
+///
+/// 导出规则、调度、数据质量配置和清洗流程，可通过 `POST /task/import` 导入到其他账号或环境。
+#[post_api("/task/export")]
+async fn export_tasks(
+    claims: Claims,
+    Component(bundle_service): Component<TaskBundleService>,
+    Valid(Json(body)): Valid<Json<TaskExportReq>>,
+) -> Result<Json<TaskBundle>> {
+    let bundle = bundle_service.export(claims.uid, &body.ids).await?;
+    Ok(Json(bundle))
+}
+
+/// # 导入任务
+/// @tag task
+///
+/// 存在重名任务时按 `on_conflict` 处理；`dry_run` 为真时只返回导入计划。
+#[post_api("/task/import")]
+async fn import_tasks(
+    claims: Claims,
+    Component(db): Component<DbConn>,
+    Component(us): Component<UserService>,
+    Component(bundle_service): Component<TaskBundleService>,
+    Json(body): Json<TaskImportReq>,
+) -> Result<Json<TaskImportResp>> {
+    let user = AccountUser::find_by_id(claims.uid)
+        .one(&db)
+        .await
+        .context("find user failed")?
+        .ok_or_else(|| KnownWebError::not_found("用户不存在"))?;
+    let user = us
+        .refresh_user_membership(user)
+        .await
+        .context("refresh membership failed")?;
+
+    let resp = bundle_service
+        .import(claims.uid, user.edition, body)
+        .await?;
+    Ok(Json(resp))
+}
+
 /// # 获取任务详情
 /// @tag task
 #[get_api("/task/{id}")]
@@ -219,6 +289,97 @@ async fn get_task(
     Ok(Json(task))
 }
 
+/// 手动运行计数窗口（秒）
+const MANUAL_RUN_WINDOW_SECONDS: u64 = 3600;
+
+/// # 立即运行任务
+/// @tag task
+#[post_api("/task/{id}/run")]
+async fn run_task(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Query(q): Query<RunTaskQuery>,
+    Component(db): Component<DbConn>,
+    Component(us): Component<UserService>,
+    Component(mut redis): Component<Redis>,
+    Component(mut publisher): Component<TaskPublisher>,
+    Component(quota): Component<QuotaService>,
+) -> Result<Json<RunTaskResp>> {
+    let trigger_source = q.source.unwrap_or(TriggerSource::Manual);
+    if !matches!(trigger_source, TriggerSource::Manual | TriggerSource::Api) {
+        return Err(KnownWebError::bad_request("触发来源只能是手动或接口调用"))?;
+    }
+
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+    if task.deleted {
+        return Err(KnownWebError::not_found("任务不存在"))?;
+    }
+
+    let user = AccountUser::find_by_id(claims.uid)
+        .one(&db)
+        .await
+        .context("find user failed")?
+        .ok_or_else(|| KnownWebError::not_found("用户不存在"))?;
+    let user = us
+        .refresh_user_membership(user)
+        .await
+        .context("refresh membership failed")?;
+
+    // 会员到期降级后任务数可能超出当前版本上限，此时不允许手动运行
+    let task_count = ScraperTask::find()
+        .filter(scraper_task::Column::UserId.eq(claims.uid))
+        .filter(scraper_task::Column::Deleted.eq(false))
+        .count(&db)
+        .await
+        .context("count user tasks failed")?;
+    if task_count > user.edition.task_limit() {
+        return Err(KnownWebError::forbidden(
+            "任务数量超出当前版本上限，请升级版本或删除多余任务后再运行",
+        ))?;
+    }
+
+    let status = quota.status(claims.uid).await?;
+    if let Some(reason) = status.exceeded() {
+        return Err(KnownWebError::forbidden(reason))?;
+    }
+
+    let limit = user.edition.manual_run_limit();
+    let key = format!("task:run:{}", claims.uid);
+    // 只有入队成功才计数，发布失败不占用手动运行次数
+    if rate_limit::window_count(&mut redis, &key).await? >= limit {
+        return Err(KnownWebError::too_many_requests(format!(
+            "当前版本每小时最多手动运行{limit}次，请稍后再试"
+        )))?;
+    }
+
+    let job_id = match crate::task::publish_task(
+        &db,
+        &mut publisher,
+        &quota,
+        &task,
+        trigger_source,
+        Some(claims.uid),
+        None,
+    )
+    .await
+    {
+        Ok(job_id) => job_id,
+        // 并发触发时预检查之后配额可能已被占用
+        Err(e) => match e.downcast_ref::<QuotaExceeded>() {
+            Some(exceeded) => Err(KnownWebError::forbidden(exceeded.0))?,
+            None => Err(e)?,
+        },
+    };
+    let count = rate_limit::incr_window(&mut redis, &key, MANUAL_RUN_WINDOW_SECONDS).await?;
+
+    Ok(Json(RunTaskResp {
+        task_id: task.id,
+        job_id,
+        trigger_source,
+        remaining: limit.saturating_sub(count),
+    }))
+}
+
 /// # 删除任务
 /// @tag task
 #[delete_api("/task/{id}")]
@@ -230,13 +391,6 @@ async fn delete_task(
 ) -> Result<Json<i64>> {
     let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
 
-    // 从调度器中移除关联的 cron 任务
-    if let Some(job_id) = task.job_id {
-        if let Err(e) = sched.remove(&job_id).await {
-            tracing::warn!("移除调度任务失败: {e:?}, job_id={job_id}");
-        }
-    }
-
     scraper_task::ActiveModel {
         id: Set(task.id),
         deleted: Set(true),
@@ -246,6 +400,9 @@ async fn delete_task(
     .await
     .context("save scraper task failed")?;
 
+    // 从本副本的调度器中移除关联的 cron 任务，其他副本在下次对齐时移除
+    unschedule(&sched, task.id).await;
+
     Ok(Json(task.id))
 }
 
@@ -256,26 +413,34 @@ async fn update_task(
     claims: Claims,
     Path(id): Path<i64>,
     Component(db): Component<DbConn>,
+    Component(us): Component<UserService>,
     Valid(Json(body)): Valid<Json<ScraperUpdateTaskReq>>,
 ) -> Result<Json<scraper_task::Model>> {
     let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
 
     let mut body = body;
     if let Some(ref mut d) = body.data {
+        d.validate()?;
+        if let Some(schedule) = &d.schedule {
+            let edition = current_edition(&db, &us, claims.uid).await?;
+            check_schedule(&db, claims.uid, &edition, schedule).await?;
+        }
         if let Some(ref mut dq) = d.data_quality {
             scraper_task::apply_data_quality_dedupe_version(task.data.as_ref(), dq);
         }
     }
 
+    let txn = db.begin().await.context("begin transaction failed")?;
+    TaskRuleRevision::save_rule(&txn, &task, body.rule, claims.uid, body.comment).await?;
     scraper_task::ActiveModel {
         id: Set(task.id),
         data: Set(body.data),
-        rule: Set(body.rule),
         ..Default::default()
     }
-    .save(&db)
+    .save(&txn)
     .await
     .context("save scraper task failed")?;
+    txn.commit().await.context("commit transaction failed")?;
 
     Ok(Json(task))
 }
@@ -300,22 +465,96 @@ async fn update_task_rule(
     claims: Claims,
     Path(id): Path<i64>,
     Component(db): Component<DbConn>,
+    Valid(Query(q)): Valid<Query<UpdateRuleQuery>>,
     Json(rule): Json<Value>,
 ) -> Result<Json<i64>> {
     let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
 
-    scraper_task::ActiveModel {
-        id: Set(task.id),
-        rule: Set(rule),
-        ..Default::default()
-    }
-    .save(&db)
-    .await
-    .context("save scraper task failed")?;
+    let txn = db.begin().await.context("begin transaction failed")?;
+    TaskRuleRevision::save_rule(&txn, &task, rule, claims.uid, q.comment).await?;
+    txn.commit().await.context("commit transaction failed")?;
 
     Ok(Json(task.id))
 }
 
+/// # 查询规则修订历史
+/// @tag task
+#[get_api("/task/{id}/rule/revisions")]
+async fn query_rule_revisions(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    pagination: Pagination,
+) -> Result<Json<Page<task_rule_revision::Model>>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+
+    let page = TaskRuleRevision::find()
+        .filter(task_rule_revision::Column::TaskId.eq(task.id))
+        .order_by_desc(task_rule_revision::Column::Revision)
+        .page(&db, &pagination)
+        .await
+        .context("query rule revisions failed")?;
+
+    Ok(Json(page))
+}
+
+/// # 对比两个规则修订
+/// @tag task
+///
+/// 返回把 `from` 修订变为 `to` 修订的 JSON Patch，`to` 为空时与当前规则对比。
+#[get_api("/task/{id}/rule/diff")]
+async fn diff_rule_revisions(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Query(q): Query<RuleDiffQuery>,
+) -> Result<Json<RuleDiffResp>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+
+    let from = TaskRuleRevision::find_revision(&db, task.id, q.from).await?;
+    let (to, to_rule) = match q.to {
+        Some(to) => (
+            to,
+            TaskRuleRevision::find_revision(&db, task.id, to)
+                .await?
+                .rule,
+        ),
+        None => (task.rule_revision, task.rule),
+    };
+
+    Ok(Json(RuleDiffResp {
+        from: q.from,
+        to,
+        patch: json_patch::diff(&from.rule, &to_rule),
+    }))
+}
+
+/// # 回滚规则到指定修订
+/// @tag task
+///
+/// 以目标修订的规则生成一个新修订，历史修订保持不变。
+#[post_api("/task/{id}/rule/revisions/{revision}/rollback")]
+async fn rollback_rule(
+    claims: Claims,
+    Path((id, revision)): Path<(i64, i32)>,
+    Component(db): Component<DbConn>,
+    Valid(Json(body)): Valid<Json<RollbackRuleReq>>,
+) -> Result<Json<i32>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+    let target = TaskRuleRevision::find_revision(&db, task.id, revision).await?;
+
+    let comment = body
+        .comment
+        .unwrap_or_else(|| format!("回滚到修订{revision}"));
+    let txn = db.begin().await.context("begin transaction failed")?;
+    let saved =
+        TaskRuleRevision::save_rule(&txn, &task, target.rule, claims.uid, Some(comment)).await?;
+    txn.commit().await.context("commit transaction failed")?;
+
+    let current = saved.map(|r| r.revision).unwrap_or(task.rule_revision);
+    Ok(Json(current))
+}
+
 /// # 更新任务名
 /// @tag task
 #[patch_api("/task/{id}/name")]
@@ -348,6 +587,7 @@ async fn update_task_cron(
     claims: Claims,
     Path(id): Path<i64>,
     Component(db): Component<DbConn>,
+    Component(us): Component<UserService>,
     Component(sched): Component<JobScheduler>,
     AppRef(app): AppRef,
     Json(cron): Json<String>,
@@ -370,18 +610,19 @@ async fn update_task_cron(
     })?;
 
     schedule.cron = cron_trim.to_string();
+    let edition = current_edition(&db, &us, claims.uid).await?;
+    check_schedule(&db, claims.uid, &edition, schedule).await?;
 
-    let new_job_id = replace_cron_job(&sched, app, &task, cron_trim).await?;
-
-    scraper_task::ActiveModel {
+    let task = scraper_task::ActiveModel {
         id: Set(task.id),
         data: Set(Some(new_data)),
-        job_id: Set(Some(new_job_id)),
         ..Default::default()
     }
-    .save(&db)
+    .update(&db)
     .await
     .context("save scraper task failed")?;
+    // 已暂停的任务只保存配置，恢复时再注册调度
+    sync_schedule(&sched, app, &task).await?;
 
     Ok(Json(task.id))
 }
@@ -398,21 +639,69 @@ async fn get_task_schedule_info(
     Ok(Json(task.data.as_ref().and_then(|d| d.schedule.clone())))
 }
 
-async fn replace_cron_job(
-    sched: &JobScheduler,
-    app: Arc<summer::app::App>,
-    task: &scraper_task::Model,
-    cron: &str,
-) -> anyhow::Result<summer_job::JobId> {
-    if let Some(old_job_id) = task.job_id {
-        if let Err(e) = sched.remove(&old_job_id).await {
-            tracing::warn!("移除旧调度任务失败: {e:?}, job_id={old_job_id}");
-        }
+/// 校验调度配置：cron 与时区合法，触发间隔不小于当前版本的下限，且代理池可用。
+///
+/// 所有写入 `data.schedule` 的入口都须调用，否则非法调度会在服务重启恢复调度时被注册。
+pub(crate) async fn check_schedule(
+    db: &DbConn,
+    user_id: i64,
+    edition: &ProductEdition,
+    schedule: &ScheduleData,
+) -> Result<()> {
+    schedule.parse_cron()?;
+    schedule.tz()?;
+    schedule.check_interval(edition.clone())?;
+    ProxyPool::check_usable(db, schedule.proxy_id, user_id).await
+}
+
+/// 查询用户当前（已按到期时间刷新的）会员版本
+async fn current_edition(db: &DbConn, us: &UserService, user_id: i64) -> Result<ProductEdition> {
+    let user = AccountUser::find_by_id(user_id)
+        .one(db)
+        .await
+        .context("find user failed")?
+        .ok_or_else(|| KnownWebError::not_found("用户不存在"))?;
+    let user = us
+        .refresh_user_membership(user)
+        .await
+        .context("refresh membership failed")?;
+    Ok(user.edition)
+}
+
+/// # 预览调度的后续触发时间
+/// @tag task
+///
+/// 默认预览任务已保存的调度，传入 `cron` / `timezone` 时按传入值预览（不保存）。
+#[get_api("/task/{id}/schedule/preview")]
+async fn preview_task_schedule(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Valid(Query(q)): Valid<Query<SchedulePreviewQuery>>,
+) -> Result<Json<SchedulePreviewResp>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+
+    let mut schedule = match (task.data.and_then(|d| d.schedule), q.cron) {
+        (Some(saved), None) => saved,
+        (Some(saved), Some(cron)) => ScheduleData { cron, ..saved },
+        (None, Some(cron)) => ScheduleData {
+            cron,
+            proxy_id: 0,
+            ty: ScheduleType::Fast,
+            timezone: None,
+        },
+        (None, None) => Err(KnownWebError::bad_request("任务尚未配置调度"))?,
+    };
+    if q.timezone.is_some() {
+        schedule.timezone = q.timezone;
     }
-    let job = Job::cron_with_data(cron, task.id)
-        .run(crate::task::dispatch_task)
-        .build(app);
-    sched.add(job).await.context("添加调度失败")
+
+    let fire_times = schedule.next_fire_times(Utc::now(), q.count.unwrap_or(5))?;
+    Ok(Json(SchedulePreviewResp {
+        cron: schedule.cron,
+        timezone: schedule.timezone,
+        fire_times,
+    }))
 }
 
 /// # 修改指定任务的调度配置
@@ -422,13 +711,16 @@ async fn update_task_schedule_info(
     claims: Claims,
     Path(id): Path<i64>,
     Component(db): Component<DbConn>,
+    Component(us): Component<UserService>,
     Component(sched): Component<JobScheduler>,
     AppRef(app): AppRef,
-    Json(schedule): Json<ScheduleData>,
+    Json(mut schedule): Json<ScheduleData>,
 ) -> Result<Json<i64>> {
     let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
 
-    let new_job_id = replace_cron_job(&sched, app, &task, &schedule.cron).await?;
+    schedule.cron = schedule.cron.trim().to_string();
+    let edition = current_edition(&db, &us, claims.uid).await?;
+    check_schedule(&db, claims.uid, &edition, &schedule).await?;
 
     let new_data = match task.data {
         Some(mut env) => {
@@ -437,23 +729,269 @@ async fn update_task_schedule_info(
         }
         None => Some(ScraperTaskData {
             schedule: Some(schedule),
-            data_quality: None,
+            ..Default::default()
         }),
     };
 
-    scraper_task::ActiveModel {
+    let task = scraper_task::ActiveModel {
         id: Set(task.id),
         data: Set(new_data),
-        job_id: Set(Some(new_job_id)),
         ..Default::default()
     }
-    .save(&db)
+    .update(&db)
     .await
     .context("save scraper task failed")?;
+    sync_schedule(&sched, app, &task).await?;
+
+    Ok(Json(task.id))
+}
+
+/// # 暂停任务调度
+/// @tag task
+///
+/// 从调度器移除 cron 任务，调度配置保留，可随时恢复。
+#[post_api("/task/{id}/pause")]
+async fn pause_task(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Component(sched): Component<JobScheduler>,
+) -> Result<Json<i64>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+    if task.schedule_state == ScheduleState::Paused {
+        return Err(KnownWebError::bad_request("任务已暂停"))?;
+    }
+
+    pause_schedule(&db, &sched, &task).await?;
 
     Ok(Json(task.id))
 }
 
+/// # 恢复任务调度
+/// @tag task
+#[post_api("/task/{id}/resume")]
+async fn resume_task(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Component(sched): Component<JobScheduler>,
+    AppRef(app): AppRef,
+) -> Result<Json<i64>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+    if task.deleted {
+        return Err(KnownWebError::not_found("任务不存在"))?;
+    }
+    if task.schedule_state == ScheduleState::Active {
+        return Err(KnownWebError::bad_request("任务未暂停"))?;
+    }
+
+    resume_schedule(&db, &sched, app, &task).await?;
+
+    Ok(Json(task.id))
+}
+
+/// # 新增下游依赖
+/// @tag task
+///
+/// 当前任务的实例以 `trigger_on` 状态结束后自动运行下游任务，依赖关系不能成环。
+#[post_api("/task/{id}/dependencies")]
+async fn add_task_dependency(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Json(body): Json<AddDependencyReq>,
+) -> Result<Json<task_dependency::Model>> {
+    if !matches!(
+        body.trigger_on,
+        InstanceStatus::Success | InstanceStatus::Failed
+    ) {
+        return Err(KnownWebError::bad_request(
+            "只能在实例成功或失败时触发下游任务",
+        ))?;
+    }
+    let upstream = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+    let downstream = ScraperTask::find_check_task(&db, body.downstream_task_id, claims.uid).await?;
+    if upstream.deleted || downstream.deleted {
+        return Err(KnownWebError::not_found("任务不存在"))?;
+    }
+    if TaskDependency::creates_cycle(&db, upstream.id, downstream.id).await? {
+        return Err(KnownWebError::bad_request("任务依赖不能成环"))?;
+    }
+
+    let dependency = task_dependency::ActiveModel {
+        user_id: Set(claims.uid),
+        upstream_task_id: Set(upstream.id),
+        downstream_task_id: Set(downstream.id),
+        trigger_on: Set(body.trigger_on),
+        pass_records: Set(body.pass_records),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .context("save task dependency failed")?;
+
+    Ok(Json(dependency))
+}
+
+/// # 删除下游依赖
+/// @tag task
+#[delete_api("/task/{id}/dependencies/{dependency_id}")]
+async fn delete_task_dependency(
+    claims: Claims,
+    Path((id, dependency_id)): Path<(i64, i64)>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<bool>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+
+    let result = TaskDependency::delete_many()
+        .filter(task_dependency::Column::Id.eq(dependency_id))
+        .filter(task_dependency::Column::UpstreamTaskId.eq(task.id))
+        .exec(&db)
+        .await
+        .context("delete task dependency failed")?;
+
+    Ok(Json(result.rows_affected > 0))
+}
+
+/// # 查询任务依赖链
+/// @tag task
+///
+/// 返回与当前任务直接或间接相连的全部上下游任务及依赖。
+#[get_api("/task/{id}/chain")]
+async fn get_task_chain(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<TaskChainResp>> {
+    let task = ScraperTask::find_check_task(&db, id, claims.uid).await?;
+
+    let dependencies = TaskDependency::find_chain(&db, task.id).await?;
+    let mut task_ids = dependencies
+        .iter()
+        .flat_map(|d| [d.upstream_task_id, d.downstream_task_id])
+        .collect_vec();
+    task_ids.push(task.id);
+    let tasks = ScraperTask::find()
+        .filter(scraper_task::Column::Id.is_in(task_ids.into_iter().unique()))
+        .order_by_asc(scraper_task::Column::Id)
+        .all(&db)
+        .await
+        .context("query chain tasks failed")?
+        .into_iter()
+        .map(|t| ChainTask {
+            id: t.id,
+            name: t.name,
+            schedule_state: t.schedule_state,
+        })
+        .collect();
+
+    Ok(Json(TaskChainResp {
+        tasks,
+        dependencies,
+    }))
+}
+
+/// # 查询实例的触发链路
+/// @tag task
+#[get_api("/instance/{id}/chain")]
+async fn get_instance_chain(
+    claims: Claims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<InstanceChainResp>> {
+    let instance = TaskInstance::find_by_id(id)
+        .one(&db)
+        .await
+        .context("find task instance failed")?
+        .ok_or_else(|| KnownWebError::not_found("实例不存在"))?;
+    ScraperTask::find_check_task(&db, instance.task_id, claims.uid).await?;
+
+    let dispatch = TaskDispatch::find()
+        .filter(task_dispatch::Column::InstanceId.eq(instance.id))
+        .one(&db)
+        .await
+        .context("find instance dispatch failed")?;
+    let downstream = TaskDispatch::find()
+        .filter(task_dispatch::Column::UpstreamInstanceId.eq(instance.id))
+        .order_by_asc(task_dispatch::Column::Id)
+        .all(&db)
+        .await
+        .context("query downstream dispatches failed")?;
+
+    Ok(Json(InstanceChainResp {
+        dispatch,
+        downstream,
+    }))
+}
+
+/// # 取消运行中的实例
+/// @tag task
+///
+/// 实例标记为已取消，并通过 Redis 通知执行该实例的 worker 停止。
+#[post_api("/task/{task_id}/instance/{instance_id}/cancel")]
+async fn cancel_task_instance(
+    claims: Claims,
+    Path((task_id, instance_id)): Path<(i64, i64)>,
+    Component(db): Component<DbConn>,
+    Component(mut redis): Component<Redis>,
+) -> Result<Json<task_instance::Model>> {
+    let task = ScraperTask::find_check_task(&db, task_id, claims.uid).await?;
+    let instance = TaskInstance::find_by_id(instance_id)
+        .one(&db)
+        .await
+        .context("find task instance failed")?
+        .ok_or_else(|| KnownWebError::not_found("实例不存在"))?;
+    if instance.task_id != task.id {
+        return Err(KnownWebError::bad_request("实例与任务不匹配"))?;
+    }
+    if !TaskInstance::cancel_if_running(&db, instance.id).await? {
+        return Err(KnownWebError::bad_request("实例已结束"))?;
+    }
+
+    // 实例已标记取消，worker 通过心跳也能感知，通知失败不影响结果
+    if let Err(e) = signal_cancel(&mut redis, task.id, instance.id).await {
+        tracing::warn!("{e:?}");
+    }
+    if let Err(e) = TaskLogService::publish_logs_eof(&mut redis, task.id, instance.id).await {
+        tracing::warn!("{e:?}");
+    }
+
+    let instance = TaskInstance::find_by_id(instance.id)
+        .one(&db)
+        .await
+        .context("find task instance failed")?
+        .ok_or_else(|| KnownWebError::not_found("实例不存在"))?;
+    Ok(Json(instance))
+}
+
+/// # 取消排队中的运行
+/// @tag task
+///
+/// `job_id` 为立即运行接口返回的队列任务ID，只能取消尚未开始的运行。
+#[post_api("/task/{task_id}/job/{job_id}/cancel")]
+async fn cancel_task_job(
+    claims: Claims,
+    Path((task_id, job_id)): Path<(i64, String)>,
+    Component(db): Component<DbConn>,
+    Component(mut redis): Component<Redis>,
+    Component(publisher): Component<TaskPublisher>,
+) -> Result<Json<task_dispatch::Model>> {
+    let task = ScraperTask::find_check_task(&db, task_id, claims.uid).await?;
+    let dispatch = TaskDispatch::cancel_pending(&db, task.id, &job_id)
+        .await?
+        .ok_or_else(|| KnownWebError::bad_request("运行已开始或不存在"))?;
+    // 取消以数据库标记为准，出队失败时 worker 取到任务后创建实例会被拒绝
+    // 从入队时的队列移除，任务调度类型可能已修改；历史记录没有队列名时按当前调度类型
+    let queue = dispatch
+        .queue
+        .clone()
+        .unwrap_or_else(|| publisher.queue(&task.schedule_type()).to_string());
+    if let Err(e) = publisher.discard(&mut redis, &queue, &job_id).await {
+        tracing::warn!("从队列移除已取消的任务#{task_id}失败: {e:?}");
+    }
+    Ok(Json(dispatch))
+}
+
 /// # 某次任务实例的执行日志（SSE）
 ///
 /// - 若 `task_instance.log_key` 已写入且服务端 `[s3]` 配置完整：从对象存储拉取 NDJSON，按行推送后结束连接。
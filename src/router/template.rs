@@ -1,13 +1,18 @@
 use crate::{
     model::{
         favorite,
-        prelude::{Favorite, TaskTemplate},
-        task_template,
+        prelude::{AccountUser, Favorite, ProxyPool, TaskTemplate},
+        proxy_pool::DEFAULT_PROXY_POOL,
+        scraper_task::{self, ScraperTaskData},
+        sea_orm_active_enums::ScheduleState,
+        task_template::{self, resolve_params, substitute_params},
     },
+    service::user::UserService,
     utils::jwt::{Claims, OptionalClaims},
-    views::template::{ListTemplateResp, TemplateQuery},
+    views::template::{InstantiateTemplateReq, ListTemplateResp, TemplateQuery},
 };
 use anyhow::Context;
+use axum_valid::Valid;
 use itertools::Itertools;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, ExprTrait, QueryFilter, QueryOrder, Set,
@@ -149,3 +154,78 @@ async fn delete_favorite(
     txn.commit().await.context("commit transaction failed")?;
     Ok(Json(result.rows_affected > 0))
 }
+
+/// # 从模板创建任务
+/// @tag template
+///
+/// 校验参数并代入模板规则，模板要求的版本高于当前会员版本时不可使用。
+#[post_api("/template/{template_id}/instantiate")]
+async fn instantiate(
+    Path(template_id): Path<i64>,
+    claims: Claims,
+    Component(db): Component<DbConn>,
+    Component(us): Component<UserService>,
+    Valid(Json(body)): Valid<Json<InstantiateTemplateReq>>,
+) -> Result<Json<scraper_task::Model>> {
+    let template = TaskTemplate::find_by_id(template_id)
+        .one(&db)
+        .await
+        .context("find task template failed")?
+        .ok_or_else(|| KnownWebError::not_found("模板不存在"))?;
+
+    let user = AccountUser::find_by_id(claims.uid)
+        .one(&db)
+        .await
+        .context("find user failed")?
+        .ok_or_else(|| KnownWebError::not_found("用户不存在"))?;
+    let user = us
+        .refresh_user_membership(user)
+        .await
+        .context("refresh membership failed")?;
+    if template.edition.rank() > user.edition.rank() {
+        return Err(KnownWebError::forbidden(format!(
+            "该模板需要{}及以上版本，请先升级",
+            template.edition
+        )))?;
+    }
+    super::task::check_task_limit(&db, claims.uid, Some(user.edition)).await?;
+
+    let params = resolve_params(&template.param_defs()?, body.params)?;
+    let rule = substitute_params(&template.rule, &params);
+    let mut data = match &template.data {
+        serde_json::Value::Null => None,
+        data => Some(
+            serde_json::from_value::<ScraperTaskData>(data.clone()).context("模板任务配置无效")?,
+        ),
+    };
+    // 模板引用了当前用户无权使用的代理池时回退到系统默认代理
+    if let Some(schedule) = data.as_mut().and_then(|d| d.schedule.as_mut()) {
+        if ProxyPool::check_usable(&db, schedule.proxy_id, claims.uid)
+            .await
+            .is_err()
+        {
+            schedule.proxy_id = DEFAULT_PROXY_POOL;
+        }
+        super::task::check_schedule(&db, claims.uid, &user.edition, schedule).await?;
+    }
+    // 模板自带调度时先暂停，用户确认调度配置后再恢复
+    let schedule_state = match data.as_ref().and_then(|d| d.schedule.as_ref()) {
+        Some(_) => ScheduleState::Paused,
+        None => ScheduleState::Active,
+    };
+
+    let task = scraper_task::ActiveModel {
+        user_id: Set(claims.uid),
+        name: Set(body.name.unwrap_or_else(|| template.name.clone())),
+        rule: Set(rule),
+        data: Set(data),
+        schedule_state: Set(schedule_state),
+        template_id: Set(Some(template.id)),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await
+    .context("save scraper task failed")?;
+
+    Ok(Json(task))
+}
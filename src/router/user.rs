@@ -1,12 +1,13 @@
 use crate::{
-    config::mail::Email,
+    config::{mail::Email, trial::TrialConfig},
     model::{
-        account_user,
-        prelude::AccountUser,
+        account_user, pay_order,
+        prelude::{AccountUser, MembershipTrial},
         sea_orm_active_enums::{CreditOperation, ProductEdition},
     },
     router::{admin::marketing as marketing_router, ClientIp},
     service::credit::CreditService,
+    service::quota::QuotaService,
     service::user::UserService,
     utils::{
         jwt::{self, Claims},
@@ -16,8 +17,9 @@ use crate::{
     views::{
         token::UserToken,
         user::{
-            CheckInResp, CreditLogResp, RegisterReq, ResetPasswdReq, SendEmailReq, SetNameReq,
-            UnsubscribeMarketingQuery, UserResp, ValidateCodeEmailTemplate,
+            CheckInResp, CreditLogResp, QuotaResp, RegisterReq, ResetPasswdReq, SendEmailReq,
+            SetNameReq, StartTrialReq, TrialResp, UnsubscribeMarketingQuery, UserResp,
+            ValidateCodeEmailTemplate,
         },
     },
 };
@@ -445,3 +447,114 @@ async fn get_credit_logs(
     let resp: Vec<CreditLogResp> = logs.into_iter().map(|log| log.into()).collect();
     Ok(Json(resp))
 }
+
+/// # 试用状态
+/// @tag user
+#[get_api("/user/trial")]
+async fn trial_status(
+    claims: Claims,
+    Component(db): Component<DbConn>,
+    Config(trial): Config<TrialConfig>,
+) -> Result<Json<TrialResp>> {
+    let user = AccountUser::find_by_id(claims.uid)
+        .one(&db)
+        .await
+        .with_context(|| format!("find user by id#{}", claims.uid))?
+        .ok_or_else(|| KnownWebError::not_found("用户不存在"))?;
+    let used = MembershipTrial::find_by_user(&db, user.id).await?;
+    let eligible =
+        trial.enable && used.is_none() && trial_ineligible_reason(&db, &user).await?.is_none();
+
+    Ok(Json(TrialResp {
+        enable: trial.enable,
+        eligible,
+        edition: trial.edition,
+        days: trial.days,
+        expired_at: used.as_ref().map(|t| t.expired_at),
+        converted: used.is_some_and(|t| t.converted_order_id.is_some()),
+    }))
+}
+
+/// # 开通试用
+/// @tag user
+#[post_api("/user/trial")]
+async fn start_trial(
+    claims: Claims,
+    ClientIp(client_ip): ClientIp,
+    Component(db): Component<DbConn>,
+    Component(us): Component<UserService>,
+    Config(trial): Config<TrialConfig>,
+    Valid(Json(req)): Valid<Json<StartTrialReq>>,
+) -> Result<Json<TrialResp>> {
+    if !trial.enable {
+        return Err(KnownWebError::bad_request("试用活动未开放"))?;
+    }
+    let user = AccountUser::find_by_id(claims.uid)
+        .one(&db)
+        .await
+        .with_context(|| format!("find user by id#{}", claims.uid))?
+        .ok_or_else(|| KnownWebError::not_found("用户不存在"))?;
+    let user = us
+        .refresh_user_membership(user)
+        .await
+        .context("refresh membership failed")?;
+
+    if let Some(reason) = trial_ineligible_reason(&db, &user).await? {
+        return Err(KnownWebError::bad_request(reason))?;
+    }
+    let email = user.email.to_lowercase();
+    if MembershipTrial::find_used(&db, user.id, &email, &req.fingerprint)
+        .await?
+        .is_some()
+    {
+        return Err(KnownWebError::bad_request("该账号或设备已试用过"))?;
+    }
+
+    let t = us
+        .activate_trial(
+            &user,
+            trial.edition,
+            trial.days,
+            req.fingerprint,
+            Some(client_ip.0.into()),
+        )
+        .await?;
+
+    Ok(Json(TrialResp {
+        enable: true,
+        eligible: false,
+        edition: t.edition,
+        days: trial.days,
+        expired_at: Some(t.expired_at),
+        converted: false,
+    }))
+}
+
+/// 付费会员或购买过会员的用户不能试用
+async fn trial_ineligible_reason(
+    db: &DbConn,
+    user: &account_user::Model,
+) -> anyhow::Result<Option<&'static str>> {
+    if user.edition != ProductEdition::L0 {
+        return Ok(Some("当前已是付费会员"));
+    }
+    if pay_order::Entity::find_last_fulfilled(db, user.id)
+        .await?
+        .is_some()
+    {
+        return Ok(Some("已购买过会员，不能试用"));
+    }
+    Ok(None)
+}
+
+/// # 运行配额
+/// @tag user
+///
+/// 当前版本的同时运行实例数、今日运行次数和本月采集记录数的使用情况。
+#[get_api("/user/quota")]
+async fn quota_status(
+    claims: Claims,
+    Component(quota): Component<QuotaService>,
+) -> Result<Json<QuotaResp>> {
+    Ok(Json(quota.status(claims.uid).await?))
+}
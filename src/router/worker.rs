@@ -0,0 +1,250 @@
+//! worker 接口：worker 凭 id + secret 换取令牌后创建实例、上报心跳和结束实例，
+//! 不再直接写数据库。接口仅供 autowds-instance 使用，不暴露 openapi 文档。
+
+use crate::model::prelude::{Proxy, ScraperTask, TaskDispatch, TaskInstance, Worker};
+use crate::model::sea_orm_active_enums::InstanceStatus;
+use crate::model::{proxy, task_dispatch, task_instance};
+use crate::service::proxy::ProxyService;
+use crate::utils::jwt::{self, WorkerClaims, WORKER_TOKEN_TTL_SECONDS};
+use crate::views::worker::{
+    CreateInstanceReq, FinishInstanceReq, HeartbeatReq, HeartbeatResp, ProxyHealthReq,
+    WorkerInstanceResp, WorkerProxyPoolResp, WorkerTaskResp, WorkerTokenReq, WorkerTokenResp,
+};
+use anyhow::Context;
+use axum_valid::Valid;
+use chrono::Local;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, QueryFilter, Set};
+use summer_web::axum::Json;
+use summer_web::error::{KnownWebError, Result};
+use summer_web::extractor::{Component, Path};
+use summer_web::{get, post};
+
+/// 换取 worker 访问令牌
+#[post("/worker/token")]
+async fn worker_token(
+    Component(db): Component<DbConn>,
+    Valid(Json(req)): Valid<Json<WorkerTokenReq>>,
+) -> Result<Json<WorkerTokenResp>> {
+    let worker = Worker::find_by_id(req.worker_id)
+        .one(&db)
+        .await
+        .context("find worker failed")?
+        .filter(|w| w.verify_secret(&req.secret))
+        .ok_or_else(|| KnownWebError::unauthorized("worker不存在或密钥错误"))?;
+    if !worker.enabled {
+        return Err(KnownWebError::forbidden("worker已禁用"))?;
+    }
+    Worker::touch(&db, worker.id).await?;
+
+    Ok(Json(WorkerTokenResp {
+        token: jwt::encode_worker(worker.id, worker.secret_version())?,
+        expires_in: WORKER_TOKEN_TTL_SECONDS,
+    }))
+}
+
+/// 获取任务规则
+#[get("/worker/tasks/{id}")]
+async fn get_worker_task(
+    worker: WorkerClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+) -> Result<Json<WorkerTaskResp>> {
+    authorize_worker(&db, &worker).await?;
+    let task = ScraperTask::find_by_id(id)
+        .one(&db)
+        .await
+        .context("find scraper task failed")?
+        .ok_or_else(|| KnownWebError::not_found("任务不存在"))?;
+
+    Ok(Json(WorkerTaskResp {
+        id: task.id,
+        name: task.name,
+        rule: task.rule,
+        rule_revision: task.rule_revision,
+        data: task.data,
+    }))
+}
+
+/// 取到队列任务后创建实例
+#[post("/worker/instances")]
+async fn create_instance(
+    worker: WorkerClaims,
+    Component(db): Component<DbConn>,
+    Valid(Json(req)): Valid<Json<CreateInstanceReq>>,
+) -> Result<Json<WorkerInstanceResp>> {
+    authorize_worker(&db, &worker).await?;
+    let task = ScraperTask::find_by_id(req.task_id)
+        .one(&db)
+        .await
+        .context("find scraper task failed")?
+        .ok_or_else(|| KnownWebError::not_found("任务不存在"))?;
+    if task.deleted {
+        return Err(KnownWebError::bad_request("任务已删除"))?;
+    }
+    // 触发来源、重试次数、规则修订由数据库触发器在插入时填入，
+    // 对应派发已取消时触发器拒绝插入
+    let inserted = task_instance::ActiveModel {
+        task_id: Set(task.id),
+        status: Set(InstanceStatus::Running),
+        data_count: Set(0),
+        worker_id: Set(Some(worker.worker_id)),
+        job_id: Set(req.job_id.clone()),
+        heartbeat: Set(Some(Local::now().naive_local())),
+        ..Default::default()
+    }
+    .insert(&db)
+    .await;
+    let instance = match inserted {
+        Ok(instance) => instance,
+        Err(e) => {
+            if dispatch_cancelled(&db, task.id, req.job_id.as_deref()).await? {
+                return Err(KnownWebError::bad_request("运行已取消"))?;
+            }
+            Err(e).context("create task instance failed")?
+        }
+    };
+
+    let dispatch = TaskDispatch::find()
+        .filter(task_dispatch::Column::InstanceId.eq(instance.id))
+        .one(&db)
+        .await
+        .context("find instance dispatch failed")?;
+
+    Ok(Json(WorkerInstanceResp { instance, dispatch }))
+}
+
+/// 上报进度和心跳
+#[post("/worker/instances/{id}/heartbeat")]
+async fn instance_heartbeat(
+    worker: WorkerClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Valid(Json(req)): Valid<Json<HeartbeatReq>>,
+) -> Result<Json<HeartbeatResp>> {
+    authorize_worker(&db, &worker).await?;
+    let instance = find_worker_instance(&db, id, worker.worker_id).await?;
+    if instance.status == InstanceStatus::Running
+        && TaskInstance::heartbeat(&db, id, worker.worker_id, req.pages, req.records).await?
+    {
+        return Ok(Json(HeartbeatResp {
+            status: InstanceStatus::Running,
+        }));
+    }
+
+    // 实例已被标记结束（如心跳超时），worker 应停止执行
+    let instance = find_worker_instance(&db, id, worker.worker_id).await?;
+    Ok(Json(HeartbeatResp {
+        status: instance.status,
+    }))
+}
+
+/// 结束实例
+#[post("/worker/instances/{id}/finish")]
+async fn finish_instance(
+    worker: WorkerClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Valid(Json(req)): Valid<Json<FinishInstanceReq>>,
+) -> Result<Json<task_instance::Model>> {
+    authorize_worker(&db, &worker).await?;
+    if !matches!(req.status, InstanceStatus::Success | InstanceStatus::Failed) {
+        return Err(KnownWebError::bad_request("结束状态只能是成功或失败"))?;
+    }
+    find_worker_instance(&db, id, worker.worker_id).await?;
+
+    let finished = TaskInstance::finish(
+        &db,
+        id,
+        worker.worker_id,
+        req.status,
+        req.data_count,
+        req.log_key,
+        req.error_message,
+    )
+    .await?;
+    if !finished {
+        return Err(KnownWebError::bad_request("实例已结束"))?;
+    }
+
+    Ok(Json(find_worker_instance(&db, id, worker.worker_id).await?))
+}
+
+/// 解析任务调度引用的代理池
+#[get("/worker/proxy-pools/{id}")]
+async fn resolve_proxy_pool(
+    worker: WorkerClaims,
+    Path(id): Path<i32>,
+    Component(db): Component<DbConn>,
+    Component(ps): Component<ProxyService>,
+) -> Result<Json<WorkerProxyPoolResp>> {
+    authorize_worker(&db, &worker).await?;
+    Ok(Json(ps.resolve_pool(id).await?))
+}
+
+/// 上报代理健康检查结果
+#[post("/worker/proxies/{id}/health")]
+async fn report_proxy_health(
+    worker: WorkerClaims,
+    Path(id): Path<i64>,
+    Component(db): Component<DbConn>,
+    Valid(Json(req)): Valid<Json<ProxyHealthReq>>,
+) -> Result<Json<bool>> {
+    authorize_worker(&db, &worker).await?;
+    let result = Proxy::update_many()
+        .set(proxy::ActiveModel {
+            last_checked: Set(Some(Local::now().naive_local())),
+            last_check_ok: Set(Some(req.ok)),
+            last_latency_ms: Set(req.latency_ms),
+            last_error: Set(req.error),
+            ..Default::default()
+        })
+        .filter(proxy::Column::Id.eq(id))
+        .exec(&db)
+        .await
+        .context("update proxy health failed")?;
+    Ok(Json(result.rows_affected > 0))
+}
+
+/// 实例插入失败后判断是否因派发已取消被触发器拒绝
+async fn dispatch_cancelled(db: &DbConn, task_id: i64, job_id: Option<&str>) -> Result<bool> {
+    let mut query = TaskDispatch::find()
+        .filter(task_dispatch::Column::TaskId.eq(task_id))
+        .filter(task_dispatch::Column::InstanceId.is_null())
+        .filter(task_dispatch::Column::Cancelled.eq(true));
+    if let Some(job_id) = job_id {
+        query = query.filter(task_dispatch::Column::JobId.eq(job_id));
+    }
+    let cancelled = query.one(db).await.context("find task dispatch failed")?;
+    Ok(cancelled.is_some())
+}
+
+/// 校验令牌对应的 worker 仍然存在、未被禁用且密钥未被重置，
+/// 使禁用和重置密钥立即吊销已签发的令牌
+async fn authorize_worker(db: &DbConn, claims: &WorkerClaims) -> Result<()> {
+    let worker = Worker::find_by_id(claims.worker_id)
+        .one(db)
+        .await
+        .context("find worker failed")?
+        .filter(|w| w.secret_version() == claims.secret_version)
+        .ok_or_else(|| KnownWebError::unauthorized("invalid token"))?;
+    if !worker.enabled {
+        return Err(KnownWebError::forbidden("worker已禁用"))?;
+    }
+    Ok(())
+}
+
+async fn find_worker_instance(
+    db: &DbConn,
+    id: i64,
+    worker_id: i64,
+) -> Result<task_instance::Model> {
+    let instance = TaskInstance::find_by_id(id)
+        .one(db)
+        .await
+        .context("find task instance failed")?
+        .ok_or_else(|| KnownWebError::not_found("实例不存在"))?;
+    if instance.worker_id != Some(worker_id) {
+        return Err(KnownWebError::forbidden("实例不属于当前worker"))?;
+    }
+    Ok(instance)
+}
@@ -8,8 +8,36 @@ use anyhow::{anyhow, Context, Result};
 use duckdb::Connection;
 use serde_json::{Map, Number, Value};
 use std::collections::{HashMap, HashSet, VecDeque};
+use summer_sqlx::{sqlx, ConnectPool};
 use tempfile::tempdir;
 
+/// 清洗流程表由接口按需创建，兼容未执行最新 DDL 的环境
+pub async fn ensure_pipeline_table(pool: &ConnectPool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS data_clean_pipeline (
+            id bigserial PRIMARY KEY,
+            user_id bigint NOT NULL,
+            store_id varchar(64) NOT NULL,
+            name varchar(80) NOT NULL,
+            definition jsonb NOT NULL,
+            created_at timestamp NOT NULL,
+            modified_at timestamp NOT NULL
+        )",
+    )
+    .execute(pool)
+    .await
+    .context("初始化数据清洗流程表失败")?;
+
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_data_clean_pipeline_user_store_modified \
+         ON data_clean_pipeline(user_id, store_id, modified_at DESC)",
+    )
+    .execute(pool)
+    .await
+    .context("初始化数据清洗流程索引失败")?;
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct RuleFieldProjection {
     pub output_name: String,
@@ -0,0 +1,235 @@
+use crate::views::pay::{
+    FinanceReport, FinanceSummary, Granularity, RevenueBreakdown, RevenueBucket,
+};
+use anyhow::Context;
+use chrono::{Days, Local, NaiveDate, NaiveTime};
+use sea_orm::{prelude::DateTime, ConnectionTrait, DbConn, FromQueryResult, Statement};
+use summer::plugin::service::Service;
+use summer_web::error::{KnownWebError, Result};
+
+/// 收入/会员指标统计。
+///
+/// 收入只统计 `paid` 状态的订单，全额退款的订单不计入，部分退款的订单按扣除退款后的金额计入；
+/// MRR 按订单覆盖周期近似：周期末仍在订单有效期内的新购/续费/升级订单，金额折算为 30 天；
+/// 流失会员不含试用到期未付费的用户。
+#[derive(Clone, Service)]
+pub struct FinanceService {
+    #[inject(component)]
+    db: DbConn,
+}
+
+impl FinanceService {
+    pub async fn report(
+        &self,
+        start_date: Option<NaiveDate>,
+        end_date: Option<NaiveDate>,
+        granularity: Granularity,
+    ) -> Result<FinanceReport> {
+        // 默认最近30天
+        let end_date = end_date.unwrap_or_else(|| Local::now().date_naive());
+        let start_date = match start_date {
+            Some(start_date) => start_date,
+            None => end_date
+                .checked_sub_days(Days::new(30))
+                .ok_or_else(|| KnownWebError::bad_request("结束日期超出范围"))?,
+        };
+        if start_date > end_date {
+            Err(KnownWebError::bad_request("开始日期不能晚于结束日期"))?;
+        }
+        let max_days = granularity.max_days();
+        if (end_date - start_date).num_days() >= max_days {
+            Err(KnownWebError::bad_request(format!(
+                "按{}统计时时间跨度不能超过{max_days}天",
+                granularity.title()
+            )))?;
+        }
+        let start = start_date.and_time(NaiveTime::MIN);
+        let end = end_date
+            .checked_add_days(Days::new(1))
+            .ok_or_else(|| KnownWebError::bad_request("结束日期超出范围"))?
+            .and_time(NaiveTime::MIN);
+        let now = Local::now().naive_local();
+        let backend = self.db.get_database_backend();
+
+        let series = RevenueBucket::find_by_statement(Statement::from_sql_and_values(
+            backend,
+            r#"
+            WITH buckets AS (
+                SELECT generate_series(
+                    date_trunc($3, $1::timestamp),
+                    date_trunc($3, $2::timestamp - interval '1 microsecond'),
+                    ('1 ' || $3)::interval
+                ) AS bucket
+            ),
+            paid AS (
+                SELECT
+                    id, user_id, kind, level, amount - refunded_amount AS amount, confirm,
+                    row_number() OVER (PARTITION BY user_id, kind ORDER BY confirm, id) AS seq
+                FROM pay_order
+                WHERE status = 'paid' AND confirm IS NOT NULL
+            ),
+            revenue AS (
+                SELECT
+                    date_trunc($3, confirm) AS bucket,
+                    SUM(amount) AS revenue,
+                    SUM(amount) FILTER (WHERE kind = 'purchase' AND seq = 1) AS new_revenue,
+                    SUM(amount) FILTER (WHERE kind = 'purchase' AND seq > 1) AS renewal_revenue,
+                    SUM(amount) FILTER (WHERE kind = 'upgrade') AS upgrade_revenue,
+                    COUNT(*) AS order_count,
+                    COUNT(DISTINCT user_id) AS paying_users
+                FROM paid
+                WHERE confirm >= $1 AND confirm < $2
+                GROUP BY 1
+            ),
+            churn AS (
+                SELECT date_trunc($3, u.vip_expired_at) AS bucket, COUNT(*) AS churned
+                FROM account_user u
+                WHERE u.vip_expired_at >= $1 AND u.vip_expired_at < LEAST($2, $4)
+                  AND NOT EXISTS (
+                      SELECT 1 FROM membership_trial t
+                      WHERE t.user_id = u.id AND t.expired_at = u.vip_expired_at
+                  )
+                GROUP BY 1
+            )
+            SELECT
+                b.bucket,
+                COALESCE(r.revenue, 0)::bigint AS revenue,
+                COALESCE(r.new_revenue, 0)::bigint AS new_revenue,
+                COALESCE(r.renewal_revenue, 0)::bigint AS renewal_revenue,
+                COALESCE(r.upgrade_revenue, 0)::bigint AS upgrade_revenue,
+                COALESCE(r.order_count, 0) AS order_count,
+                COALESCE(r.paying_users, 0) AS paying_users,
+                COALESCE(c.churned, 0) AS churned,
+                (
+                    SELECT COALESCE(SUM(
+                        p.amount * 30.0 / CASE p.level WHEN 'monthly' THEN 30 ELSE 365 END
+                    ), 0)::bigint
+                    FROM paid p
+                    WHERE p.kind IN ('purchase', 'upgrade')
+                      AND p.confirm < LEAST(b.bucket + ('1 ' || $3)::interval, $4)
+                      AND p.confirm + CASE p.level
+                            WHEN 'monthly' THEN interval '30 days'
+                            ELSE interval '365 days'
+                          END >= LEAST(b.bucket + ('1 ' || $3)::interval, $4)
+                ) AS mrr
+            FROM buckets b
+            LEFT JOIN revenue r ON r.bucket = b.bucket
+            LEFT JOIN churn c ON c.bucket = b.bucket
+            ORDER BY b.bucket
+            "#,
+            vec![
+                start.into(),
+                end.into(),
+                granularity.unit().into(),
+                now.into(),
+            ],
+        ))
+        .all(&self.db)
+        .await
+        .context("RevenueBucket execute failed")?;
+
+        let by_provider = self.breakdown("pay_from", start, end).await?;
+        let by_edition = self.breakdown("edition", start, end).await?;
+
+        let paying_users: i64 = self
+            .db
+            .query_one_raw(Statement::from_sql_and_values(
+                backend,
+                r#"
+                SELECT COUNT(DISTINCT user_id)::bigint AS count
+                FROM pay_order
+                WHERE status = 'paid' AND confirm >= $1 AND confirm < $2
+                "#,
+                vec![start.into(), end.into()],
+            ))
+            .await
+            .context("统计付费用户数失败")?
+            .and_then(|row| row.try_get("", "count").ok())
+            .unwrap_or_default();
+
+        let sum = |f: fn(&RevenueBucket) -> i64| series.iter().map(f).sum::<i64>();
+        let revenue = sum(|b| b.revenue);
+        let mrr = series.last().map(|b| b.mrr).unwrap_or_default();
+        let summary = FinanceSummary {
+            revenue,
+            new_revenue: sum(|b| b.new_revenue),
+            renewal_revenue: sum(|b| b.renewal_revenue),
+            upgrade_revenue: sum(|b| b.upgrade_revenue),
+            paying_users,
+            arpu: if paying_users > 0 {
+                revenue / paying_users
+            } else {
+                0
+            },
+            churned: sum(|b| b.churned),
+            mrr,
+            arr: mrr * 12,
+        };
+
+        Ok(FinanceReport {
+            start_date,
+            end_date,
+            summary,
+            series,
+            by_provider,
+            by_edition,
+        })
+    }
+
+    /// 按订单列（`pay_from`/`edition`）汇总收入，列名只允许内部传入
+    async fn breakdown(
+        &self,
+        column: &'static str,
+        start: DateTime,
+        end: DateTime,
+    ) -> anyhow::Result<Vec<RevenueBreakdown>> {
+        let sql = format!(
+            r#"
+            SELECT {column}::text AS key, COUNT(*) AS order_count, SUM(amount - refunded_amount)::bigint AS revenue
+            FROM pay_order
+            WHERE status = 'paid' AND confirm >= $1 AND confirm < $2
+            GROUP BY {column}
+            ORDER BY revenue DESC
+            "#
+        );
+        RevenueBreakdown::find_by_statement(Statement::from_sql_and_values(
+            self.db.get_database_backend(),
+            sql,
+            vec![start.into(), end.into()],
+        ))
+        .all(&self.db)
+        .await
+        .with_context(|| format!("revenue breakdown by {column} failed"))
+    }
+}
+
+/// 导出统计周期明细为 CSV
+pub fn report_to_csv(report: &FinanceReport) -> String {
+    let mut lines = vec![[
+        "bucket",
+        "revenue",
+        "new_revenue",
+        "renewal_revenue",
+        "upgrade_revenue",
+        "order_count",
+        "paying_users",
+        "churned",
+        "mrr",
+    ]
+    .join(",")];
+    for b in &report.series {
+        lines.push(format!(
+            "{},{},{},{},{},{},{},{},{}",
+            b.bucket.format("%Y-%m-%d"),
+            b.revenue,
+            b.new_revenue,
+            b.renewal_revenue,
+            b.upgrade_revenue,
+            b.order_count,
+            b.paying_users,
+            b.churned,
+            b.mrr
+        ));
+    }
+    lines.join("\n")
+}
@@ -1,6 +1,12 @@
 pub mod credit;
 pub mod data_clean;
+pub mod finance;
 pub mod pay;
+pub mod pay_notify;
+pub mod pay_status;
+pub mod proxy;
+pub mod quota;
+pub mod task_bundle;
 pub mod task_log;
 pub mod tencent_ses;
 pub mod user;
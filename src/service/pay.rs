@@ -1,593 +1,538 @@
 use crate::{
     config::pay::PayConfig,
     model::{
-        pay_order,
-        sea_orm_active_enums::{OrderLevel, OrderStatus, PayFrom, ProductEdition},
+        account_user, pay_order,
+        prelude::AccountUser,
+        sea_orm_active_enums::{OrderKind, OrderLevel, OrderStatus, PayFrom, ProductEdition},
     },
-    plugin::pay::{Alipay, PaddleClient, WechatPayClient},
+    service::pay_status::pay_status_channel,
+    views::pay::{PayMode, PayStatusEvent, PaymentAction, UpgradeQuote},
 };
-use alipay_sdk_rust::{biz, response::TradePrecreateResponse};
 use anyhow::{anyhow, Context};
-use chrono::Local;
-use hmac::{Hmac, KeyInit, Mac};
-use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
-use sea_orm::{prelude::DateTime, ActiveModelTrait, ActiveValue::Set, DbConn, EntityTrait};
-use serde::{Deserialize, Serialize};
-use serde_json::{json, Value};
-use sha2::Sha256;
-use std::{collections::HashMap, env, fs::File, io::Write as _, path::Path};
-use subtle::ConstantTimeEq;
-use summer::{plugin::service::Service, tracing};
-use wechat_pay_rust_sdk::{
-    model::{NativeParams, WechatPayDecodeData, WechatPayNotify},
-    pay::PayNotifyTrait,
-    response::{NativeResponse, ResponseTrait},
+use chrono::{Duration, Local};
+use sea_orm::{
+    prelude::DateTime,
+    ActiveModelTrait,
+    ActiveValue::{NotSet, Set},
+    DbConn, EntityTrait,
 };
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+use summer::{async_trait, plugin::service::Service, tracing};
+use summer_redis::{redis::AsyncCommands, Redis};
+use summer_web::{
+    axum::http::HeaderMap,
+    error::{KnownWebError, Result},
+};
+
+mod alipay;
+mod mock;
+mod paddle;
+mod wechat;
+
+pub use alipay::AlipayProvider;
+pub use mock::MockProvider;
+pub use paddle::PaddleProvider;
+pub use wechat::WechatProvider;
 
 const PAY_OUT_TRADE_PREFIX: &str = "AWDS";
 const PAY_OUT_TRADE_TIME_LEN: usize = 14;
-const PADDLE_SIGNATURE_PREFIX: &str = "h1=";
-const PADDLE_SIGNATURE_TS_PREFIX: &str = "ts=";
+/// 升级订单最低支付金额（分）
+const MIN_UPGRADE_AMOUNT: i32 = 100;
+
+/// 客户端的支付方式及各方式所需的参数
+#[derive(Debug, Clone, Default)]
+pub struct Checkout {
+    pub mode: PayMode,
+    /// 用户IP，微信 H5 支付必填
+    pub client_ip: Option<String>,
+    /// 用户在公众号下的 openid，微信 JSAPI 支付必填
+    pub openid: Option<String>,
+}
+
+/// 渠道下单参数
+pub struct ProviderOrder<'a> {
+    pub order: &'a pay_order::Model,
+    pub subject: &'a str,
+    /// 支付金额，单位：分
+    pub amount: i32,
+    pub checkout: &'a Checkout,
+}
+
+/// 渠道侧的交易信息
+pub struct ProviderTrade {
+    pub status: OrderStatus,
+    /// 前端需要执行的支付动作，仅下单时返回
+    pub action: Option<PaymentAction>,
+    /// 渠道原始响应，保存到 `pay_order.resp`
+    pub resp: Option<Value>,
+}
+
+/// 验签通过后解析出的渠道回调
+pub struct ProviderNotification {
+    pub order_id: i64,
+    pub status: OrderStatus,
+    pub resp: Value,
+}
+
+/// 支付渠道。
+///
+/// 新增渠道只需实现该 trait 并在 [`PayPlugin`](crate::plugin::pay::PayPlugin) 中注册，
+/// 下单、查单、关单、回调处理都通过 [`PaymentProviders`] 按 [`PayFrom`] 分发。
+#[async_trait]
+pub trait PaymentProvider: Send + Sync {
+    fn pay_from(&self) -> PayFrom;
+
+    /// 是否支持该支付方式
+    fn supports(&self, mode: PayMode) -> bool;
+
+    /// 在渠道侧下单
+    async fn create(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade>;
+
+    /// 查询渠道侧交易状态
+    async fn query(&self, order: &pay_order::Model) -> anyhow::Result<ProviderTrade>;
+
+    /// 关闭渠道侧交易，已支付等无法关闭的情况返回错误
+    async fn close(&self, order: &pay_order::Model) -> anyhow::Result<Option<Value>>;
 
-type HmacSha256 = Hmac<Sha256>;
+    /// 退款，`refund_no` 为本次退款单号，`total` 为订单金额，`amount` 为退款金额，单位：分
+    async fn refund(
+        &self,
+        order: &pay_order::Model,
+        refund_no: &str,
+        total: i32,
+        amount: i32,
+        reason: &str,
+    ) -> anyhow::Result<Value>;
+
+    /// 校验回调签名
+    async fn verify_notification(&self, headers: &HeaderMap, raw_body: &[u8])
+        -> anyhow::Result<()>;
+
+    /// 渠道回调的事件ID，用于回调去重
+    fn notification_id(&self, raw_body: &[u8]) -> Option<String>;
+
+    /// 解析回调报文
+    fn parse_notification(&self, raw_body: &[u8]) -> anyhow::Result<ProviderNotification>;
+}
+
+/// 已启用的支付渠道，由 [`PayPlugin`](crate::plugin::pay::PayPlugin) 按配置构建
+#[derive(Clone, Default)]
+pub struct PaymentProviders(Arc<HashMap<PayFrom, Arc<dyn PaymentProvider>>>);
+
+impl PaymentProviders {
+    pub fn new(providers: Vec<Arc<dyn PaymentProvider>>) -> Self {
+        let providers = providers
+            .into_iter()
+            .map(|provider| (provider.pay_from(), provider))
+            .collect();
+        Self(Arc::new(providers))
+    }
+
+    pub fn get(&self, from: PayFrom) -> anyhow::Result<&dyn PaymentProvider> {
+        self.0
+            .get(&from)
+            .map(|provider| provider.as_ref())
+            .ok_or_else(|| anyhow!("支付渠道未启用: {from}"))
+    }
+
+    pub fn is_enabled(&self, from: PayFrom) -> bool {
+        self.0.contains_key(&from)
+    }
+}
 
 #[derive(Clone, Service)]
 pub struct PayOrderService {
     #[inject(component)]
     pub db: DbConn,
     #[inject(component)]
-    alipay: Alipay,
-    #[inject(component)]
-    wechat: WechatPayClient,
+    providers: PaymentProviders,
     #[inject(component)]
-    paddle: PaddleClient,
+    redis: Redis,
     #[inject(config)]
     config: PayConfig,
 }
 
 impl PayOrderService {
+    /// 新购或续费当前版本。会员有效期内更换版本需要走 [`Self::create_upgrade_order`]
     pub async fn create_order(
         &self,
         uid: i64,
         level: OrderLevel,
         edition: ProductEdition,
         from: PayFrom,
-    ) -> anyhow::Result<(i64, Option<String>)> {
+        checkout: Checkout,
+    ) -> Result<(i64, Option<PaymentAction>)> {
+        let user = self.find_user(uid).await?;
+        let now = Local::now().naive_local();
+        let active = user.vip_expired_at.is_some_and(|t| t > now);
+        if active && user.edition != edition {
+            return Err(if edition.rank() > user.edition.rank() {
+                KnownWebError::bad_request("会员有效期内请通过升级购买更高版本")
+            } else {
+                KnownWebError::bad_request("会员有效期内不能购买更低版本")
+            })?;
+        }
+
         let order = pay_order::ActiveModel {
             user_id: Set(uid),
             level: Set(level),
             edition: Set(edition.clone()),
             pay_from: Set(from),
+            kind: Set(OrderKind::Purchase),
+            amount: Set(edition.amount(level)),
             ..Default::default()
-        }
-        .insert(&self.db)
-        .await
-        .context("创建订单失败")?;
-
-        let subject = format!("AutoWDS{}会员", level.title());
-        let order_id = order.id;
-        let amount = if self.config.test_pay_amount {
-            1 // 1分钱
-        } else {
-            level.amount()
         };
-        let qrcode_url = match from {
-            PayFrom::Alipay => {
-                self.alipay(subject, order.id, order.created, amount)
-                    .await?
-            }
-            PayFrom::Wechat => {
-                self.wechat_pay(subject, order.id, order.created, amount)
-                    .await?
-            }
-            PayFrom::Paddle => self.paddle_pay(uid, level, edition, order.id).await?,
-        };
-        Ok((order_id, qrcode_url))
-    }
-
-    async fn wechat_pay(
-        &self,
-        subject: String,
-        order_id: i64,
-        created: DateTime,
-        amount: i32,
-    ) -> anyhow::Result<Option<String>> {
-        let out_trade_no = Self::build_pay_out_trade_no(order_id, created);
-        let wechat = self.wechat.clone();
-        let resp = wechat
-            .native_pay(NativeParams::new(subject, out_trade_no, amount.into()))
+        let subject = format!("AutoWDS{}会员", level.title());
+        self.create_provider_order(order, from, &subject, &checkout)
             .await
-            .context("微信订单创建失败")?;
-        let NativeResponse {
-            code_url,
-            code,
-            message,
-        } = resp;
-        tracing::info!("wechat pay resp code ==> {code:?}, message ==> {message:?}");
-        Ok(code_url)
     }
 
-    async fn alipay(
-        &self,
-        subject: String,
-        order_id: i64,
-        created: DateTime,
-        amount: i32,
-    ) -> anyhow::Result<Option<String>> {
-        let alipay = self.alipay.clone();
-        let out_trade_no = Self::build_pay_out_trade_no(order_id, created);
-        let mut biz_content = biz::TradePrecreateBiz::new();
-        biz_content.set_subject(subject.into());
-        biz_content.set_out_trade_no(out_trade_no.into());
-        biz_content.set_total_amount((amount as f64 / 100.0).into());
-        let resp = alipay
-            .trade_precreate(&biz_content)
-            .context("支付宝订单创建失败")?;
-        let resp_json = serde_json::to_value(&resp).context("支付宝响应出错")?;
-        pay_order::ActiveModel {
-            id: Set(order_id),
-            resp: Set(Some(resp_json)),
-            ..Default::default()
-        }
-        .update(&self.db)
-        .await
-        .context("更新订单响应失败")?;
-        let TradePrecreateResponse {
-            response,
-            alipay_cert_sn,
-            sign,
-        } = resp;
-        tracing::info!("alipay resp sign ==> {sign:?}, alipay_cert_sn ==> {alipay_cert_sn:?}");
-        Ok(response.qr_code)
-    }
-
-    async fn paddle_pay(
+    /// 计算从当前版本升级到 `edition` 的报价
+    pub async fn quote_upgrade(
         &self,
         uid: i64,
-        level: OrderLevel,
         edition: ProductEdition,
-        order_id: i64,
-    ) -> anyhow::Result<Option<String>> {
-        if !self.config.paddle_enable {
-            return Err(anyhow!("Paddle 支付未启用"));
-        }
-
-        let price_id = match level {
-            OrderLevel::Monthly => &self.config.paddle_monthly_price_id,
-            OrderLevel::Annual => &self.config.paddle_annual_price_id,
-        };
-        if price_id.is_empty() {
-            return Err(anyhow!("Paddle price_id 未配置: {level:?}"));
+        level: OrderLevel,
+    ) -> Result<UpgradeQuote> {
+        let user = self.find_user(uid).await?;
+        if user.edition == ProductEdition::L0 {
+            return Err(KnownWebError::bad_request("免费版请直接购买会员"))?;
         }
-        if self.config.paddle_api_key.is_empty() {
-            return Err(anyhow!("Paddle API Key 未配置"));
+        if edition.rank() <= user.edition.rank() {
+            return Err(KnownWebError::bad_request("只能升级到更高版本"))?;
         }
-
-        let body = PaddleCreateTransactionReq {
-            items: vec![PaddleTransactionItem {
-                price_id: price_id.clone(),
-                quantity: 1,
-            }],
-            collection_mode: "automatic",
-            custom_data: json!({
-                "order_id": order_id,
-                "user_id": uid,
-                "level": level,
-                "edition": edition,
-            }),
+        let now = Local::now().naive_local();
+        let expired_at = match user.vip_expired_at {
+            Some(t) if t > now => t,
+            Some(_) => return Err(KnownWebError::bad_request("会员已过期，请直接购买"))?,
+            None => {
+                return Err(KnownWebError::bad_request(
+                    "当前会员没有到期时间，请联系客服升级",
+                ))?
+            }
         };
 
-        let url = format!(
-            "{}/transactions",
-            self.config.paddle_api_url.trim_end_matches('/')
-        );
-        let resp = self
-            .paddle
-            .post(url)
-            .header(
-                AUTHORIZATION,
-                format!("Bearer {}", self.config.paddle_api_key),
-            )
-            .header(CONTENT_TYPE, "application/json")
-            .json(&body)
-            .send()
-            .await
-            .context("Paddle 订单创建请求失败")?
-            .error_for_status()
-            .context("Paddle 订单创建失败")?
-            .json::<PaddleApiResp<PaddleTransaction>>()
-            .await
-            .context("Paddle 订单创建响应解析失败")?;
-
-        pay_order::ActiveModel {
-            id: Set(order_id),
-            resp: Set(Some(
-                serde_json::to_value(&resp).context("Paddle resp to json failed")?,
-            )),
-            ..Default::default()
-        }
-        .update(&self.db)
-        .await
-        .context("更新 Paddle 订单响应失败")?;
+        // 只折算最近一笔当前版本付费订单所购买的时长（支付时间起算一个周期）。
+        // 试用、赠送等叠加的时长没有实际支付，不折算抵扣
+        let last_order = pay_order::Entity::find_last_fulfilled(&self.db, uid)
+            .await?
+            .filter(|order| order.edition == user.edition);
+        let (from_level, remaining) = match last_order {
+            Some(order) => {
+                let paid_at = order
+                    .confirm
+                    .or(order.fulfilled_at)
+                    .unwrap_or(order.created);
+                let paid_until = paid_at + Duration::days(order.level.days());
+                (order.level, expired_at.min(paid_until) - now)
+            }
+            None => (level, Duration::zero()),
+        };
 
-        Ok(resp.data.checkout.and_then(|checkout| checkout.url))
+        Ok(prorate_upgrade(
+            user.edition,
+            from_level,
+            remaining,
+            edition,
+            level,
+            now,
+        ))
     }
 
-    pub async fn query_alipay_order(
+    /// 创建升级补差价订单，报价保存在订单 `meta` 中，支付成功后按报价换算会员时长
+    pub async fn create_upgrade_order(
         &self,
-        model: pay_order::Model,
-    ) -> anyhow::Result<pay_order::Model> {
-        let order_id = model.id;
-        let out_trade_no = Self::build_pay_out_trade_no(order_id, model.created);
-        let alipay = self.alipay.clone();
-        let mut biz_content = biz::TradeQueryBiz::new();
-        biz_content.set_out_trade_no(out_trade_no.into());
-
-        let resp = alipay
-            .trade_query(&biz_content)
-            .context("支付宝订单查询失败")?;
-
-        let status_str = resp.response.trade_status.clone().unwrap_or_default();
-
-        tracing::info!("支付宝订单#{order_id}状态: {status_str}");
-
-        let status = OrderStatus::from_alipay(&status_str);
-        let now = Local::now().naive_local();
-
-        let model = pay_order::ActiveModel {
-            id: Set(order_id),
-            confirm: Set(Some(now)),
-            status: Set(status),
-            resp: Set(Some(
-                serde_json::to_value(resp).context("resp to json failed")?,
+        uid: i64,
+        level: OrderLevel,
+        edition: ProductEdition,
+        from: PayFrom,
+        checkout: Checkout,
+    ) -> Result<(i64, Option<PaymentAction>)> {
+        let quote = self.quote_upgrade(uid, edition.clone(), level).await?;
+        let order = pay_order::ActiveModel {
+            user_id: Set(uid),
+            level: Set(level),
+            edition: Set(edition.clone()),
+            pay_from: Set(from),
+            kind: Set(OrderKind::Upgrade),
+            amount: Set(quote.amount),
+            upgrade_from: Set(Some(quote.from_edition.clone())),
+            meta: Set(Some(
+                serde_json::to_value(&quote).context("upgrade quote to json failed")?,
             )),
             ..Default::default()
-        }
-        .update(&self.db)
-        .await
-        .with_context(|| format!("update_pay_order({order_id}) failed"))?;
-
-        Ok(model)
+        };
+        let subject = format!("AutoWDS升级{edition}{}会员", level.title());
+        self.create_provider_order(order, from, &subject, &checkout)
+            .await
     }
 
-    pub async fn query_wechat_order(
+    async fn create_provider_order(
         &self,
-        model: pay_order::Model,
-    ) -> anyhow::Result<pay_order::Model> {
-        let wechat = self.wechat.clone();
-        let order_id = model.id;
-        let out_trade_no = Self::build_pay_out_trade_no(order_id, model.created);
-        let mchid = &wechat.mch_id;
-        let resp = wechat
-            .get_pay::<WechatPayOrderResp>(&format!(
-                "/v3/pay/transactions/out-trade-no/{out_trade_no}?mchid={mchid}"
-            ))
+        order: pay_order::ActiveModel,
+        from: PayFrom,
+        subject: &str,
+        checkout: &Checkout,
+    ) -> Result<(i64, Option<PaymentAction>)> {
+        let provider = self.providers.get(from)?;
+        if !provider.supports(checkout.mode) {
+            return Err(KnownWebError::bad_request(format!(
+                "{}不支持{}支付方式",
+                from.title(),
+                checkout.mode
+            )))?;
+        }
+        if checkout.mode == PayMode::Jsapi && checkout.openid.is_none() {
+            return Err(KnownWebError::bad_request("JSAPI 支付缺少 openid"))?;
+        }
+        let order = order.insert(&self.db).await.context("创建订单失败")?;
+
+        let amount = self.order_amount(&order);
+        let trade = provider
+            .create(&ProviderOrder {
+                order: &order,
+                subject,
+                amount,
+                checkout,
+            })
+            .await?;
+
+        if let Some(resp) = trade.resp {
+            pay_order::ActiveModel {
+                id: Set(order.id),
+                resp: Set(Some(resp)),
+                ..Default::default()
+            }
+            .update(&self.db)
             .await
-            .with_context(|| format!("微信订单查询失败: {out_trade_no}"))?;
-
-        tracing::info!(
-            "微信订单#{order_id}状态: {}({})",
-            resp.trade_state,
-            resp.trade_state_desc
-        );
-
-        let status = OrderStatus::from_wechat(&resp.trade_state);
-        let now = Local::now().naive_local();
-
-        let model = pay_order::ActiveModel {
-            id: Set(order_id),
-            confirm: Set(Some(now)),
-            status: Set(status),
-            resp: Set(Some(
-                serde_json::to_value(resp).context("resp to json failed")?,
-            )),
-            ..Default::default()
+            .context("更新订单响应失败")?;
         }
-        .update(&self.db)
-        .await
-        .with_context(|| format!("update_pay_order({order_id}) failed"))?;
 
-        Ok(model)
+        Ok((order.id, trade.action))
     }
 
-    pub async fn query_paddle_order(
-        &self,
-        model: pay_order::Model,
-    ) -> anyhow::Result<pay_order::Model> {
-        let transaction_id = model
-            .resp
-            .as_ref()
-            .and_then(|resp| resp.pointer("/data/id"))
-            .and_then(Value::as_str)
-            .ok_or_else(|| anyhow!("Paddle transaction_id 不存在: order_id={}", model.id))?;
-
-        let url = format!(
-            "{}/transactions/{transaction_id}",
-            self.config.paddle_api_url.trim_end_matches('/')
-        );
-        let resp = self
-            .paddle
-            .get(url)
-            .header(
-                AUTHORIZATION,
-                format!("Bearer {}", self.config.paddle_api_key),
-            )
-            .send()
-            .await
-            .context("Paddle 订单查询请求失败")?
-            .error_for_status()
-            .context("Paddle 订单查询失败")?
-            .json::<PaddleApiResp<PaddleTransaction>>()
+    async fn find_user(&self, uid: i64) -> Result<account_user::Model> {
+        Ok(AccountUser::find_by_id(uid)
+            .one(&self.db)
             .await
-            .context("Paddle 订单查询响应解析失败")?;
-
-        tracing::info!("Paddle 订单#{}状态: {}", model.id, resp.data.status);
-
-        let status = OrderStatus::from_paddle(&resp.data.status);
-        let confirm = (status == OrderStatus::Paid)
-            .then(|| Local::now().naive_local())
-            .or(model.confirm);
+            .with_context(|| format!("find_user({uid}) failed"))?
+            .ok_or_else(|| KnownWebError::not_found("用户不存在"))?)
+    }
 
-        let model = pay_order::ActiveModel {
-            id: Set(model.id),
-            confirm: Set(confirm),
-            status: Set(status),
-            resp: Set(Some(
-                serde_json::to_value(resp).context("Paddle resp to json failed")?,
-            )),
-            ..Default::default()
-        }
-        .update(&self.db)
-        .await
-        .with_context(|| format!("update_pay_order({}) failed", model.id))?;
+    pub fn is_enabled(&self, from: PayFrom) -> bool {
+        self.providers.is_enabled(from)
+    }
 
-        Ok(model)
+    /// 向支付渠道查询订单状态并落库
+    pub async fn query_order(&self, model: pay_order::Model) -> anyhow::Result<pay_order::Model> {
+        let trade = self.providers.get(model.pay_from)?.query(&model).await?;
+        self.update_order_status(model.id, trade.status, trade.resp)
+            .await
     }
 
-    pub async fn alipay_verify_sign(&self, raw_body: &[u8]) -> anyhow::Result<()> {
-        let alipay = self.alipay.clone();
-        let r = alipay
-            .async_verify_sign(raw_body)
-            .context("支付宝验签失败")?;
-        if r {
-            Ok(())
-        } else {
-            Err(anyhow!("支付宝验签失败"))
-        }
+    /// 订单未支付的有效期
+    pub fn order_ttl(&self) -> Duration {
+        Duration::minutes(self.config.order_ttl_minutes)
     }
 
-    pub async fn wechat_verify_signature(
+    pub async fn find_expired_created(
         &self,
-        serial: &str,
-        timestamp: &str,
-        nonce: &str,
-        signature: &str,
-        body: &str,
-    ) -> anyhow::Result<WechatPayNotify> {
-        let wechat = self.wechat.clone();
-        let pub_key = self.get_wechat_pub_key(serial).await?;
-
-        wechat
-            .verify_signature(&pub_key, timestamp, nonce, signature, body)
-            .context("微信验签失败，非法数据")?;
-
-        serde_json::from_str::<WechatPayNotify>(body).context("微信回调数据解析失败")
+        before: DateTime,
+    ) -> anyhow::Result<Vec<pay_order::Model>> {
+        pay_order::Entity::find_expired_created(&self.db, before).await
     }
 
-    pub fn paddle_verify_signature(
+    /// 关闭超时未支付的订单。
+    ///
+    /// 关单前先向渠道查单，避免用户恰好在有效期临界点完成支付；渠道关单失败时再查一次，
+    /// 以识别“关单过程中支付到账”的情况。最终以 `status = 'created'` 为条件落库，
+    /// 与并发到达的支付回调互斥，返回值为订单的最新状态。
+    pub async fn close_expired_order(
         &self,
-        signature_header: &str,
-        raw_body: &[u8],
-    ) -> anyhow::Result<PaddleWebhookEvent> {
-        if self.config.paddle_webhook_secret.is_empty() {
-            return Err(anyhow!("Paddle Webhook Secret 未配置"));
-        }
-
-        let mut timestamp = None;
-        let mut signature = None;
-        for item in signature_header.split(';').map(str::trim) {
-            if let Some(value) = item.strip_prefix(PADDLE_SIGNATURE_TS_PREFIX) {
-                timestamp = Some(value);
-            }
-            if let Some(value) = item.strip_prefix(PADDLE_SIGNATURE_PREFIX) {
-                signature = Some(value);
+        model: pay_order::Model,
+    ) -> anyhow::Result<pay_order::Model> {
+        let order_id = model.id;
+        let provider = self.providers.get(model.pay_from)?;
+        let model = match self.query_order(model.clone()).await {
+            Ok(model) => model,
+            Err(e) => {
+                // 用户未扫码时部分渠道查不到订单，继续关单
+                tracing::warn!("关单前查询订单#{order_id}失败: {e:#}");
+                model
             }
+        };
+        if model.status != OrderStatus::Created {
+            return Ok(model);
         }
 
-        let timestamp = timestamp.ok_or_else(|| anyhow!("Paddle 签名缺少 ts"))?;
-        let signature = signature.ok_or_else(|| anyhow!("Paddle 签名缺少 h1"))?;
-        let signature = hex::decode(signature).context("Paddle 签名格式错误")?;
-
-        let mut mac = HmacSha256::new_from_slice(self.config.paddle_webhook_secret.as_bytes())
-            .context("Paddle HMAC 初始化失败")?;
-        mac.update(timestamp.as_bytes());
-        mac.update(b":");
-        mac.update(raw_body);
-        let expected = mac.finalize().into_bytes();
+        let close_resp = match provider.close(&model).await {
+            Ok(resp) => resp,
+            Err(e) => {
+                let model = self.query_order(model).await?;
+                if model.status != OrderStatus::Created {
+                    tracing::info!("订单#{order_id}关单失败，渠道状态已变为{:?}", model.status);
+                    return Ok(model);
+                }
+                return Err(e);
+            }
+        };
 
-        if expected.as_slice().ct_eq(signature.as_slice()).into() {
-            serde_json::from_slice::<PaddleWebhookEvent>(raw_body)
-                .context("Paddle 回调数据解析失败")
-        } else {
-            Err(anyhow!("Paddle 验签失败"))
+        if !pay_order::Entity::close_if_created(&self.db, order_id, close_resp).await? {
+            tracing::info!("订单#{order_id}关单时状态已被并发修改");
         }
+        pay_order::Entity::find_by_id(order_id)
+            .one(&self.db)
+            .await
+            .with_context(|| format!("find_pay_order({order_id}) failed"))?
+            .ok_or_else(|| anyhow!("订单不存在: {order_id}"))
     }
 
-    pub async fn get_wechat_pub_key(&self, serial: &str) -> anyhow::Result<String> {
-        let pub_key_dir =
-            env::var("WECHAT_PAY_PUB_KEY_DIR").unwrap_or("/data/wechat-cert/pubkey".to_string());
-        let cert_dir = Path::new(&pub_key_dir);
-        if !cert_dir.exists() {
-            std::fs::create_dir_all(cert_dir)
-                .with_context(|| format!("create dir {cert_dir:?} failed"))?;
+    /// 原路退款，`amount` 为空时退还剩余全部金额。
+    ///
+    /// 支持多次部分退款，累计退款金额达到订单金额后订单置为 `refunded`，会员权益由管理员另行调整
+    pub async fn refund_order(
+        &self,
+        order_id: i64,
+        amount: Option<i32>,
+        reason: &str,
+    ) -> anyhow::Result<pay_order::Model> {
+        let order = pay_order::Entity::find_by_id(order_id)
+            .one(&self.db)
+            .await
+            .with_context(|| format!("find_pay_order({order_id}) failed"))?
+            .ok_or_else(|| anyhow!("订单不存在: {order_id}"))?;
+        if order.status != OrderStatus::Paid {
+            return Err(anyhow!("订单#{order_id}未支付或已全额退款，不能退款"));
         }
-        let cert_path = format!("{pub_key_dir}/{serial}/pubkey.pem");
-        let cert_path = Path::new(&cert_path);
-        if cert_path.exists() {
-            let pub_key = std::fs::read_to_string(cert_path)
-                .with_context(|| format!("read pub key from {cert_path:?} failed"))?;
-            return Ok(pub_key);
+
+        let total = self.order_amount(&order);
+        let remaining = total - order.refunded_amount;
+        let amount = amount.unwrap_or(remaining);
+        if amount <= 0 || amount > remaining {
+            return Err(anyhow!(
+                "退款金额不合法: {amount}, 订单金额: {total}, 已退款: {}",
+                order.refunded_amount
+            ));
+        }
+        // Paddle 退款按交易整单退，不支持部分退款
+        if order.pay_from == PayFrom::Paddle && amount < remaining {
+            return Err(anyhow!("Paddle 订单只能全额退款"));
         }
+        let refund_no = build_refund_no(&order);
+        let resp = self
+            .providers
+            .get(order.pay_from)?
+            .refund(&order, &refund_no, total, amount, reason)
+            .await?;
+        tracing::info!(
+            "订单#{order_id}退款成功: refund_no={refund_no}, amount={amount}, resp={resp}"
+        );
 
-        let wechat = self.wechat.clone();
-        tracing::info!("fetch wechat pay certificates from wechat server");
+        // 保留下单/支付时的渠道响应，退款响应只记录日志
+        if !pay_order::Entity::add_refunded(&self.db, &order, amount, total).await? {
+            return Err(anyhow!("订单#{order_id}退款金额已被并发修改，请刷新后确认"));
+        }
+        let order = pay_order::Entity::find_by_id(order_id)
+            .one(&self.db)
+            .await
+            .with_context(|| format!("find_pay_order({order_id}) failed"))?
+            .ok_or_else(|| anyhow!("订单不存在: {order_id}"))?;
+        if order.status == OrderStatus::Refunded {
+            self.publish_status(&order).await;
+        }
+        Ok(order)
+    }
 
-        let resp = wechat
-            .certificates()
+    /// 广播订单最新状态，推送给各实例上等待支付结果的 SSE 连接。广播失败只记录日志，前端仍可轮询
+    pub async fn publish_status(&self, order: &pay_order::Model) {
+        let event = PayStatusEvent {
+            order_id: order.id,
+            status: order.status,
+        };
+        let payload = serde_json::to_string(&event).expect("pay status event to json failed");
+        let mut redis = self.redis.clone();
+        if let Err(e) = redis
+            .publish::<_, _, ()>(pay_status_channel(order.id), payload)
             .await
-            .context("获取微信平台证书失败")?;
-
-        let certs = resp.data.ok_or_else(|| anyhow!("微信平台证书为空"))?;
-
-        for cert in certs {
-            let serial_no = cert.serial_no;
-            let ciphertext = cert.encrypt_certificate.ciphertext;
-            let nonce = cert.encrypt_certificate.nonce;
-            let associated_data = cert.encrypt_certificate.associated_data;
-            let data = wechat
-                .decrypt_bytes(ciphertext, nonce, associated_data)
-                .context("微信平台证书解密失败")?;
-            let pub_key = wechat_pay_rust_sdk::util::x509_to_pem(data.as_slice())
-                .map_err(|e| anyhow!("微信平台证书转换PEM失败:{e}"))?;
-            let cert_path = format!("{pub_key_dir}/{serial_no}/pubkey.pem");
-            let mut pub_key_file = File::create(cert_path).context("create pub key file failed")?;
-            pub_key_file
-                .write_all(pub_key.as_bytes())
-                .context("write pub key file failed")?;
-
-            let (pub_key_valid, expire_timestamp) =
-                wechat_pay_rust_sdk::util::x509_is_valid(data.as_slice())
-                    .map_err(|e| anyhow!("公钥验证失败:{e}"))?;
-            tracing::debug!(
-                "pub key valid:{} expire_timestamp:{}",
-                pub_key_valid,
-                expire_timestamp
-            ); //检测证书是否可用,打印过期时间
+        {
+            tracing::warn!("广播订单#{}状态失败: {e}", order.id);
         }
+    }
 
-        let cert_path = format!("{pub_key_dir}/{serial}/pubkey.pem");
-        let cert_path = Path::new(&cert_path);
-        if cert_path.exists() {
-            let pub_key = std::fs::read_to_string(cert_path)
-                .with_context(|| format!("read pub key from {cert_path:?} failed"))?;
-            return Ok(pub_key);
+    fn order_amount(&self, order: &pay_order::Model) -> i32 {
+        if self.config.test_pay_amount {
+            1 // 1分钱
         } else {
-            return Err(anyhow!("微信公钥不存在"));
+            order.amount
         }
     }
 
-    pub async fn notify_wechat_pay(
+    /// 校验渠道回调签名
+    pub async fn verify_notification(
         &self,
-        notify: &WechatPayNotify,
-    ) -> anyhow::Result<pay_order::Model> {
-        let wechat = self.wechat.clone();
-        let resource = notify.resource.clone();
-        let nonce = resource.nonce;
-        let ciphertext = resource.ciphertext;
-        let associated_data = resource.associated_data.unwrap_or_default();
-        let data: WechatPayDecodeData = wechat
-            .decrypt_paydata(
-                ciphertext,      //加密数据
-                nonce,           //随机串
-                associated_data, //关联数据
-            )
-            .context("解析关联数据失败")?;
-
-        tracing::info!("接收到微信订单状态: {}", data.trade_state);
-
-        let status = OrderStatus::from_wechat(&data.trade_state);
-        let out_trade_no =
-            Self::parse_pay_out_trade_no(&data.out_trade_no).context("解析订单号失败")?;
-        let now = Local::now().naive_local();
-
-        let model = pay_order::ActiveModel {
-            id: Set(out_trade_no),
-            confirm: Set(Some(now)),
-            status: Set(status),
-            resp: Set(Some(
-                serde_json::to_value(notify).context("resp to json failed")?,
-            )),
-            ..Default::default()
-        }
-        .update(&self.db)
-        .await
-        .with_context(|| format!("update_pay_order({out_trade_no}) failed"))?;
-        Ok(model)
+        from: PayFrom,
+        headers: &HeaderMap,
+        raw_body: &[u8],
+    ) -> anyhow::Result<()> {
+        self.providers
+            .get(from)?
+            .verify_notification(headers, raw_body)
+            .await
     }
 
-    pub async fn notify_alipay(&self, raw_body: &[u8]) -> anyhow::Result<pay_order::Model> {
-        let notify = serde_urlencoded::from_bytes::<AlipayNotify>(raw_body)
-            .context("支付宝notify解析失败")?;
-
-        tracing::info!("接收到支付宝订单状态: {}", notify.trade_status);
-
-        let out_trade_no =
-            Self::parse_pay_out_trade_no(&notify.out_trade_no).context("解析订单号失败")?;
-        let status = OrderStatus::from_alipay(&notify.trade_status);
-        let now = Local::now().naive_local();
+    pub fn notification_id(&self, from: PayFrom, raw_body: &[u8]) -> Option<String> {
+        self.providers
+            .get(from)
+            .ok()
+            .and_then(|provider| provider.notification_id(raw_body))
+    }
 
-        let model = pay_order::ActiveModel {
-            id: Set(out_trade_no),
-            confirm: Set(Some(now)),
-            status: Set(status),
-            resp: Set(Some(
-                serde_json::to_value(notify).context("resp to json failed")?,
-            )),
-            ..Default::default()
-        }
-        .update(&self.db)
+    /// 处理已验签的渠道回调，更新订单状态
+    pub async fn notify(&self, from: PayFrom, raw_body: &[u8]) -> anyhow::Result<pay_order::Model> {
+        let notification = self.providers.get(from)?.parse_notification(raw_body)?;
+        self.update_order_status(
+            notification.order_id,
+            notification.status,
+            Some(notification.resp),
+        )
         .await
-        .with_context(|| format!("update_pay_order({out_trade_no}) failed"))?;
-
-        Ok(model)
     }
 
-    pub async fn notify_paddle(
+    /// 更新订单状态。
+    ///
+    /// - 已支付/已退款的订单不会被回退：关单、退款后渠道推送的 `TRADE_CLOSED` 等通知不能覆盖订单结果。
+    /// - 只有进入终态（支付/关闭）时才写入 `confirm`，未支付的订单会继续被定时任务轮询，
+    ///   直到超时关单。
+    async fn update_order_status(
         &self,
-        event: &PaddleWebhookEvent,
+        order_id: i64,
+        status: OrderStatus,
+        resp: Option<Value>,
     ) -> anyhow::Result<pay_order::Model> {
-        tracing::info!(
-            "接收到 Paddle 事件: {}, transaction status: {}",
-            event.event_type,
-            event.data.status
-        );
-
-        let order_id = event
-            .data
-            .custom_data
-            .as_ref()
-            .and_then(|custom_data| custom_data.get("order_id"))
-            .and_then(Value::as_i64)
-            .ok_or_else(|| anyhow!("Paddle 回调缺少 custom_data.order_id"))?;
-
-        let status = OrderStatus::from_paddle_event(&event.event_type, &event.data.status);
         let current = pay_order::Entity::find_by_id(order_id)
             .one(&self.db)
             .await
-            .with_context(|| format!("find_pay_order({order_id}) failed"))?;
-        let (status, confirm) = match current {
-            Some(current) if current.status == OrderStatus::Paid && status != OrderStatus::Paid => {
-                (current.status, current.confirm)
-            }
-            Some(current) => (
-                status,
-                (status == OrderStatus::Paid)
-                    .then(|| Local::now().naive_local())
-                    .or(current.confirm),
-            ),
-            None => (
-                status,
-                (status == OrderStatus::Paid).then(|| Local::now().naive_local()),
-            ),
+            .with_context(|| format!("find_pay_order({order_id}) failed"))?
+            .ok_or_else(|| anyhow!("订单不存在: {order_id}"))?;
+        let status = if matches!(current.status, OrderStatus::Paid | OrderStatus::Refunded) {
+            current.status
+        } else {
+            status
+        };
+        let confirm = if status != OrderStatus::Created
+            && (status != current.status || current.confirm.is_none())
+        {
+            Some(Local::now().naive_local())
+        } else {
+            current.confirm
         };
 
         let model = pay_order::ActiveModel {
             id: Set(order_id),
             confirm: Set(confirm),
             status: Set(status),
-            resp: Set(Some(
-                serde_json::to_value(event).context("Paddle event to json failed")?,
-            )),
+            resp: resp.map_or(NotSet, |resp| Set(Some(resp))),
             ..Default::default()
         }
         .update(&self.db)
@@ -603,133 +548,139 @@ impl PayOrderService {
     ) -> anyhow::Result<Vec<pay_order::Model>> {
         pay_order::Entity::find_wait_confirm_after(&self.db, after_time).await
     }
+}
 
-    fn build_pay_out_trade_no(order_id: i64, created: DateTime) -> String {
-        format!(
-            "{PAY_OUT_TRADE_PREFIX}{}{order_id:08}",
-            created.format("%Y%m%d%H%M%S")
-        )
-    }
-
-    fn parse_pay_out_trade_no(out_trade_no: &str) -> anyhow::Result<i64> {
-        let suffix = out_trade_no
-            .strip_prefix(PAY_OUT_TRADE_PREFIX)
-            .ok_or_else(|| anyhow!("非法商户订单号前缀: {out_trade_no}"))?;
-        let order_id = suffix
-            .get(PAY_OUT_TRADE_TIME_LEN..)
-            .ok_or_else(|| anyhow!("非法商户订单号长度: {out_trade_no}"))?;
-        order_id
-            .parse::<i64>()
-            .with_context(|| format!("非法商户订单号: {out_trade_no}"))
+/// 计算升级报价。
+///
+/// 当前版本剩余时长按原周期单价折算为抵扣金额，抵扣后至少支付 [`MIN_UPGRADE_AMOUNT`]，
+/// 抵扣不完的部分按新版本单价折算为赠送时长，叠加在新周期之后。
+pub fn prorate_upgrade(
+    from_edition: ProductEdition,
+    from_level: OrderLevel,
+    remaining: Duration,
+    to_edition: ProductEdition,
+    level: OrderLevel,
+    now: DateTime,
+) -> UpgradeQuote {
+    let remaining_seconds = remaining.num_seconds().max(0);
+    let from_period = Duration::days(from_level.days()).num_seconds();
+    let to_period = Duration::days(level.days()).num_seconds();
+
+    let from_price = from_edition.amount(from_level) as i64;
+    let price = to_edition.amount(level);
+    let credit = from_price * remaining_seconds / from_period;
+
+    let applied = credit.min((price - MIN_UPGRADE_AMOUNT) as i64).max(0);
+    let amount = price - applied as i32;
+    let bonus_seconds = (credit - applied) * to_period / price as i64;
+
+    UpgradeQuote {
+        from_edition,
+        from_level,
+        to_edition,
+        level,
+        remaining_seconds,
+        price,
+        credit: credit as i32,
+        amount,
+        bonus_seconds,
+        expired_at: now + Duration::seconds(to_period + bonus_seconds),
     }
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct WechatPayOrderResp {
-    pub appid: String,
-    pub mchid: String,
-    pub out_trade_no: String,
-    pub trade_state: String,
-    pub trade_state_desc: String,
-    pub transaction_id: Option<String>,
-    pub trade_type: Option<String>,
-    #[serde(flatten)]
-    pub extra: HashMap<String, Value>,
+pub fn build_pay_out_trade_no(order_id: i64, created: DateTime) -> String {
+    format!(
+        "{PAY_OUT_TRADE_PREFIX}{}{order_id:08}",
+        created.format("%Y%m%d%H%M%S")
+    )
 }
 
-impl ResponseTrait for WechatPayOrderResp {}
-
-#[derive(Debug, Serialize)]
-struct PaddleCreateTransactionReq {
-    items: Vec<PaddleTransactionItem>,
-    collection_mode: &'static str,
-    custom_data: Value,
+/// 退款单号按订单已退款金额生成：每次部分退款的单号不同，
+/// 而同一次退款请求重试时单号不变，由渠道保证不会重复退款
+fn build_refund_no(order: &pay_order::Model) -> String {
+    format!(
+        "R{}_{}",
+        build_pay_out_trade_no(order.id, order.created),
+        order.refunded_amount
+    )
 }
 
-#[derive(Debug, Serialize)]
-struct PaddleTransactionItem {
-    price_id: String,
-    quantity: u32,
+fn parse_pay_out_trade_no(out_trade_no: &str) -> anyhow::Result<i64> {
+    let suffix = out_trade_no
+        .strip_prefix(PAY_OUT_TRADE_PREFIX)
+        .ok_or_else(|| anyhow!("非法商户订单号前缀: {out_trade_no}"))?;
+    let order_id = suffix
+        .get(PAY_OUT_TRADE_TIME_LEN..)
+        .ok_or_else(|| anyhow!("非法商户订单号长度: {out_trade_no}"))?;
+    order_id
+        .parse::<i64>()
+        .with_context(|| format!("非法商户订单号: {out_trade_no}"))
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PaddleApiResp<T> {
-    pub data: T,
-    #[serde(default)]
-    pub meta: Option<Value>,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PaddleTransaction {
-    pub id: String,
-    pub status: String,
-    #[serde(default)]
-    pub custom_data: Option<Value>,
-    #[serde(default)]
-    pub checkout: Option<PaddleCheckout>,
-    #[serde(flatten)]
-    pub extra: HashMap<String, Value>,
-}
+    fn now() -> DateTime {
+        NaiveDate::from_ymd_opt(2025, 1, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PaddleCheckout {
-    pub url: Option<String>,
-    #[serde(flatten)]
-    pub extra: HashMap<String, Value>,
-}
+    #[test]
+    fn prorates_remaining_time_as_credit() {
+        let quote = prorate_upgrade(
+            ProductEdition::L1,
+            OrderLevel::Annual,
+            Duration::days(73),
+            ProductEdition::L2,
+            OrderLevel::Annual,
+            now(),
+        );
+        let from_price = ProductEdition::L1.amount(OrderLevel::Annual);
+        let price = ProductEdition::L2.amount(OrderLevel::Annual);
+        assert_eq!(quote.credit, from_price * 73 / 365);
+        assert_eq!(quote.amount, price - from_price * 73 / 365);
+        assert_eq!(quote.bonus_seconds, 0);
+        assert_eq!(quote.expired_at, now() + Duration::days(365));
+    }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct PaddleWebhookEvent {
-    pub event_id: String,
-    pub event_type: String,
-    pub occurred_at: String,
-    pub data: PaddleTransaction,
-    #[serde(flatten)]
-    pub extra: HashMap<String, Value>,
-}
+    #[test]
+    fn converts_excess_credit_into_bonus_time() {
+        let quote = prorate_upgrade(
+            ProductEdition::L1,
+            OrderLevel::Annual,
+            Duration::days(365),
+            ProductEdition::L2,
+            OrderLevel::Monthly,
+            now(),
+        );
+        let from_price = ProductEdition::L1.amount(OrderLevel::Annual);
+        let price = ProductEdition::L2.amount(OrderLevel::Monthly);
+        assert_eq!(quote.credit, from_price);
+        assert_eq!(quote.amount, MIN_UPGRADE_AMOUNT);
+        let leftover = (from_price - (price - MIN_UPGRADE_AMOUNT)) as i64;
+        assert_eq!(quote.bonus_seconds, leftover * 30 * 86400 / price as i64);
+        assert_eq!(
+            quote.expired_at,
+            now() + Duration::days(30) + Duration::seconds(quote.bonus_seconds)
+        );
+    }
 
-/// 支付宝异步通知参数
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AlipayNotify {
-    pub notify_time: String,
-    pub notify_type: String,
-    pub notify_id: String,
-    pub sign_type: String,
-    pub sign: String,
-
-    pub trade_no: String,
-    pub app_id: String,
-    pub auth_app_id: String,
-    pub out_trade_no: String,
-    pub out_biz_no: Option<String>,
-
-    #[serde(alias = "buyer_id", alias = "buyer_open_id")]
-    pub buyer_id: Option<String>,
-    pub buyer_logon_id: Option<String>,
-    pub seller_id: Option<String>,
-    pub seller_email: Option<String>,
-
-    pub trade_status: String,
-    pub total_amount: String,
-    pub receipt_amount: Option<String>,
-    pub invoice_amount: Option<String>,
-    pub buyer_pay_amount: Option<String>,
-    pub point_amount: Option<String>,
-    pub refund_fee: Option<String>,
-    pub send_back_fee: Option<String>,
-
-    pub subject: Option<String>,
-    pub body: Option<String>,
-
-    pub gmt_create: Option<String>,
-    pub gmt_payment: Option<String>,
-    pub gmt_refund: Option<String>,
-    pub gmt_close: Option<String>,
-
-    pub fund_bill_list: Option<String>, // 原始 JSON 字符串，必要时再反序列化
-    pub voucher_detail_list: Option<String>, // 同上
-    pub biz_settle_mode: Option<String>,
-
-    pub merchant_app_id: Option<String>,
-    pub version: Option<String>,
+    #[test]
+    fn expired_membership_gets_no_credit() {
+        let quote = prorate_upgrade(
+            ProductEdition::L1,
+            OrderLevel::Monthly,
+            Duration::days(-3),
+            ProductEdition::L3,
+            OrderLevel::Monthly,
+            now(),
+        );
+        assert_eq!(quote.remaining_seconds, 0);
+        assert_eq!(quote.credit, 0);
+        assert_eq!(quote.amount, ProductEdition::L3.amount(OrderLevel::Monthly));
+    }
 }
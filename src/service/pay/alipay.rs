@@ -0,0 +1,279 @@
+use super::{
+    build_pay_out_trade_no, parse_pay_out_trade_no, PaymentProvider, ProviderNotification,
+    ProviderOrder, ProviderTrade,
+};
+use crate::{
+    model::{
+        pay_order,
+        sea_orm_active_enums::{OrderStatus, PayFrom},
+    },
+    views::pay::{PayMode, PaymentAction},
+};
+use alipay_sdk_rust::{
+    biz::{self, BizObject},
+    pay::{PayClient, Payer},
+    response::TradePrecreateResponse,
+};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use summer::{async_trait, tracing};
+use summer_web::axum::http::HeaderMap;
+
+const ALIPAY_SUCCESS_CODE: &str = "10000";
+const ALIPAY_TRADE_NOT_EXIST: &str = "ACQ.TRADE_NOT_EXIST";
+const ALIPAY_WAP_PRODUCT_CODE: &str = "QUICK_WAP_WAY";
+const ALIPAY_PAGE_PRODUCT_CODE: &str = "FAST_INSTANT_TRADE_PAY";
+
+pub struct AlipayProvider {
+    client: Arc<dyn Payer + Send + Sync>,
+}
+
+impl AlipayProvider {
+    pub fn new(client: PayClient) -> Self {
+        Self {
+            client: Arc::new(client),
+        }
+    }
+
+    /// 当面付预下单，返回二维码内容
+    fn precreate(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.order.id, order.order.created);
+        let mut biz_content = biz::TradePrecreateBiz::new();
+        biz_content.set_subject(order.subject.into());
+        biz_content.set_out_trade_no(out_trade_no.into());
+        biz_content.set_total_amount((order.amount as f64 / 100.0).into());
+        let resp = self
+            .client
+            .trade_precreate(&biz_content)
+            .context("支付宝订单创建失败")?;
+        let resp_json = serde_json::to_value(&resp).context("支付宝响应出错")?;
+        let TradePrecreateResponse {
+            response,
+            alipay_cert_sn,
+            sign,
+        } = resp;
+        tracing::info!("alipay resp sign ==> {sign:?}, alipay_cert_sn ==> {alipay_cert_sn:?}");
+        Ok(ProviderTrade {
+            status: OrderStatus::Created,
+            action: response
+                .qr_code
+                .map(|code_url| PaymentAction::QrCode { code_url }),
+            resp: Some(resp_json),
+        })
+    }
+
+    /// 手机网站支付，返回跳转到支付宝收银台的地址
+    fn wap_pay(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.order.id, order.order.created);
+        let mut biz_content = biz::TradeWapPayBiz::new();
+        biz_content.set_subject(order.subject.into());
+        biz_content.set_out_trade_no(out_trade_no.into());
+        biz_content.set_total_amount((order.amount as f64 / 100.0).into());
+        biz_content.set_product_code(ALIPAY_WAP_PRODUCT_CODE.into());
+        let url = self
+            .client
+            .trade_wap_pay(&biz_content)
+            .context("支付宝手机网站支付下单失败")?;
+        Ok(Self::redirect_trade(url))
+    }
+
+    /// 电脑网站支付，返回跳转到支付宝收银台的地址
+    fn page_pay(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.order.id, order.order.created);
+        let mut biz_content = biz::TradePagePayBiz::new();
+        biz_content.set_subject(order.subject.into());
+        biz_content.set_out_trade_no(out_trade_no.into());
+        biz_content.set_total_amount((order.amount as f64 / 100.0).into());
+        biz_content.set_product_code(ALIPAY_PAGE_PRODUCT_CODE.into());
+        let url = self
+            .client
+            .trade_page_pay(&biz_content)
+            .context("支付宝电脑网站支付下单失败")?;
+        Ok(Self::redirect_trade(url))
+    }
+
+    /// 跳转类支付在用户打开收银台前支付宝侧不会创建交易，保存跳转地址便于排查
+    fn redirect_trade(url: String) -> ProviderTrade {
+        ProviderTrade {
+            status: OrderStatus::Created,
+            resp: Some(json!({ "pay_url": url })),
+            action: Some(PaymentAction::Redirect { url }),
+        }
+    }
+
+    /// 支付宝网关的业务响应统一包在 `response` 中，`code=10000` 表示成功
+    fn check_response(resp: &Value) -> anyhow::Result<()> {
+        let code = resp.pointer("/response/code").and_then(Value::as_str);
+        match code {
+            Some(ALIPAY_SUCCESS_CODE) => Ok(()),
+            _ => Err(anyhow!("支付宝接口返回失败: {resp}")),
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for AlipayProvider {
+    fn pay_from(&self) -> PayFrom {
+        PayFrom::Alipay
+    }
+
+    fn supports(&self, mode: PayMode) -> bool {
+        matches!(mode, PayMode::Native | PayMode::Page | PayMode::Wap)
+    }
+
+    async fn create(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        match order.checkout.mode {
+            PayMode::Page => self.page_pay(order),
+            PayMode::Wap => self.wap_pay(order),
+            _ => self.precreate(order),
+        }
+    }
+
+    async fn query(&self, order: &pay_order::Model) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.id, order.created);
+        let mut biz_content = biz::TradeQueryBiz::new();
+        biz_content.set_out_trade_no(out_trade_no.into());
+
+        let resp = self
+            .client
+            .trade_query(&biz_content)
+            .context("支付宝订单查询失败")?;
+
+        let status_str = resp.response.trade_status.clone().unwrap_or_default();
+        tracing::info!("支付宝订单#{}状态: {status_str}", order.id);
+
+        Ok(ProviderTrade {
+            status: OrderStatus::from_alipay(&status_str),
+            action: None,
+            resp: Some(serde_json::to_value(resp).context("resp to json failed")?),
+        })
+    }
+
+    async fn close(&self, order: &pay_order::Model) -> anyhow::Result<Option<Value>> {
+        let out_trade_no = build_pay_out_trade_no(order.id, order.created);
+        let mut biz_content = biz::TradeCloseBiz::new();
+        biz_content.set_out_trade_no(out_trade_no.into());
+
+        let resp = self
+            .client
+            .trade_close(&biz_content)
+            .context("支付宝关单请求失败")?;
+        let resp = serde_json::to_value(resp).context("resp to json failed")?;
+        let sub_code = resp.pointer("/response/sub_code").and_then(Value::as_str);
+        if sub_code == Some(ALIPAY_TRADE_NOT_EXIST) {
+            // 用户未扫码时支付宝侧没有交易，直接本地关单
+            return Ok(Some(resp));
+        }
+        Self::check_response(&resp).context("支付宝关单失败")?;
+        Ok(Some(resp))
+    }
+
+    async fn refund(
+        &self,
+        order: &pay_order::Model,
+        refund_no: &str,
+        _total: i32,
+        amount: i32,
+        reason: &str,
+    ) -> anyhow::Result<Value> {
+        let out_trade_no = build_pay_out_trade_no(order.id, order.created);
+        let mut biz_content = biz::TradeRefundBiz::new();
+        biz_content.set_out_trade_no(out_trade_no.into());
+        biz_content.set_refund_amount((amount as f64 / 100.0).into());
+        biz_content.set_refund_reason(reason.into());
+        // 部分退款须指定退款请求号，同一请求号重复提交时支付宝只退款一次
+        biz_content.set("out_request_no", refund_no.into());
+
+        let resp = self
+            .client
+            .trade_refund(&biz_content)
+            .context("支付宝退款请求失败")?;
+        let resp = serde_json::to_value(resp).context("resp to json failed")?;
+        Self::check_response(&resp).context("支付宝退款失败")?;
+        Ok(resp)
+    }
+
+    async fn verify_notification(
+        &self,
+        _headers: &HeaderMap,
+        raw_body: &[u8],
+    ) -> anyhow::Result<()> {
+        let valid = self
+            .client
+            .async_verify_sign(raw_body)
+            .context("支付宝验签失败")?;
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow!("支付宝验签失败"))
+        }
+    }
+
+    fn notification_id(&self, raw_body: &[u8]) -> Option<String> {
+        serde_urlencoded::from_bytes::<AlipayNotify>(raw_body)
+            .ok()
+            .map(|notify| notify.notify_id)
+    }
+
+    fn parse_notification(&self, raw_body: &[u8]) -> anyhow::Result<ProviderNotification> {
+        let notify = serde_urlencoded::from_bytes::<AlipayNotify>(raw_body)
+            .context("支付宝notify解析失败")?;
+
+        tracing::info!("接收到支付宝订单状态: {}", notify.trade_status);
+
+        let order_id = parse_pay_out_trade_no(&notify.out_trade_no).context("解析订单号失败")?;
+        Ok(ProviderNotification {
+            order_id,
+            status: OrderStatus::from_alipay(&notify.trade_status),
+            resp: serde_json::to_value(notify).context("resp to json failed")?,
+        })
+    }
+}
+
+/// 支付宝异步通知参数
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AlipayNotify {
+    pub notify_time: String,
+    pub notify_type: String,
+    pub notify_id: String,
+    pub sign_type: String,
+    pub sign: String,
+
+    pub trade_no: String,
+    pub app_id: String,
+    pub auth_app_id: String,
+    pub out_trade_no: String,
+    pub out_biz_no: Option<String>,
+
+    #[serde(alias = "buyer_id", alias = "buyer_open_id")]
+    pub buyer_id: Option<String>,
+    pub buyer_logon_id: Option<String>,
+    pub seller_id: Option<String>,
+    pub seller_email: Option<String>,
+
+    pub trade_status: String,
+    pub total_amount: String,
+    pub receipt_amount: Option<String>,
+    pub invoice_amount: Option<String>,
+    pub buyer_pay_amount: Option<String>,
+    pub point_amount: Option<String>,
+    pub refund_fee: Option<String>,
+    pub send_back_fee: Option<String>,
+
+    pub subject: Option<String>,
+    pub body: Option<String>,
+
+    pub gmt_create: Option<String>,
+    pub gmt_payment: Option<String>,
+    pub gmt_refund: Option<String>,
+    pub gmt_close: Option<String>,
+
+    pub fund_bill_list: Option<String>, // 原始 JSON 字符串，必要时再反序列化
+    pub voucher_detail_list: Option<String>, // 同上
+    pub biz_settle_mode: Option<String>,
+
+    pub merchant_app_id: Option<String>,
+    pub version: Option<String>,
+}
@@ -0,0 +1,137 @@
+use super::{PaymentProvider, ProviderNotification, ProviderOrder, ProviderTrade};
+use crate::{
+    model::{
+        pay_order,
+        sea_orm_active_enums::{OrderStatus, PayFrom},
+    },
+    utils::rand::rand_alphanumeric,
+    views::pay::{PayMode, PaymentAction},
+};
+use anyhow::{anyhow, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use summer::async_trait;
+use summer_web::axum::http::HeaderMap;
+
+/// 本地模拟支付渠道，仅用于开发/测试环境离线跑通购买流程。
+///
+/// 渠道侧的交易状态直接保存在订单的 `resp.status` 中：
+/// `POST /api/pay/mock/{order_id}/pay` 生成一条模拟回调，走与真实渠道相同的回调日志和履约流程。
+pub struct MockProvider;
+
+impl MockProvider {
+    /// 构造一条模拟的支付成功回调报文
+    pub fn paid_notification(order_id: i64) -> Vec<u8> {
+        let notify = MockNotify {
+            event_id: format!("mock-{order_id}-{}", rand_alphanumeric(16)),
+            order_id,
+            status: OrderStatus::Paid,
+        };
+        serde_json::to_vec(&notify).expect("mock notify to json failed")
+    }
+
+    fn trade_status(order: &pay_order::Model) -> OrderStatus {
+        order
+            .resp
+            .as_ref()
+            .and_then(|resp| resp.get("status"))
+            .and_then(|status| serde_json::from_value(status.clone()).ok())
+            .unwrap_or(OrderStatus::Created)
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for MockProvider {
+    fn pay_from(&self) -> PayFrom {
+        PayFrom::Mock
+    }
+
+    fn supports(&self, _mode: PayMode) -> bool {
+        true
+    }
+
+    async fn create(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        let pay_url = format!("/api/pay/mock/{}/pay", order.order.id);
+        let action = match order.checkout.mode {
+            PayMode::Native => PaymentAction::QrCode { code_url: pay_url },
+            PayMode::Page | PayMode::Wap | PayMode::H5 => PaymentAction::Redirect { url: pay_url },
+            PayMode::Jsapi => PaymentAction::Jsapi {
+                params: json!({ "mock_pay_url": pay_url }),
+            },
+        };
+        Ok(ProviderTrade {
+            status: OrderStatus::Created,
+            action: Some(action),
+            resp: Some(json!({
+                "status": OrderStatus::Created,
+                "subject": order.subject,
+                "amount": order.amount,
+            })),
+        })
+    }
+
+    async fn query(&self, order: &pay_order::Model) -> anyhow::Result<ProviderTrade> {
+        let status = Self::trade_status(order);
+        Ok(ProviderTrade {
+            status,
+            action: None,
+            resp: Some(json!({ "status": status })),
+        })
+    }
+
+    async fn close(&self, order: &pay_order::Model) -> anyhow::Result<Option<Value>> {
+        match Self::trade_status(order) {
+            OrderStatus::Paid => Err(anyhow!("模拟订单#{}已支付，不能关闭", order.id)),
+            _ => Ok(Some(json!({ "status": OrderStatus::Closed }))),
+        }
+    }
+
+    async fn refund(
+        &self,
+        order: &pay_order::Model,
+        refund_no: &str,
+        total: i32,
+        amount: i32,
+        reason: &str,
+    ) -> anyhow::Result<Value> {
+        Ok(json!({
+            "order_id": order.id,
+            "refund_no": refund_no,
+            "total": total,
+            "refund": amount,
+            "reason": reason,
+        }))
+    }
+
+    async fn verify_notification(
+        &self,
+        _headers: &HeaderMap,
+        _raw_body: &[u8],
+    ) -> anyhow::Result<()> {
+        // 模拟回调只由服务端自己生成，不对外暴露回调地址
+        Ok(())
+    }
+
+    fn notification_id(&self, raw_body: &[u8]) -> Option<String> {
+        serde_json::from_slice::<MockNotify>(raw_body)
+            .ok()
+            .map(|notify| notify.event_id)
+    }
+
+    fn parse_notification(&self, raw_body: &[u8]) -> anyhow::Result<ProviderNotification> {
+        let notify =
+            serde_json::from_slice::<MockNotify>(raw_body).context("模拟回调数据解析失败")?;
+        Ok(ProviderNotification {
+            order_id: notify.order_id,
+            status: notify.status,
+            resp: serde_json::to_value(&notify).context("resp to json failed")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MockNotify {
+    event_id: String,
+    order_id: i64,
+    status: OrderStatus,
+}
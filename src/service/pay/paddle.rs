@@ -0,0 +1,338 @@
+use super::{PaymentProvider, ProviderNotification, ProviderOrder, ProviderTrade};
+use crate::{
+    config::pay::PayConfig,
+    model::{
+        pay_order,
+        sea_orm_active_enums::{OrderKind, OrderLevel, OrderStatus, PayFrom},
+    },
+    views::pay::{PayMode, PaymentAction},
+};
+use anyhow::{anyhow, Context};
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Client, RequestBuilder,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use subtle::ConstantTimeEq;
+use summer::{async_trait, tracing};
+use summer_web::axum::http::HeaderMap;
+
+const PADDLE_SIGNATURE_PREFIX: &str = "h1=";
+const PADDLE_SIGNATURE_TS_PREFIX: &str = "ts=";
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub struct PaddleProvider {
+    client: Client,
+    config: PayConfig,
+}
+
+impl PaddleProvider {
+    pub fn new(client: Client, config: PayConfig) -> Self {
+        Self { client, config }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.config.paddle_api_url.trim_end_matches('/'))
+    }
+
+    fn authorized(&self, builder: RequestBuilder) -> RequestBuilder {
+        builder.header(
+            AUTHORIZATION,
+            format!("Bearer {}", self.config.paddle_api_key),
+        )
+    }
+
+    fn transaction_id(order: &pay_order::Model) -> Option<&str> {
+        order
+            .resp
+            .as_ref()
+            .and_then(|resp| resp.pointer("/data/id"))
+            .and_then(Value::as_str)
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for PaddleProvider {
+    fn pay_from(&self) -> PayFrom {
+        PayFrom::Paddle
+    }
+
+    /// Paddle 统一跳转到托管收银台，默认方式与 PC 网页跳转等价
+    fn supports(&self, mode: PayMode) -> bool {
+        matches!(mode, PayMode::Native | PayMode::Page)
+    }
+
+    async fn create(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        // Paddle 按目录价格下单，无法按补差价金额收款
+        if order.order.kind == OrderKind::Upgrade {
+            return Err(anyhow!("Paddle 暂不支持升级补差价"));
+        }
+        let pay_order::Model {
+            id: order_id,
+            user_id,
+            level,
+            edition,
+            ..
+        } = order.order.clone();
+
+        let price_id = match level {
+            OrderLevel::Monthly => &self.config.paddle_monthly_price_id,
+            OrderLevel::Annual => &self.config.paddle_annual_price_id,
+        };
+        if price_id.is_empty() {
+            return Err(anyhow!("Paddle price_id 未配置: {level:?}"));
+        }
+        if self.config.paddle_api_key.is_empty() {
+            return Err(anyhow!("Paddle API Key 未配置"));
+        }
+
+        let body = PaddleCreateTransactionReq {
+            items: vec![PaddleTransactionItem {
+                price_id: price_id.clone(),
+                quantity: 1,
+            }],
+            collection_mode: "automatic",
+            custom_data: json!({
+                "order_id": order_id,
+                "user_id": user_id,
+                "level": level,
+                "edition": edition,
+            }),
+        };
+
+        let resp = self
+            .authorized(self.client.post(self.url("/transactions")))
+            .header(CONTENT_TYPE, "application/json")
+            .json(&body)
+            .send()
+            .await
+            .context("Paddle 订单创建请求失败")?
+            .error_for_status()
+            .context("Paddle 订单创建失败")?
+            .json::<PaddleApiResp<PaddleTransaction>>()
+            .await
+            .context("Paddle 订单创建响应解析失败")?;
+
+        let resp_json = serde_json::to_value(&resp).context("Paddle resp to json failed")?;
+        Ok(ProviderTrade {
+            status: OrderStatus::Created,
+            action: resp
+                .data
+                .checkout
+                .and_then(|checkout| checkout.url)
+                .map(|url| PaymentAction::Redirect { url }),
+            resp: Some(resp_json),
+        })
+    }
+
+    async fn query(&self, order: &pay_order::Model) -> anyhow::Result<ProviderTrade> {
+        let transaction_id = Self::transaction_id(order)
+            .ok_or_else(|| anyhow!("Paddle transaction_id 不存在: order_id={}", order.id))?;
+
+        let resp = self
+            .authorized(
+                self.client
+                    .get(self.url(&format!("/transactions/{transaction_id}"))),
+            )
+            .send()
+            .await
+            .context("Paddle 订单查询请求失败")?
+            .error_for_status()
+            .context("Paddle 订单查询失败")?
+            .json::<PaddleApiResp<PaddleTransaction>>()
+            .await
+            .context("Paddle 订单查询响应解析失败")?;
+
+        tracing::info!("Paddle 订单#{}状态: {}", order.id, resp.data.status);
+
+        Ok(ProviderTrade {
+            status: OrderStatus::from_paddle(&resp.data.status),
+            action: None,
+            resp: Some(serde_json::to_value(resp).context("Paddle resp to json failed")?),
+        })
+    }
+
+    async fn close(&self, order: &pay_order::Model) -> anyhow::Result<Option<Value>> {
+        let Some(transaction_id) = Self::transaction_id(order) else {
+            // Paddle 交易未创建成功，无需远程取消
+            return Ok(None);
+        };
+
+        let resp = self
+            .authorized(
+                self.client
+                    .patch(self.url(&format!("/transactions/{transaction_id}"))),
+            )
+            .header(CONTENT_TYPE, "application/json")
+            .json(&json!({ "status": "canceled" }))
+            .send()
+            .await
+            .context("Paddle 取消订单请求失败")?
+            .error_for_status()
+            .context("Paddle 取消订单失败")?
+            .json::<PaddleApiResp<PaddleTransaction>>()
+            .await
+            .context("Paddle 取消订单响应解析失败")?;
+
+        Ok(Some(
+            serde_json::to_value(resp).context("Paddle resp to json failed")?,
+        ))
+    }
+
+    async fn refund(
+        &self,
+        order: &pay_order::Model,
+        _refund_no: &str,
+        _total: i32,
+        _amount: i32,
+        reason: &str,
+    ) -> anyhow::Result<Value> {
+        let transaction_id = Self::transaction_id(order)
+            .ok_or_else(|| anyhow!("Paddle transaction_id 不存在: order_id={}", order.id))?;
+
+        // Paddle 按交易整单退款，部分退款已在 PayOrderService::refund_order 中拒绝
+        self.authorized(self.client.post(self.url("/adjustments")))
+            .header(CONTENT_TYPE, "application/json")
+            .json(&json!({
+                "action": "refund",
+                "type": "full",
+                "transaction_id": transaction_id,
+                "reason": reason,
+            }))
+            .send()
+            .await
+            .context("Paddle 退款请求失败")?
+            .error_for_status()
+            .context("Paddle 退款失败")?
+            .json::<Value>()
+            .await
+            .context("Paddle 退款响应解析失败")
+    }
+
+    async fn verify_notification(
+        &self,
+        headers: &HeaderMap,
+        raw_body: &[u8],
+    ) -> anyhow::Result<()> {
+        if self.config.paddle_webhook_secret.is_empty() {
+            return Err(anyhow!("Paddle Webhook Secret 未配置"));
+        }
+        let signature_header = headers
+            .get("Paddle-Signature")
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default();
+
+        let mut timestamp = None;
+        let mut signature = None;
+        for item in signature_header.split(';').map(str::trim) {
+            if let Some(value) = item.strip_prefix(PADDLE_SIGNATURE_TS_PREFIX) {
+                timestamp = Some(value);
+            }
+            if let Some(value) = item.strip_prefix(PADDLE_SIGNATURE_PREFIX) {
+                signature = Some(value);
+            }
+        }
+
+        let timestamp = timestamp.ok_or_else(|| anyhow!("Paddle 签名缺少 ts"))?;
+        let signature = signature.ok_or_else(|| anyhow!("Paddle 签名缺少 h1"))?;
+        let signature = hex::decode(signature).context("Paddle 签名格式错误")?;
+
+        let mut mac = HmacSha256::new_from_slice(self.config.paddle_webhook_secret.as_bytes())
+            .context("Paddle HMAC 初始化失败")?;
+        mac.update(timestamp.as_bytes());
+        mac.update(b":");
+        mac.update(raw_body);
+        let expected = mac.finalize().into_bytes();
+
+        if expected.as_slice().ct_eq(signature.as_slice()).into() {
+            Ok(())
+        } else {
+            Err(anyhow!("Paddle 验签失败"))
+        }
+    }
+
+    fn notification_id(&self, raw_body: &[u8]) -> Option<String> {
+        serde_json::from_slice::<PaddleWebhookEvent>(raw_body)
+            .ok()
+            .map(|event| event.event_id)
+    }
+
+    fn parse_notification(&self, raw_body: &[u8]) -> anyhow::Result<ProviderNotification> {
+        let event = serde_json::from_slice::<PaddleWebhookEvent>(raw_body)
+            .context("Paddle 回调数据解析失败")?;
+
+        tracing::info!(
+            "接收到 Paddle 事件: {}, transaction status: {}",
+            event.event_type,
+            event.data.status
+        );
+
+        let order_id = event
+            .data
+            .custom_data
+            .as_ref()
+            .and_then(|custom_data| custom_data.get("order_id"))
+            .and_then(Value::as_i64)
+            .ok_or_else(|| anyhow!("Paddle 回调缺少 custom_data.order_id"))?;
+
+        Ok(ProviderNotification {
+            order_id,
+            status: OrderStatus::from_paddle_event(&event.event_type, &event.data.status),
+            resp: serde_json::to_value(event).context("Paddle event to json failed")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PaddleCreateTransactionReq {
+    items: Vec<PaddleTransactionItem>,
+    collection_mode: &'static str,
+    custom_data: Value,
+}
+
+#[derive(Debug, Serialize)]
+struct PaddleTransactionItem {
+    price_id: String,
+    quantity: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaddleApiResp<T> {
+    pub data: T,
+    #[serde(default)]
+    pub meta: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaddleTransaction {
+    pub id: String,
+    pub status: String,
+    #[serde(default)]
+    pub custom_data: Option<Value>,
+    #[serde(default)]
+    pub checkout: Option<PaddleCheckout>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaddleCheckout {
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PaddleWebhookEvent {
+    pub event_id: String,
+    pub event_type: String,
+    pub occurred_at: String,
+    pub data: PaddleTransaction,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
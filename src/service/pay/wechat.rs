@@ -0,0 +1,398 @@
+use super::{
+    build_pay_out_trade_no, parse_pay_out_trade_no, PaymentProvider, ProviderNotification,
+    ProviderOrder, ProviderTrade,
+};
+use crate::{
+    model::{
+        pay_order,
+        sea_orm_active_enums::{OrderStatus, PayFrom},
+    },
+    views::pay::{PayMode, PaymentAction},
+};
+use anyhow::{anyhow, Context};
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{collections::HashMap, env, fs::File, io::Write as _, path::Path, sync::Arc};
+use summer::{async_trait, tracing};
+use summer_web::axum::http::HeaderMap;
+use wechat_pay_rust_sdk::{
+    model::{
+        H5Params, H5SceneInfo, JsapiParams, NativeParams, WechatPayDecodeData, WechatPayNotify,
+    },
+    pay::{PayNotifyTrait, WechatPay, WechatPayTrait},
+    request::HttpMethod,
+    response::{H5Response, JsapiResponse, NativeResponse, ResponseTrait},
+};
+
+/// 微信 H5 支付场景信息中的应用名称
+const H5_APP_NAME: &str = "AutoWDS";
+
+pub struct WechatProvider {
+    client: Arc<WechatPay>,
+    http: reqwest::Client,
+    /// H5 支付完成后返回的页面
+    return_url: String,
+}
+
+impl WechatProvider {
+    pub fn new(client: WechatPay, return_url: String) -> Self {
+        Self {
+            client: Arc::new(client),
+            http: reqwest::Client::new(),
+            return_url,
+        }
+    }
+
+    async fn native_pay(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.order.id, order.order.created);
+        let resp = self
+            .client
+            .native_pay(NativeParams::new(
+                order.subject.to_string(),
+                out_trade_no,
+                order.amount.into(),
+            ))
+            .await
+            .context("微信订单创建失败")?;
+        let NativeResponse {
+            code_url,
+            code,
+            message,
+        } = resp;
+        tracing::info!("wechat pay resp code ==> {code:?}, message ==> {message:?}");
+        Ok(ProviderTrade {
+            status: OrderStatus::Created,
+            action: code_url.map(|code_url| PaymentAction::QrCode { code_url }),
+            resp: None,
+        })
+    }
+
+    /// 微信外的手机浏览器支付，`h5_url` 后拼接 `redirect_url` 指定支付完成后的返回页面
+    async fn h5_pay(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.order.id, order.order.created);
+        let client_ip = order
+            .checkout
+            .client_ip
+            .as_deref()
+            .ok_or_else(|| anyhow!("微信 H5 支付缺少用户IP"))?;
+        let resp = self
+            .client
+            .h5_pay(H5Params::new(
+                order.subject.to_string(),
+                out_trade_no,
+                order.amount.into(),
+                H5SceneInfo::new(client_ip, H5_APP_NAME, &self.return_url),
+            ))
+            .await
+            .context("微信H5订单创建失败")?;
+        let H5Response {
+            h5_url,
+            code,
+            message,
+        } = resp;
+        tracing::info!("wechat h5 pay resp code ==> {code:?}, message ==> {message:?}");
+        let h5_url = h5_url.ok_or_else(|| anyhow!("微信H5下单失败: {code:?} {message:?}"))?;
+        let url = if self.return_url.is_empty() {
+            h5_url
+        } else {
+            format!(
+                "{h5_url}&redirect_url={}",
+                urlencoding::encode(&self.return_url)
+            )
+        };
+        Ok(ProviderTrade {
+            status: OrderStatus::Created,
+            action: Some(PaymentAction::Redirect { url }),
+            resp: None,
+        })
+    }
+
+    /// 微信内网页支付，返回 `getBrandWCPayRequest` 所需的签名参数
+    async fn jsapi_pay(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.order.id, order.order.created);
+        let openid = order
+            .checkout
+            .openid
+            .as_deref()
+            .ok_or_else(|| anyhow!("微信 JSAPI 支付缺少 openid"))?;
+        let resp = self
+            .client
+            .jsapi_pay(JsapiParams::new(
+                order.subject.to_string(),
+                out_trade_no,
+                order.amount.into(),
+                openid.into(),
+            ))
+            .await
+            .context("微信JSAPI订单创建失败")?;
+        let JsapiResponse {
+            code,
+            message,
+            prepay_id,
+            sign_data,
+        } = resp;
+        tracing::info!(
+            "wechat jsapi pay resp code ==> {code:?}, message ==> {message:?}, prepay_id ==> {prepay_id:?}"
+        );
+        let sign_data =
+            sign_data.ok_or_else(|| anyhow!("微信JSAPI下单失败: {code:?} {message:?}"))?;
+        let params = json!({
+            "appId": sign_data.app_id,
+            "timeStamp": sign_data.timestamp,
+            "nonceStr": sign_data.nonce_str,
+            "package": sign_data.package,
+            "signType": sign_data.sign_type,
+            "paySign": sign_data.pay_sign,
+        });
+        Ok(ProviderTrade {
+            status: OrderStatus::Created,
+            action: Some(PaymentAction::Jsapi { params }),
+            resp: None,
+        })
+    }
+
+    /// SDK 没有封装的 v3 接口（关单、退款）由这里签名后直接调用
+    async fn post_v3(&self, url: &str, body: Value) -> anyhow::Result<reqwest::Response> {
+        let body = body.to_string();
+        let headers = self
+            .client
+            .get_headers(HttpMethod::POST, url, &body)
+            .context("微信请求签名失败")?;
+        self.http
+            .post(format!("{}{url}", self.client.base_url()))
+            .headers(headers)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .with_context(|| format!("微信请求失败: {url}"))
+    }
+
+    async fn get_wechat_pub_key(&self, serial: &str) -> anyhow::Result<String> {
+        let pub_key_dir =
+            env::var("WECHAT_PAY_PUB_KEY_DIR").unwrap_or("/data/wechat-cert/pubkey".to_string());
+        let cert_dir = Path::new(&pub_key_dir);
+        if !cert_dir.exists() {
+            std::fs::create_dir_all(cert_dir)
+                .with_context(|| format!("create dir {cert_dir:?} failed"))?;
+        }
+        let cert_path = format!("{pub_key_dir}/{serial}/pubkey.pem");
+        let cert_path = Path::new(&cert_path);
+        if cert_path.exists() {
+            let pub_key = std::fs::read_to_string(cert_path)
+                .with_context(|| format!("read pub key from {cert_path:?} failed"))?;
+            return Ok(pub_key);
+        }
+
+        let wechat = self.client.clone();
+        tracing::info!("fetch wechat pay certificates from wechat server");
+
+        let resp = wechat
+            .certificates()
+            .await
+            .context("获取微信平台证书失败")?;
+
+        let certs = resp.data.ok_or_else(|| anyhow!("微信平台证书为空"))?;
+
+        for cert in certs {
+            let serial_no = cert.serial_no;
+            let ciphertext = cert.encrypt_certificate.ciphertext;
+            let nonce = cert.encrypt_certificate.nonce;
+            let associated_data = cert.encrypt_certificate.associated_data;
+            let data = wechat
+                .decrypt_bytes(ciphertext, nonce, associated_data)
+                .context("微信平台证书解密失败")?;
+            let pub_key = wechat_pay_rust_sdk::util::x509_to_pem(data.as_slice())
+                .map_err(|e| anyhow!("微信平台证书转换PEM失败:{e}"))?;
+            let cert_path = format!("{pub_key_dir}/{serial_no}/pubkey.pem");
+            let mut pub_key_file = File::create(cert_path).context("create pub key file failed")?;
+            pub_key_file
+                .write_all(pub_key.as_bytes())
+                .context("write pub key file failed")?;
+
+            let (pub_key_valid, expire_timestamp) =
+                wechat_pay_rust_sdk::util::x509_is_valid(data.as_slice())
+                    .map_err(|e| anyhow!("公钥验证失败:{e}"))?;
+            tracing::debug!(
+                "pub key valid:{} expire_timestamp:{}",
+                pub_key_valid,
+                expire_timestamp
+            ); //检测证书是否可用,打印过期时间
+        }
+
+        let cert_path = format!("{pub_key_dir}/{serial}/pubkey.pem");
+        let cert_path = Path::new(&cert_path);
+        if cert_path.exists() {
+            let pub_key = std::fs::read_to_string(cert_path)
+                .with_context(|| format!("read pub key from {cert_path:?} failed"))?;
+            return Ok(pub_key);
+        } else {
+            return Err(anyhow!("微信公钥不存在"));
+        }
+    }
+}
+
+#[async_trait]
+impl PaymentProvider for WechatProvider {
+    fn pay_from(&self) -> PayFrom {
+        PayFrom::Wechat
+    }
+
+    fn supports(&self, mode: PayMode) -> bool {
+        matches!(mode, PayMode::Native | PayMode::H5 | PayMode::Jsapi)
+    }
+
+    async fn create(&self, order: &ProviderOrder<'_>) -> anyhow::Result<ProviderTrade> {
+        match order.checkout.mode {
+            PayMode::H5 => self.h5_pay(order).await,
+            PayMode::Jsapi => self.jsapi_pay(order).await,
+            _ => self.native_pay(order).await,
+        }
+    }
+
+    async fn query(&self, order: &pay_order::Model) -> anyhow::Result<ProviderTrade> {
+        let out_trade_no = build_pay_out_trade_no(order.id, order.created);
+        let mchid = &self.client.mch_id;
+        let resp = self
+            .client
+            .get_pay::<WechatPayOrderResp>(&format!(
+                "/v3/pay/transactions/out-trade-no/{out_trade_no}?mchid={mchid}"
+            ))
+            .await
+            .with_context(|| format!("微信订单查询失败: {out_trade_no}"))?;
+
+        tracing::info!(
+            "微信订单#{}状态: {}({})",
+            order.id,
+            resp.trade_state,
+            resp.trade_state_desc
+        );
+
+        Ok(ProviderTrade {
+            status: OrderStatus::from_wechat(&resp.trade_state),
+            action: None,
+            resp: Some(serde_json::to_value(resp).context("resp to json failed")?),
+        })
+    }
+
+    async fn close(&self, order: &pay_order::Model) -> anyhow::Result<Option<Value>> {
+        let out_trade_no = build_pay_out_trade_no(order.id, order.created);
+        let url = format!("/v3/pay/transactions/out-trade-no/{out_trade_no}/close");
+        let resp = self
+            .post_v3(&url, json!({ "mchid": self.client.mch_id }))
+            .await?;
+
+        // 关单成功时微信返回 204 No Content
+        if resp.status().is_success() {
+            return Ok(None);
+        }
+        let status = resp.status();
+        let text = resp.text().await.unwrap_or_default();
+        Err(anyhow!("微信关单失败({status}): {text}"))
+    }
+
+    async fn refund(
+        &self,
+        order: &pay_order::Model,
+        refund_no: &str,
+        total: i32,
+        amount: i32,
+        reason: &str,
+    ) -> anyhow::Result<Value> {
+        let out_trade_no = build_pay_out_trade_no(order.id, order.created);
+        let body = json!({
+            "out_trade_no": out_trade_no,
+            "out_refund_no": refund_no,
+            "reason": reason,
+            "amount": {
+                "refund": amount,
+                "total": total,
+                "currency": "CNY",
+            },
+        });
+        let resp = self.post_v3("/v3/refund/domestic/refunds", body).await?;
+        let status = resp.status();
+        let resp = resp.json::<Value>().await.context("微信退款响应解析失败")?;
+        if status.is_success() {
+            Ok(resp)
+        } else {
+            Err(anyhow!("微信退款失败({status}): {resp}"))
+        }
+    }
+
+    async fn verify_notification(
+        &self,
+        headers: &HeaderMap,
+        raw_body: &[u8],
+    ) -> anyhow::Result<()> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+        };
+        let serial = header("Wechatpay-Serial");
+        let signature = header("Wechatpay-Signature");
+        let timestamp = header("Wechatpay-Timestamp");
+        let nonce = header("Wechatpay-Nonce");
+        let body = std::str::from_utf8(raw_body).context("微信回调报文不是合法的UTF-8")?;
+
+        let pub_key = self.get_wechat_pub_key(serial).await?;
+        self.client
+            .verify_signature(&pub_key, timestamp, nonce, signature, body)
+            .with_context(|| {
+                format!(
+                    "微信验签失败，非法数据: serial={serial}, timestamp={timestamp}, nonce={nonce}"
+                )
+            })?;
+        Ok(())
+    }
+
+    fn notification_id(&self, raw_body: &[u8]) -> Option<String> {
+        serde_json::from_slice::<WechatPayNotify>(raw_body)
+            .ok()
+            .map(|notify| notify.id)
+    }
+
+    fn parse_notification(&self, raw_body: &[u8]) -> anyhow::Result<ProviderNotification> {
+        let notify =
+            serde_json::from_slice::<WechatPayNotify>(raw_body).context("微信回调数据解析失败")?;
+        let resource = notify.resource.clone();
+        let nonce = resource.nonce;
+        let ciphertext = resource.ciphertext;
+        let associated_data = resource.associated_data.unwrap_or_default();
+        let data: WechatPayDecodeData = self
+            .client
+            .decrypt_paydata(
+                ciphertext,      //加密数据
+                nonce,           //随机串
+                associated_data, //关联数据
+            )
+            .context("解析关联数据失败")?;
+
+        tracing::info!("接收到微信订单状态: {}", data.trade_state);
+
+        let order_id = parse_pay_out_trade_no(&data.out_trade_no).context("解析订单号失败")?;
+        Ok(ProviderNotification {
+            order_id,
+            status: OrderStatus::from_wechat(&data.trade_state),
+            resp: serde_json::to_value(notify).context("resp to json failed")?,
+        })
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WechatPayOrderResp {
+    pub appid: String,
+    pub mchid: String,
+    pub out_trade_no: String,
+    pub trade_state: String,
+    pub trade_state_desc: String,
+    pub transaction_id: Option<String>,
+    pub trade_type: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+impl ResponseTrait for WechatPayOrderResp {}
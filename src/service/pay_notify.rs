@@ -0,0 +1,190 @@
+use crate::{
+    model::{
+        pay_notify_event::{self, Entity as PayNotifyEvent},
+        pay_order,
+        sea_orm_active_enums::{NotifyStatus, PayFrom},
+    },
+    service::{pay::PayOrderService, user::UserService},
+};
+use anyhow::{anyhow, Context};
+use chrono::{Duration, Local};
+use sea_orm::{ActiveModelTrait, ActiveValue::Set, DbConn};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use summer::{plugin::service::Service, tracing};
+use summer_web::axum::http::{header, HeaderMap};
+
+/// 自动重试的最大次数，超过后只能由管理员手动重放
+const MAX_NOTIFY_ATTEMPTS: i32 = 8;
+const EVENT_ID_MAX_LEN: usize = 128;
+
+/// 支付回调事件日志。
+///
+/// 所有渠道推送先原样落库再处理，按 `(provider, event_id)` 去重：
+/// 已处理成功的事件再次推送时直接应答成功，不会重复开通会员。
+#[derive(Clone, Service)]
+pub struct PayNotifyService {
+    #[inject(component)]
+    db: DbConn,
+}
+
+impl PayNotifyService {
+    /// 记录并处理一次渠道回调，返回处理后的事件记录
+    pub async fn receive(
+        &self,
+        provider: PayFrom,
+        raw_body: &[u8],
+        headers: &HeaderMap,
+        signature_valid: bool,
+        ps: &PayOrderService,
+        us: &UserService,
+    ) -> anyhow::Result<pay_notify_event::Model> {
+        let event_id = Self::extract_event_id(ps, provider, raw_body);
+        let (event, first_seen) = PayNotifyEvent::insert_if_absent(
+            &self.db,
+            provider,
+            &event_id,
+            String::from_utf8_lossy(raw_body).into_owned(),
+            Self::headers_to_json(headers),
+            signature_valid,
+        )
+        .await?;
+
+        if !first_seen {
+            tracing::info!(
+                "收到重复的{provider}回调事件: {event_id}, 当前状态: {}",
+                event.status
+            );
+        }
+        if !signature_valid {
+            return Ok(event);
+        }
+
+        match event.status {
+            NotifyStatus::Processed => Ok(event),
+            // 首次推送验签失败（如平台证书未及时更新），渠道重推且验签通过后继续处理。
+            // 事件ID取自未验签的报文，此前落库的报文可能是伪造的，须替换为本次验签通过的报文
+            NotifyStatus::Rejected => {
+                let event = pay_notify_event::ActiveModel {
+                    id: Set(event.id),
+                    raw_body: Set(String::from_utf8_lossy(raw_body).into_owned()),
+                    headers: Set(Some(Self::headers_to_json(headers))),
+                    signature_valid: Set(true),
+                    status: Set(NotifyStatus::Pending),
+                    ..Default::default()
+                }
+                .update(&self.db)
+                .await
+                .with_context(|| format!("update pay_notify_event({}) failed", event.id))?;
+                self.process(event, ps, us).await
+            }
+            NotifyStatus::Pending | NotifyStatus::Failed => self.process(event, ps, us).await,
+        }
+    }
+
+    /// 处理事件：更新订单状态，订单支付成功时开通会员。
+    ///
+    /// 处理结果（成功/失败原因/次数）回写到事件记录，失败的事件由定时任务重试。
+    pub async fn process(
+        &self,
+        event: pay_notify_event::Model,
+        ps: &PayOrderService,
+        us: &UserService,
+    ) -> anyhow::Result<pay_notify_event::Model> {
+        if !event.signature_valid {
+            return Err(anyhow!("回调事件#{}验签未通过，不能处理", event.id));
+        }
+
+        let result = Self::apply(&event, ps, us).await;
+        let mut active = pay_notify_event::ActiveModel {
+            id: Set(event.id),
+            attempts: Set(event.attempts + 1),
+            ..Default::default()
+        };
+        match &result {
+            Ok(order) => {
+                active.status = Set(NotifyStatus::Processed);
+                active.order_id = Set(Some(order.id));
+                active.error_message = Set(None);
+                active.processed_at = Set(Some(Local::now().naive_local()));
+            }
+            Err(e) => {
+                tracing::error!("处理{}回调事件#{}失败: {e:#}", event.provider, event.id);
+                active.status = Set(NotifyStatus::Failed);
+                active.error_message = Set(Some(format!("{e:#}")));
+            }
+        }
+
+        active
+            .update(&self.db)
+            .await
+            .with_context(|| format!("update pay_notify_event({}) failed", event.id))
+    }
+
+    /// 重试处理失败的事件，按已尝试次数线性退避
+    pub async fn retry_failed(
+        &self,
+        ps: &PayOrderService,
+        us: &UserService,
+    ) -> anyhow::Result<usize> {
+        let now = Local::now().naive_local();
+        let events = PayNotifyEvent::find_retryable(
+            &self.db,
+            MAX_NOTIFY_ATTEMPTS,
+            now - Duration::minutes(1),
+            100,
+        )
+        .await?;
+
+        let mut retried = 0;
+        for event in events {
+            if event.modified + Duration::minutes(event.attempts as i64 * 2) > now {
+                continue;
+            }
+            retried += 1;
+            let event_id = event.id;
+            match self.process(event, ps, us).await {
+                Ok(event) => {
+                    tracing::info!("重试回调事件#{event_id}完成, 状态: {}", event.status)
+                }
+                Err(e) => tracing::error!("重试回调事件#{event_id}失败: {e:#}"),
+            }
+        }
+        Ok(retried)
+    }
+
+    async fn apply(
+        event: &pay_notify_event::Model,
+        ps: &PayOrderService,
+        us: &UserService,
+    ) -> anyhow::Result<pay_order::Model> {
+        let order = ps.notify(event.provider, event.raw_body.as_bytes()).await?;
+        us.fulfil_paid_order(&order).await?;
+        // 会员开通后再广播，前端收到 paid 时权益已经生效
+        ps.publish_status(&order).await;
+        Ok(order)
+    }
+
+    /// 提取渠道事件ID（微信 `id`、支付宝 `notify_id`、Paddle `event_id`）。
+    ///
+    /// 无法解析时使用原始报文的 SHA-256，保证同一报文重复推送仍能去重。
+    fn extract_event_id(ps: &PayOrderService, provider: PayFrom, raw_body: &[u8]) -> String {
+        ps.notification_id(provider, raw_body)
+            .filter(|id| !id.is_empty() && id.len() <= EVENT_ID_MAX_LEN)
+            .unwrap_or_else(|| hex::encode(Sha256::digest(raw_body)))
+    }
+
+    fn headers_to_json(headers: &HeaderMap) -> Value {
+        let headers = headers
+            .iter()
+            .filter(|(name, _)| *name != header::AUTHORIZATION && *name != header::COOKIE)
+            .map(|(name, value)| {
+                (
+                    name.to_string(),
+                    Value::String(String::from_utf8_lossy(value.as_bytes()).into_owned()),
+                )
+            })
+            .collect::<Map<String, Value>>();
+        Value::Object(headers)
+    }
+}
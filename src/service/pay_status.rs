@@ -0,0 +1,256 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{LazyLock, Once},
+    time::Duration,
+};
+
+use anyhow::Context;
+use futures_util::StreamExt;
+use sea_orm::{DbConn, EntityTrait};
+use summer::{plugin::service::Service, tracing};
+use summer_redis::{config::RedisConfig, redis, Redis};
+use summer_web::axum::response::sse::{Event, Sse};
+use summer_web::error::{KnownWebError, Result};
+use tokio::{
+    sync::{broadcast, mpsc, Mutex},
+    time::Instant,
+};
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::model::{pay_order, sea_orm_active_enums::OrderStatus};
+use crate::service::{pay::PayOrderService, user::UserService};
+use crate::utils::lock;
+use crate::views::pay::PayStatusEvent;
+
+pub type PayStatusSse = Sse<ReceiverStream<std::result::Result<Event, Infallible>>>;
+
+const STATUS_SSE_CHANNEL_CAPACITY: usize = 4;
+const STATUS_BROADCAST_CAPACITY: usize = 4;
+/// 等待回调的时长，超时后主动向渠道查单
+const NOTIFY_WAIT: Duration = Duration::from_secs(15);
+/// 单个 SSE 连接最长保持时间，超过后由前端重连或回退到轮询
+const STATUS_STREAM_TTL: Duration = Duration::from_secs(10 * 60);
+/// 订阅连接断开后重连的间隔
+const FANOUT_RECONNECT_DELAY: Duration = Duration::from_secs(3);
+const SSE_EVENT_STATUS: &str = "status";
+const PAY_STATUS_CHANNEL_PATTERN: &str = "pay:order:*:status";
+
+/// 本实例上等待支付结果的订单，同一订单的多个 SSE 连接共享一个广播
+static PAY_STATUS_WATCHERS: LazyLock<Mutex<HashMap<i64, broadcast::Sender<OrderStatus>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static PAY_STATUS_FANOUT: Once = Once::new();
+
+/// 订单状态广播的 Redis 频道
+pub fn pay_status_channel(order_id: i64) -> String {
+    format!("pay:order:{order_id}:status")
+}
+
+/// 支付结果推送。
+///
+/// 回调处理完成后由 [`PayOrderService::publish_status`] 发布到 Redis，
+/// 每个实例只用一个模式订阅接收所有订单的状态，再分发给本实例上等待该订单的 SSE 连接；
+/// 回调迟迟未到时按 [`NOTIFY_WAIT`] 间隔主动查单，同一订单的查单在所有连接间限流。
+#[derive(Clone, Service)]
+pub struct PayStatusService {
+    #[inject(component)]
+    db: DbConn,
+    #[inject(component)]
+    redis: Redis,
+    #[inject(config)]
+    redis_config: RedisConfig,
+}
+
+impl PayStatusService {
+    /// 推送订单状态：先推送当前状态，订单进入终态（支付/关闭/退款）后推送最终状态并结束连接
+    pub async fn open_order_status(
+        &self,
+        user_id: i64,
+        order_id: i64,
+        ps: PayOrderService,
+        us: UserService,
+    ) -> Result<PayStatusSse> {
+        pay_order::Entity::find_order_status(&self.db, order_id, user_id)
+            .await?
+            .ok_or_else(|| KnownWebError::not_found("订单不存在"))?;
+
+        // 先订阅再读取订单，避免两者之间完成的回调被漏掉
+        let redis_uri = self.redis_config.uri.clone();
+        PAY_STATUS_FANOUT.call_once(|| {
+            tokio::spawn(Self::run_status_fanout(redis_uri));
+        });
+        let (sender, receiver) = Self::watch_order(order_id).await;
+
+        let order = match self.find_order(order_id).await {
+            Ok(order) => order,
+            Err(e) => {
+                drop(receiver);
+                Self::unwatch_order(order_id, &sender).await;
+                return Err(e);
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(STATUS_SSE_CHANNEL_CAPACITY);
+        let redis = self.redis.clone();
+        tokio::spawn(async move {
+            Self::run_status_stream(order, receiver, ps, us, redis, tx).await;
+            Self::unwatch_order(order_id, &sender).await;
+        });
+        Ok(Sse::new(ReceiverStream::new(rx)))
+    }
+
+    async fn find_order(&self, order_id: i64) -> Result<pay_order::Model> {
+        Ok(pay_order::Entity::find_by_id(order_id)
+            .one(&self.db)
+            .await
+            .with_context(|| format!("find_pay_order({order_id}) failed"))?
+            .ok_or_else(|| KnownWebError::not_found("订单不存在"))?)
+    }
+
+    async fn watch_order(
+        order_id: i64,
+    ) -> (
+        broadcast::Sender<OrderStatus>,
+        broadcast::Receiver<OrderStatus>,
+    ) {
+        let mut watchers = PAY_STATUS_WATCHERS.lock().await;
+        let sender = watchers
+            .entry(order_id)
+            .or_insert_with(|| broadcast::channel(STATUS_BROADCAST_CAPACITY).0)
+            .clone();
+        let receiver = sender.subscribe();
+        (sender, receiver)
+    }
+
+    /// 订单的最后一个 SSE 连接结束后移除广播
+    async fn unwatch_order(order_id: i64, sender: &broadcast::Sender<OrderStatus>) {
+        let mut watchers = PAY_STATUS_WATCHERS.lock().await;
+        if watchers
+            .get(&order_id)
+            .is_some_and(|current| current.same_channel(sender) && current.receiver_count() == 0)
+        {
+            watchers.remove(&order_id);
+        }
+    }
+
+    /// 常驻的模式订阅，连接断开后自动重连；断开期间等待中的连接依靠主动查单获取结果
+    async fn run_status_fanout(redis_uri: String) {
+        loop {
+            if let Err(e) = Self::fanout_status_messages(&redis_uri).await {
+                tracing::error!("订单状态订阅失败: {e:#}");
+            }
+            tokio::time::sleep(FANOUT_RECONNECT_DELAY).await;
+        }
+    }
+
+    async fn fanout_status_messages(redis_uri: &str) -> anyhow::Result<()> {
+        let client = redis::Client::open(redis_uri).context("Redis client 创建失败")?;
+        let mut pubsub = client
+            .get_async_pubsub()
+            .await
+            .context("Redis 订阅连接失败")?;
+        pubsub
+            .psubscribe(PAY_STATUS_CHANNEL_PATTERN)
+            .await
+            .context("订阅订单状态失败")?;
+
+        let mut messages = pubsub.into_on_message();
+        while let Some(msg) = messages.next().await {
+            let event = msg
+                .get_payload::<String>()
+                .context("读取订单状态消息失败")
+                .and_then(|payload| {
+                    serde_json::from_str::<PayStatusEvent>(&payload).context("订单状态消息解析失败")
+                });
+            match event {
+                Ok(event) => {
+                    let watchers = PAY_STATUS_WATCHERS.lock().await;
+                    if let Some(sender) = watchers.get(&event.order_id) {
+                        let _ = sender.send(event.status);
+                    }
+                }
+                Err(e) => tracing::warn!("订单状态消息无效: {e:#}"),
+            }
+        }
+        Err(anyhow::anyhow!("订单状态订阅连接已断开"))
+    }
+
+    async fn run_status_stream(
+        mut order: pay_order::Model,
+        mut receiver: broadcast::Receiver<OrderStatus>,
+        ps: PayOrderService,
+        us: UserService,
+        mut redis: Redis,
+        tx: mpsc::Sender<std::result::Result<Event, Infallible>>,
+    ) {
+        let deadline = Instant::now() + STATUS_STREAM_TTL;
+
+        loop {
+            if tx.send(Ok(Self::sse_event_status(&order))).await.is_err()
+                || order.status != OrderStatus::Created
+                || Instant::now() >= deadline
+            {
+                return;
+            }
+
+            match tokio::time::timeout(NOTIFY_WAIT, receiver.recv()).await {
+                Ok(Ok(status)) => order.status = status,
+                Ok(Err(broadcast::error::RecvError::Lagged(_))) => {}
+                Ok(Err(broadcast::error::RecvError::Closed)) => return,
+                Err(_) => {
+                    if let Some(latest) =
+                        Self::query_provider(&ps, &us, &mut redis, order.clone()).await
+                    {
+                        order = latest;
+                    }
+                }
+            }
+        }
+    }
+
+    /// 回调未到时主动查单。查到已支付同样开通会员并广播，其他实例上等待的连接也能收到结果。
+    ///
+    /// 同一订单在 [`NOTIFY_WAIT`] 内只查一次，避免大量等待中的连接触发渠道限流
+    async fn query_provider(
+        ps: &PayOrderService,
+        us: &UserService,
+        redis: &mut Redis,
+        order: pay_order::Model,
+    ) -> Option<pay_order::Model> {
+        let order_id = order.id;
+        let key = format!("pay:order:{order_id}:query");
+        match lock::try_acquire(redis, &key, NOTIFY_WAIT.as_secs()).await {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(e) => {
+                tracing::warn!("订单#{order_id}查单限流失败: {e:#}");
+                return None;
+            }
+        }
+        let order = match ps.query_order(order).await {
+            Ok(order) => order,
+            Err(e) => {
+                tracing::warn!("推送状态时查询订单#{order_id}失败: {e:#}");
+                return None;
+            }
+        };
+        if let Err(e) = us.fulfil_paid_order(&order).await {
+            tracing::error!("订单#{order_id}开通会员失败: {e:?}");
+            return None;
+        }
+        if order.status != OrderStatus::Created {
+            ps.publish_status(&order).await;
+        }
+        Some(order)
+    }
+
+    fn sse_event_status(order: &pay_order::Model) -> Event {
+        let event = PayStatusEvent {
+            order_id: order.id,
+            status: order.status,
+        };
+        Event::default()
+            .event(SSE_EVENT_STATUS)
+            .data(serde_json::to_string(&event).expect("pay status event to json failed"))
+    }
+}
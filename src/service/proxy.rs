@@ -0,0 +1,87 @@
+use anyhow::Context;
+use itertools::Itertools;
+use sea_orm::{ColumnTrait, DbConn, EntityTrait, QueryFilter, QueryOrder};
+use summer::plugin::service::Service;
+use summer_web::error::{KnownWebError, Result};
+
+use crate::config::proxy::ProxyConfig;
+use crate::model::prelude::{Proxy, ProxyPool};
+use crate::model::proxy;
+use crate::utils::crypto;
+use crate::views::worker::{WorkerProxy, WorkerProxyPoolResp};
+
+/// 代理凭证加解密与 worker 解析代理池
+#[derive(Clone, Service)]
+pub struct ProxyService {
+    #[inject(component)]
+    db: DbConn,
+    #[inject(config)]
+    config: ProxyConfig,
+}
+
+impl ProxyService {
+    pub fn encrypt_password(&self, password: &str) -> Result<Vec<u8>> {
+        if !self.config.is_configured() {
+            Err(KnownWebError::internal_server_error(
+                "服务端未配置 [proxy] secret_key，无法保存代理密码",
+            ))?;
+        }
+        let key = self.config.key()?;
+        Ok(crypto::encrypt(&key, password.as_bytes())?)
+    }
+
+    pub fn decrypt_password(&self, cipher: &[u8]) -> anyhow::Result<String> {
+        let key = self.config.key()?;
+        let plain = crypto::decrypt(&key, cipher).context("解密代理密码失败")?;
+        String::from_utf8(plain).context("代理密码不是UTF-8")
+    }
+
+    /// 解析代理池中启用的代理并解密密码。
+    ///
+    /// 最近一次健康检查失败的代理排在后面，按延迟从低到高排列；轮换由 worker 按 `rotation` 执行。
+    pub async fn resolve_pool(&self, pool_id: i32) -> Result<WorkerProxyPoolResp> {
+        let pool = ProxyPool::find_by_id(pool_id)
+            .one(&self.db)
+            .await
+            .context("find proxy pool failed")?
+            .ok_or_else(|| KnownWebError::not_found("代理池不存在"))?;
+        let proxies = Proxy::find()
+            .filter(proxy::Column::PoolId.eq(pool.id))
+            .filter(proxy::Column::Enabled.eq(true))
+            .order_by_asc(proxy::Column::Id)
+            .all(&self.db)
+            .await
+            .context("query proxies failed")?;
+
+        let proxies = proxies
+            .into_iter()
+            .sorted_by_key(|p| {
+                (
+                    p.last_check_ok == Some(false),
+                    p.last_latency_ms.unwrap_or(i32::MAX),
+                )
+            })
+            .map(|p| {
+                let password = p
+                    .password_cipher
+                    .as_deref()
+                    .map(|cipher| self.decrypt_password(cipher))
+                    .transpose()?;
+                Ok(WorkerProxy {
+                    id: p.id,
+                    protocol: p.protocol,
+                    host: p.host,
+                    port: p.port,
+                    username: p.username,
+                    password,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(WorkerProxyPoolResp {
+            id: pool.id,
+            rotation: pool.rotation,
+            proxies,
+        })
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+
+use anyhow::Context;
+use chrono::{Datelike, Duration, Local, NaiveTime};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, DbConn, EntityTrait, FromQueryResult, Statement,
+    TransactionTrait,
+};
+use summer::plugin::service::Service;
+
+use crate::model::prelude::AccountUser;
+use crate::model::sea_orm_active_enums::ProductEdition;
+use crate::model::task_dispatch;
+use crate::service::user::UserService;
+use crate::views::user::{QuotaItem, QuotaResp};
+
+/// 已入队但超过该时长仍未被 worker 认领的派发不再计入同时运行数，避免丢失的队列任务长期占用配额
+const PENDING_DISPATCH_HOURS: i64 = 1;
+
+/// 派发时超出运行配额，由 [`QuotaService::reserve_dispatch`] 返回，调用方可通过 `downcast_ref` 区分
+#[derive(Debug)]
+pub struct QuotaExceeded(pub &'static str);
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+#[derive(Debug, FromQueryResult)]
+struct QuotaUsage {
+    running: i64,
+    daily_runs: i64,
+    monthly_records: i64,
+}
+
+impl QuotaUsage {
+    fn into_resp(self, edition: ProductEdition) -> QuotaResp {
+        QuotaResp {
+            running: QuotaItem::new(self.running.max(0) as u64, edition.concurrent_run_limit()),
+            daily_runs: QuotaItem::new(self.daily_runs.max(0) as u64, edition.daily_run_limit()),
+            monthly_records: QuotaItem::new(
+                self.monthly_records.max(0) as u64,
+                edition.monthly_record_limit(),
+            ),
+            edition,
+        }
+    }
+}
+
+/// 按版本限制任务的运行量：同时运行的实例数、每日运行次数、每月采集记录数。
+///
+/// 运行次数按 `task_dispatch` 统计，已入队未开始的也计入；同时运行数包含排队中的派发。
+#[derive(Clone, Service)]
+pub struct QuotaService {
+    #[inject(component)]
+    db: DbConn,
+    #[inject(component)]
+    us: UserService,
+}
+
+impl QuotaService {
+    pub async fn status(&self, user_id: i64) -> anyhow::Result<QuotaResp> {
+        let user = self.us.refresh_user_membership_by_id(user_id).await?;
+        let usage = Self::usage(&self.db, user_id).await?;
+        Ok(usage.into_resp(user.edition))
+    }
+
+    /// 在配额内写入派发记录，超出时返回 [`QuotaExceeded`]。
+    ///
+    /// 同一用户的定时、上游、重试、手动派发在事务内按用户加 advisory lock 串行执行，
+    /// 统计用量与写入派发记录之间不会插入其他派发，并发触发不会超出上限。
+    pub async fn reserve_dispatch(
+        &self,
+        user_id: i64,
+        dispatch: task_dispatch::ActiveModel,
+    ) -> anyhow::Result<task_dispatch::Model> {
+        let edition = AccountUser::find_by_id(user_id)
+            .one(&self.db)
+            .await
+            .context("find user failed")?
+            .map(|user| user.current_edition())
+            .ok_or_else(|| anyhow::anyhow!("用户不存在: user_id={user_id}"))?;
+
+        let txn = self.db.begin().await.context("begin transaction failed")?;
+        txn.execute_raw(Statement::from_sql_and_values(
+            txn.get_database_backend(),
+            "SELECT pg_advisory_xact_lock($1)",
+            [user_id.into()],
+        ))
+        .await
+        .with_context(|| format!("lock quota of user#{user_id} failed"))?;
+
+        let quota = Self::usage(&txn, user_id).await?.into_resp(edition);
+        if let Some(reason) = quota.exceeded() {
+            return Err(QuotaExceeded(reason).into());
+        }
+        let dispatch = dispatch
+            .insert(&txn)
+            .await
+            .context("save task dispatch failed")?;
+        txn.commit().await.context("commit transaction failed")?;
+        Ok(dispatch)
+    }
+
+    async fn usage<C: ConnectionTrait>(db: &C, user_id: i64) -> anyhow::Result<QuotaUsage> {
+        let now = Local::now().naive_local();
+        let today = now.date();
+        let day_start = today.and_time(NaiveTime::MIN);
+        let month_start = today
+            .with_day(1)
+            .expect("first day of month")
+            .and_time(NaiveTime::MIN);
+        let pending_after = now - Duration::hours(PENDING_DISPATCH_HOURS);
+
+        QuotaUsage::find_by_statement(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM task_instance i
+                 JOIN scraper_task t ON t.id = i.task_id
+                 WHERE t.user_id = $1 AND i.status = 'running')
+                + (SELECT COUNT(*) FROM task_dispatch d
+                   JOIN scraper_task t ON t.id = d.task_id
+                   WHERE t.user_id = $1 AND d.instance_id IS NULL AND d.cancelled = false
+                     AND d.created >= $2) AS running,
+                (SELECT COUNT(*) FROM task_dispatch d
+                 JOIN scraper_task t ON t.id = d.task_id
+                 WHERE t.user_id = $1 AND d.created >= $3 AND d.cancelled = false) AS daily_runs,
+                (SELECT COALESCE(SUM(i.data_count), 0)::bigint FROM task_instance i
+                 JOIN scraper_task t ON t.id = i.task_id
+                 WHERE t.user_id = $1 AND i.created >= $4) AS monthly_records
+            "#,
+            [
+                user_id.into(),
+                pending_after.into(),
+                day_start.into(),
+                month_start.into(),
+            ],
+        ))
+        .one(db)
+        .await
+        .context("统计运行配额失败")?
+        .ok_or_else(|| anyhow::anyhow!("quota usage query returned no row"))
+    }
+}
@@ -0,0 +1,363 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Context;
+use chrono::{Local, NaiveDateTime};
+use itertools::Itertools;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DbConn, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, Set, Statement, TransactionTrait,
+};
+use summer::plugin::service::Service;
+use summer_sqlx::sqlx::{self, Row};
+use summer_sqlx::ConnectPool;
+use summer_web::error::{KnownWebError, Result};
+
+use crate::model::prelude::{ProxyPool, ScraperTask};
+use crate::model::proxy_pool::DEFAULT_PROXY_POOL;
+use crate::model::sea_orm_active_enums::{ProductEdition, ScheduleState};
+use crate::model::{scraper_task, task_rule_revision};
+use crate::service::data_clean::{ensure_pipeline_table, validate_pipeline};
+use crate::views::data_clean::CleanPipeline;
+use crate::views::task_bundle::{
+    BundlePipeline, BundleTask, ImportConflict, ImportConflictStrategy, ImportedTask,
+    ProxyPoolConflict, TaskBundle, TaskImportReq, TaskImportResp,
+};
+
+/// 当前导出包格式版本，格式不兼容变更时递增
+pub const TASK_BUNDLE_SCHEMA_VERSION: u32 = 1;
+/// 单个导出包最多包含的任务数
+const MAX_BUNDLE_TASKS: usize = 50;
+const MAX_TASK_NAME_LEN: usize = 80;
+
+/// 任务导出/导入。
+///
+/// 与批量新增不同，导入保留规则、调度、数据质量配置（含去重规则版本）和清洗流程，
+/// 任务ID与清洗流程的 `store_id` 在导入时重新映射，当前账号不可用的代理池回退到系统默认代理。
+#[derive(Clone, Service)]
+pub struct TaskBundleService {
+    #[inject(component)]
+    db: DbConn,
+    #[inject(component)]
+    pool: ConnectPool,
+}
+
+impl TaskBundleService {
+    pub async fn export(&self, user_id: i64, ids: &[i64]) -> Result<TaskBundle> {
+        let ids = ids.iter().copied().unique().collect_vec();
+        let tasks = ScraperTask::find()
+            .filter(scraper_task::Column::UserId.eq(user_id))
+            .filter(scraper_task::Column::Deleted.eq(false))
+            .filter(scraper_task::Column::Id.is_in(ids.clone()))
+            .order_by_asc(scraper_task::Column::Id)
+            .all(&self.db)
+            .await
+            .context("query export tasks failed")?;
+        if tasks.len() != ids.len() {
+            Err(KnownWebError::not_found("部分任务不存在或无权访问"))?;
+        }
+
+        ensure_pipeline_table(&self.pool).await?;
+        let store_ids = ids.iter().map(|id| id.to_string()).collect_vec();
+        let rows = sqlx::query(
+            "SELECT store_id, name, definition FROM data_clean_pipeline \
+             WHERE user_id = $1 AND store_id = ANY($2) \
+             ORDER BY created_at",
+        )
+        .bind(user_id)
+        .bind(&store_ids)
+        .fetch_all(&self.pool)
+        .await
+        .context("查询数据清洗流程失败")?;
+        let mut pipelines: HashMap<String, Vec<BundlePipeline>> = HashMap::new();
+        for row in rows {
+            let store_id: String = row.try_get("store_id").context("解析数据清洗流程失败")?;
+            pipelines.entry(store_id).or_default().push(BundlePipeline {
+                name: row.try_get("name").context("解析数据清洗流程失败")?,
+                definition: row.try_get("definition").context("解析数据清洗流程失败")?,
+            });
+        }
+
+        let tasks = tasks
+            .into_iter()
+            .map(|task| BundleTask {
+                pipelines: pipelines.remove(&task.id.to_string()).unwrap_or_default(),
+                id: task.id,
+                name: task.name,
+                rule: task.rule,
+                data: task.data,
+                schedule_state: task.schedule_state,
+                rule_revision: task.rule_revision,
+                created: task.created,
+            })
+            .collect();
+
+        Ok(TaskBundle {
+            schema_version: TASK_BUNDLE_SCHEMA_VERSION,
+            exported_at: Local::now().naive_local(),
+            app_version: Some(env!("CARGO_PKG_VERSION").to_string()),
+            tasks,
+        })
+    }
+
+    pub async fn import(
+        &self,
+        user_id: i64,
+        edition: ProductEdition,
+        req: TaskImportReq,
+    ) -> Result<TaskImportResp> {
+        let TaskImportReq {
+            bundle,
+            on_conflict,
+            dry_run,
+        } = req;
+        validate_bundle(&bundle, edition)?;
+
+        let names = bundle.tasks.iter().map(|t| t.name.clone()).collect_vec();
+        let existing: HashMap<String, i64> = ScraperTask::find()
+            .filter(scraper_task::Column::UserId.eq(user_id))
+            .filter(scraper_task::Column::Deleted.eq(false))
+            .filter(scraper_task::Column::Name.is_in(names))
+            .all(&self.db)
+            .await
+            .context("query conflict tasks failed")?
+            .into_iter()
+            .map(|t| (t.name, t.id))
+            .collect();
+
+        // 重名冲突
+        let mut conflicts = vec![];
+        let mut taken: HashSet<String> = existing.keys().cloned().collect();
+        let mut plan = vec![];
+        for task in bundle.tasks {
+            let Some(&existing_task_id) = existing.get(&task.name) else {
+                taken.insert(task.name.clone());
+                plan.push((task.name.clone(), task));
+                continue;
+            };
+            conflicts.push(ImportConflict {
+                source_id: task.id,
+                name: task.name.clone(),
+                existing_task_id,
+                resolution: on_conflict,
+            });
+            if on_conflict == ImportConflictStrategy::Rename {
+                let name = unique_name(&task.name, &taken);
+                taken.insert(name.clone());
+                plan.push((name, task));
+            }
+        }
+        if on_conflict == ImportConflictStrategy::Abort && !conflicts.is_empty() {
+            return Ok(TaskImportResp {
+                dry_run,
+                imported: vec![],
+                conflicts,
+                proxy_conflicts: vec![],
+            });
+        }
+
+        let current_count = ScraperTask::find()
+            .filter(scraper_task::Column::UserId.eq(user_id))
+            .filter(scraper_task::Column::Deleted.eq(false))
+            .count(&self.db)
+            .await
+            .context("count user tasks failed")?;
+        if current_count + plan.len() as u64 > edition.task_limit() {
+            Err(KnownWebError::forbidden("导入将超过当前版本的任务数量上限"))?;
+        }
+
+        // 引用的代理池在当前账号不可用（跨账号/环境迁移）时回退到系统默认代理，并列为冲突
+        let mut proxy_conflicts = vec![];
+        for (name, task) in plan.iter_mut() {
+            let Some(schedule) = task.data.as_mut().and_then(|d| d.schedule.as_mut()) else {
+                continue;
+            };
+            if ProxyPool::check_usable(&self.db, schedule.proxy_id, user_id)
+                .await
+                .is_err()
+            {
+                proxy_conflicts.push(ProxyPoolConflict {
+                    source_id: task.id,
+                    name: name.clone(),
+                    proxy_id: schedule.proxy_id,
+                });
+                schedule.proxy_id = DEFAULT_PROXY_POOL;
+            }
+        }
+
+        if dry_run {
+            let imported = plan
+                .into_iter()
+                .map(|(name, task)| ImportedTask {
+                    source_id: task.id,
+                    task_id: None,
+                    schedule_state: import_schedule_state(&task),
+                    pipelines: task.pipelines.len(),
+                    name,
+                })
+                .collect();
+            return Ok(TaskImportResp {
+                dry_run,
+                imported,
+                conflicts,
+                proxy_conflicts,
+            });
+        }
+
+        ensure_pipeline_table(&self.pool).await?;
+        let txn = self.db.begin().await.context("begin transaction failed")?;
+        let mut imported = Vec::with_capacity(plan.len());
+        for (name, task) in plan {
+            let schedule_state = import_schedule_state(&task);
+            let saved = scraper_task::ActiveModel {
+                user_id: Set(user_id),
+                name: Set(name),
+                rule: Set(task.rule),
+                data: Set(task.data),
+                schedule_state: Set(schedule_state),
+                rule_revision: Set(task.rule_revision),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await
+            .context("save imported task failed")?;
+            // 修订历史不随导出包迁移，补记导入时的规则为当前修订，后续修改、回滚从该修订继续
+            if saved.rule_revision > 0 {
+                task_rule_revision::ActiveModel {
+                    task_id: Set(saved.id),
+                    revision: Set(saved.rule_revision),
+                    rule: Set(saved.rule.clone()),
+                    author_id: Set(user_id),
+                    comment: Set(Some("导入".to_string())),
+                    ..Default::default()
+                }
+                .insert(&txn)
+                .await
+                .context("save imported rule revision failed")?;
+            }
+
+            let now = Local::now().naive_local();
+            for pipeline in &task.pipelines {
+                insert_pipeline(&txn, user_id, saved.id, pipeline, now).await?;
+            }
+
+            imported.push(ImportedTask {
+                source_id: task.id,
+                task_id: Some(saved.id),
+                name: saved.name,
+                schedule_state,
+                pipelines: task.pipelines.len(),
+            });
+        }
+        txn.commit().await.context("commit transaction failed")?;
+
+        Ok(TaskImportResp {
+            dry_run,
+            imported,
+            conflicts,
+            proxy_conflicts,
+        })
+    }
+}
+
+/// 导入的任务不会立即开始调度：配置了调度的任务处于暂停状态，由用户确认后恢复
+fn import_schedule_state(task: &BundleTask) -> ScheduleState {
+    match task.data.as_ref().and_then(|d| d.schedule.as_ref()) {
+        Some(_) => ScheduleState::Paused,
+        None => ScheduleState::Active,
+    }
+}
+
+fn validate_bundle(bundle: &TaskBundle, edition: ProductEdition) -> Result<()> {
+    if bundle.schema_version == 0 || bundle.schema_version > TASK_BUNDLE_SCHEMA_VERSION {
+        Err(KnownWebError::bad_request(format!(
+            "不支持的导出包版本: {}",
+            bundle.schema_version
+        )))?;
+    }
+    if bundle.tasks.is_empty() || bundle.tasks.len() > MAX_BUNDLE_TASKS {
+        Err(KnownWebError::bad_request(format!(
+            "导出包须包含1-{MAX_BUNDLE_TASKS}个任务"
+        )))?;
+    }
+
+    let mut source_ids = HashSet::new();
+    for task in &bundle.tasks {
+        if !source_ids.insert(task.id) {
+            Err(KnownWebError::bad_request(format!(
+                "导出包中任务ID重复: {}",
+                task.id
+            )))?;
+        }
+        let name_len = task.name.trim().chars().count();
+        if name_len == 0 || name_len > MAX_TASK_NAME_LEN {
+            Err(KnownWebError::bad_request(format!(
+                "任务#{}名称长度须在1-{MAX_TASK_NAME_LEN}字符之间",
+                task.id
+            )))?;
+        }
+        if let Some(data) = &task.data {
+            data.validate()?;
+        }
+        if let Some(schedule) = task.data.as_ref().and_then(|d| d.schedule.as_ref()) {
+            schedule.parse_cron()?;
+            schedule.tz()?;
+            schedule.check_interval(edition)?;
+        }
+        for pipeline in &task.pipelines {
+            let name_len = pipeline.name.chars().count();
+            if name_len == 0 || name_len > MAX_TASK_NAME_LEN {
+                Err(KnownWebError::bad_request(format!(
+                    "任务#{}的清洗流程名称长度须在1-{MAX_TASK_NAME_LEN}字符之间",
+                    task.id
+                )))?;
+            }
+            let parsed: CleanPipeline = serde_json::from_value(pipeline.definition.clone())
+                .map_err(|e| {
+                    KnownWebError::bad_request(format!(
+                        "任务#{}的清洗流程「{}」格式不正确: {e}",
+                        task.id, pipeline.name
+                    ))
+                })?;
+            if !validate_pipeline(&parsed).valid {
+                Err(KnownWebError::bad_request(format!(
+                    "任务#{}的清洗流程「{}」校验失败",
+                    task.id, pipeline.name
+                )))?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 在名称后追加序号直到不与已有任务重名
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    (2..)
+        .map(|n| format!("{name} ({n})"))
+        .find(|candidate| !taken.contains(candidate))
+        .expect("unbounded candidates")
+}
+
+async fn insert_pipeline<C: ConnectionTrait>(
+    db: &C,
+    user_id: i64,
+    task_id: i64,
+    pipeline: &BundlePipeline,
+    now: NaiveDateTime,
+) -> Result<()> {
+    db.execute_raw(Statement::from_sql_and_values(
+        db.get_database_backend(),
+        "INSERT INTO data_clean_pipeline (user_id, store_id, name, definition, created_at, modified_at) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
+        vec![
+            user_id.into(),
+            task_id.to_string().into(),
+            pipeline.name.clone().into(),
+            pipeline.definition.clone().into(),
+            now.into(),
+            now.into(),
+        ],
+    ))
+    .await
+    .context("保存数据清洗流程失败")?;
+    Ok(())
+}
@@ -10,7 +10,7 @@ use flate2::read::GzDecoder;
 use sea_orm::{DbConn, EntityTrait};
 use serde_json::Value;
 use summer::{plugin::service::Service, tracing};
-use summer_redis::{config::RedisConfig, redis};
+use summer_redis::{config::RedisConfig, redis, Redis};
 use summer_web::axum::response::sse::{Event, Sse};
 use summer_web::error::{KnownWebError, Result};
 use tokio::sync::{broadcast, mpsc, Mutex};
@@ -31,6 +31,7 @@ const LIVE_STREAM_BATCH_SIZE: usize = 100;
 const SSE_EVENT_LOGS_COMPLETE: &str = "logs_complete";
 const SSE_DATA_LOGS_COMPLETE: &str = "{}";
 const REDIS_STREAM_LOG_PAYLOAD_FIELD: &str = "payload";
+const REDIS_STREAM_LOG_EOF_FIELD: &str = "__autowds_logs_eof";
 
 static LIVE_LOG_STREAMS: LazyLock<Mutex<HashMap<String, broadcast::Sender<LiveLogEvent>>>> =
     LazyLock::new(|| Mutex::new(HashMap::new()));
@@ -311,6 +312,25 @@ impl TaskLogService {
         Sse::new(ReceiverStream::new(rx))
     }
 
+    /// 代替已失联的 worker 写入日志流结束控制帧，正在观看实时日志的 SSE 收到 `logs_complete` 后结束。
+    pub async fn publish_logs_eof(
+        redis: &mut Redis,
+        task_id: i64,
+        instance_id: i64,
+    ) -> anyhow::Result<()> {
+        let stream_key = Self::redis_stream_key(task_id, instance_id);
+        let payload = serde_json::json!({ REDIS_STREAM_LOG_EOF_FIELD: true }).to_string();
+        redis::cmd("XADD")
+            .arg(&stream_key)
+            .arg("*")
+            .arg(REDIS_STREAM_LOG_PAYLOAD_FIELD)
+            .arg(payload)
+            .query_async::<String>(redis)
+            .await
+            .with_context(|| format!("xadd logs eof to {stream_key} failed"))?;
+        Ok(())
+    }
+
     /// 与实例侧 `task_logger` / `LogManager::publish_logs_stream_eof` 使用同一 Stream key 模板。
     fn redis_stream_key(task_id: i64, instance_id: i64) -> String {
         format!("task:{task_id}:{instance_id}:logs")
@@ -375,7 +395,7 @@ impl TaskLogService {
     fn is_stream_eof_payload(payload: &str) -> bool {
         serde_json::from_str::<Value>(payload)
             .ok()
-            .and_then(|v| v.get("__autowds_logs_eof").and_then(|x| x.as_bool()))
+            .and_then(|v| v.get(REDIS_STREAM_LOG_EOF_FIELD).and_then(|x| x.as_bool()))
             == Some(true)
     }
 
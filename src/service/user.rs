@@ -1,12 +1,23 @@
-use crate::model::{
-    account_user,
-    prelude::AccountUser,
-    sea_orm_active_enums::{OrderLevel, ProductEdition},
+use crate::{
+    model::{
+        account_user, membership_trial, pay_order,
+        prelude::{AccountUser, MembershipHistory, MembershipTrial},
+        sea_orm_active_enums::{
+            MembershipChangeReason, OrderKind, OrderLevel, OrderStatus, ProductEdition,
+        },
+    },
+    router::admin::marketing as marketing_router,
+    views::pay::UpgradeQuote,
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use chrono::{Duration, Local};
-use sea_orm::DbConn;
-use sea_orm::{ActiveModelTrait, ActiveValue::Set, EntityTrait};
+use sea_orm::{
+    prelude::{DateTime, IpNetwork},
+    ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, DatabaseTransaction, DbConn, EntityTrait, ExprTrait, QueryFilter, QuerySelect,
+    TransactionTrait,
+};
 use summer::{plugin::service::Service, tracing};
 
 #[derive(Clone, Service)]
@@ -31,34 +42,59 @@ impl UserService {
         &self,
         user: account_user::Model,
     ) -> Result<account_user::Model> {
-        if user.edition == ProductEdition::L0 {
+        if !Self::membership_lapsed(&user) {
             return Ok(user);
         }
-        let Some(expired_at) = user.vip_expired_at else {
-            // 历史数据：不做过期降级，避免无意中清退存量会员
-            return Ok(user);
+
+        // 加锁后重新判断，避免与支付履约、到期巡检并发时把刚续费的用户降级
+        let user_id = user.id;
+        let txn = self.db.begin().await?;
+        let user = AccountUser::find_by_id(user_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?;
+        let Some(user) = user else {
+            anyhow::bail!("用户不存在: user_id={user_id}");
         };
-        let now = Local::now().naive_local();
-        if expired_at > now {
+        if !Self::membership_lapsed(&user) {
             return Ok(user);
         }
 
+        // 保留 vip_expired_at，用于统计会员流失
         let updated = account_user::ActiveModel {
             id: Set(user.id),
             edition: Set(ProductEdition::L0),
-            vip_expired_at: Set(None),
             ..Default::default()
         }
-        .update(&self.db)
+        .update(&txn)
+        .await?;
+        MembershipHistory::record(
+            &txn,
+            &user,
+            &updated,
+            MembershipChangeReason::Expired,
+            None,
+            None,
+        )
         .await?;
+        txn.commit().await?;
 
         Ok(updated)
     }
 
+    /// 付费会员已过期；`vip_expired_at` 为空的历史数据不做过期降级，避免无意中清退存量会员
+    fn membership_lapsed(user: &account_user::Model) -> bool {
+        user.edition != ProductEdition::L0
+            && user
+                .vip_expired_at
+                .is_some_and(|expired_at| expired_at <= Local::now().naive_local())
+    }
+
     /// 确认用户支付，更新用户会员状态
-    pub async fn confirm_user(
-        &self,
+    async fn confirm_user(
+        txn: &DatabaseTransaction,
         user_id: i64,
+        order_id: i64,
         level: OrderLevel,
         edition: ProductEdition,
     ) -> Result<String> {
@@ -69,25 +105,34 @@ impl UserService {
             edition
         );
 
-        let user = AccountUser::find_by_id(user_id).one(&self.db).await?;
+        let user = AccountUser::find_by_id(user_id)
+            .lock_exclusive()
+            .one(txn)
+            .await?;
         let Some(user) = user else {
             anyhow::bail!("用户不存在: user_id={user_id}");
         };
 
         let now = Local::now().naive_local();
         let base = user.vip_expired_at.filter(|t| *t > now).unwrap_or(now);
-        let new_expired_at = match level {
-            OrderLevel::Monthly => base + Duration::days(30),
-            OrderLevel::Annual => base + Duration::days(365),
-        };
+        let new_expired_at = base + Duration::days(level.days());
 
-        account_user::ActiveModel {
+        let updated = account_user::ActiveModel {
             id: Set(user_id),
             edition: Set(edition),
             vip_expired_at: Set(Some(new_expired_at)),
             ..Default::default()
         }
-        .update(&self.db)
+        .update(txn)
+        .await?;
+        MembershipHistory::record(
+            txn,
+            &user,
+            &updated,
+            MembershipChangeReason::Purchase,
+            Some(order_id),
+            None,
+        )
         .await?;
 
         Ok(format!(
@@ -95,4 +140,174 @@ impl UserService {
             level, new_expired_at
         ))
     }
+
+    /// 确认升级订单：切换到新版本，会员时长按下单时的报价从当前时间重新计算。
+    ///
+    /// 旧版本的剩余时长已在报价中折算为抵扣金额/赠送时长，这里不再叠加。
+    async fn confirm_upgrade(
+        txn: &DatabaseTransaction,
+        order: &pay_order::Model,
+    ) -> Result<String> {
+        let quote = order
+            .meta
+            .clone()
+            .map(serde_json::from_value::<UpgradeQuote>)
+            .transpose()
+            .with_context(|| format!("订单#{}升级报价解析失败", order.id))?
+            .ok_or_else(|| anyhow::anyhow!("订单#{}缺少升级报价", order.id))?;
+
+        let user = AccountUser::find_by_id(order.user_id)
+            .lock_exclusive()
+            .one(txn)
+            .await?;
+        let Some(user) = user else {
+            anyhow::bail!("用户不存在: user_id={}", order.user_id);
+        };
+
+        let now = Local::now().naive_local();
+        let new_expired_at =
+            now + Duration::days(order.level.days()) + Duration::seconds(quote.bonus_seconds);
+
+        let updated = account_user::ActiveModel {
+            id: Set(order.user_id),
+            edition: Set(order.edition.clone()),
+            vip_expired_at: Set(Some(new_expired_at)),
+            ..Default::default()
+        }
+        .update(txn)
+        .await?;
+        MembershipHistory::record(
+            txn,
+            &user,
+            &updated,
+            MembershipChangeReason::Upgrade,
+            Some(order.id),
+            None,
+        )
+        .await?;
+
+        Ok(format!(
+            "用户 {} 已从 {} 升级到 {}，到期时间：{new_expired_at}",
+            order.user_id, quote.from_edition, order.edition
+        ))
+    }
+
+    /// 开通免费试用。
+    ///
+    /// 试用记录与会员变更在同一事务中写入，账号、邮箱、设备指纹上的唯一约束保证只能试用一次。
+    pub async fn activate_trial(
+        &self,
+        user: &account_user::Model,
+        edition: ProductEdition,
+        days: i64,
+        fingerprint: String,
+        ip: Option<IpNetwork>,
+    ) -> Result<membership_trial::Model> {
+        let expired_at = Local::now().naive_local() + Duration::days(days);
+
+        let txn = self.db.begin().await?;
+        let trial = membership_trial::ActiveModel {
+            user_id: Set(user.id),
+            email: Set(user.email.to_lowercase()),
+            fingerprint: Set(fingerprint),
+            ip: Set(ip),
+            edition: Set(edition.clone()),
+            expired_at: Set(expired_at),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .with_context(|| format!("用户 {} 试用记录写入失败", user.id))?;
+        let updated = account_user::ActiveModel {
+            id: Set(user.id),
+            edition: Set(edition),
+            vip_expired_at: Set(Some(expired_at)),
+            ..Default::default()
+        }
+        .update(&txn)
+        .await?;
+        MembershipHistory::record(
+            &txn,
+            user,
+            &updated,
+            MembershipChangeReason::Trial,
+            None,
+            Some(format!("试用{days}天")),
+        )
+        .await?;
+        txn.commit().await?;
+
+        tracing::info!(
+            "用户 {} 开通 {} 试用，到期时间：{expired_at}",
+            user.id,
+            trial.edition
+        );
+        Ok(trial)
+    }
+
+    /// 到期时间在 `(from, to]` 之间、仍未降级的会员
+    pub async fn find_expiring_members(
+        &self,
+        from: DateTime,
+        to: DateTime,
+    ) -> Result<Vec<account_user::Model>> {
+        Ok(AccountUser::find()
+            .filter(
+                account_user::Column::Edition
+                    .ne(ProductEdition::L0)
+                    .and(account_user::Column::VipExpiredAt.gt(from))
+                    .and(account_user::Column::VipExpiredAt.lte(to)),
+            )
+            .all(&self.db)
+            .await?)
+    }
+
+    /// 已到期但还没有降级的会员
+    pub async fn find_expired_members(&self) -> Result<Vec<account_user::Model>> {
+        let now = Local::now().naive_local();
+        Ok(AccountUser::find()
+            .filter(
+                account_user::Column::Edition
+                    .ne(ProductEdition::L0)
+                    .and(account_user::Column::VipExpiredAt.lte(now)),
+            )
+            .all(&self.db)
+            .await?)
+    }
+
+    /// 订单支付成功后开通会员并记录营销转化，未支付或已履约的订单直接忽略。
+    ///
+    /// 履约标记与会员变更在同一事务中提交：支付回调、轮询查单、超时关单并发处理同一笔订单时，
+    /// 只有抢到标记的一方开通会员，失败时整体回滚以便后续重试。
+    pub async fn fulfil_paid_order(&self, order: &pay_order::Model) -> Result<()> {
+        if order.status != OrderStatus::Paid {
+            return Ok(());
+        }
+        let txn = self.db.begin().await?;
+        if !pay_order::Entity::mark_fulfilled(&txn, order.id).await? {
+            tracing::info!("订单#{}已履约，跳过", order.id);
+            return Ok(());
+        }
+        let pay_order::Model {
+            user_id,
+            level,
+            edition,
+            ..
+        } = order.clone();
+        let msg = match order.kind {
+            OrderKind::Purchase => {
+                Self::confirm_user(&txn, user_id, order.id, level, edition).await?
+            }
+            OrderKind::Upgrade => Self::confirm_upgrade(&txn, order).await?,
+        };
+        txn.commit().await?;
+        tracing::info!("confirm_user({user_id},{level:?}) success>>>{msg}");
+        if let Err(e) = marketing_router::record_purchase_by_user(&self.db, user_id).await {
+            tracing::warn!("record marketing purchase failed: {e:#}");
+        }
+        if let Err(e) = MembershipTrial::mark_converted(&self.db, user_id, order.id).await {
+            tracing::warn!("record trial conversion failed: {e:#}");
+        }
+        Ok(())
+    }
 }
@@ -1,9 +1,17 @@
 use crate::config::apalis::ApalisConfig;
-use crate::model::prelude::ScraperTask;
-use crate::model::scraper_task;
+use crate::model::prelude::{ScraperTask, TaskDispatch};
+use crate::model::scraper_task::{self, ScheduleData, ScheduleType};
+use crate::model::sea_orm_active_enums::{ScheduleState, TriggerSource};
+use crate::model::task_dispatch;
+use crate::service::quota::{QuotaExceeded, QuotaService};
+use crate::utils::lock;
 use anyhow::Context as _;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, QueryFilter, Set};
-use std::sync::Arc;
+use chrono::{Duration, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, DbConn, EntityTrait, QueryFilter, Set,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, LazyLock};
 use summer::config::ConfigRegistry as _;
 use summer::{
     app::{App, AppBuilder},
@@ -21,16 +29,67 @@ use summer_apalis::{
 use summer_apalis::{apalis::prelude::*, apalis_board::axum::ui::ServeUI};
 use summer_job::extractor::Data;
 use summer_job::job::Job;
-use summer_job::JobScheduler;
-use summer_redis::Redis;
+use summer_job::{JobId, JobScheduler};
+use summer_redis::{redis, Redis};
 use summer_web::{
     axum::{Extension, Router},
     WebConfigurator as _,
 };
+use tokio::sync::Mutex;
 
+mod chain;
+mod membership;
 mod pay_check;
+mod retry;
+mod watchdog;
+
+/// 按调度类型把任务推送到不同队列，`Browser` 与 `Fast` worker 可以分别扩缩容。
+/// 未单独配置 `Browser` 队列时两者共用一个队列。
+#[derive(Clone)]
+pub struct TaskPublisher {
+    fast: RedisStorage<i64>,
+    browser: RedisStorage<i64>,
+    fast_queue: String,
+    browser_queue: String,
+}
 
-pub type TaskPublisher = RedisStorage<i64>;
+impl TaskPublisher {
+    fn storage(&mut self, ty: &ScheduleType) -> &mut RedisStorage<i64> {
+        match ty {
+            ScheduleType::Fast => &mut self.fast,
+            ScheduleType::Browser => &mut self.browser,
+        }
+    }
+
+    /// 调度类型对应的队列名，入队时记录到 `task_dispatch.queue`
+    pub fn queue(&self, ty: &ScheduleType) -> &str {
+        match ty {
+            ScheduleType::Fast => &self.fast_queue,
+            ScheduleType::Browser => &self.browser_queue,
+        }
+    }
+
+    /// 从 `queue` 队列中删除尚未被 worker 取走的任务；已被取走的由实例认领触发器拒绝
+    pub async fn discard(
+        &self,
+        redis: &mut Redis,
+        queue: &str,
+        job_id: &str,
+    ) -> anyhow::Result<()> {
+        let config = ApalisRedisConfig::new(queue);
+        redis::pipe()
+            .atomic()
+            .lrem(config.active_jobs_list(), 0, job_id)
+            .ignore()
+            .zrem(config.scheduled_jobs_set(), job_id)
+            .ignore()
+            .hdel(config.job_data_hash(), job_id)
+            .ignore()
+            .query_async::<()>(redis)
+            .await
+            .with_context(|| format!("remove job {job_id} from queue {queue} failed"))
+    }
+}
 
 pub fn add_storage(app: &mut AppBuilder, monitor: Monitor) -> Monitor {
     let broadcaster = TracingBroadcaster::create();
@@ -40,12 +99,28 @@ pub fn add_storage(app: &mut AppBuilder, monitor: Monitor) -> Monitor {
     let apalis_cfg = app
         .get_config::<ApalisConfig>()
         .expect("读取 [apalis] 配置失败（config/app.toml 中需有 [apalis] 段）");
-    let storage = TaskPublisher::new_with_config(
+    let fast_queue = apalis_cfg.queue.clone();
+    let fast = RedisStorage::<i64>::new_with_config(
         redis.clone(),
-        ApalisRedisConfig::new(apalis_cfg.queue.as_str()),
+        ApalisRedisConfig::new(fast_queue.as_str()),
     );
-    app.add_component(storage.clone());
-    let apalis_api = ApiBuilder::new(Router::new()).register(storage).build();
+    let mut api = ApiBuilder::new(Router::new()).register(fast.clone());
+    let (browser, browser_queue) = match apalis_cfg.browser_queue() {
+        Some(queue) => {
+            let browser =
+                RedisStorage::<i64>::new_with_config(redis.clone(), ApalisRedisConfig::new(queue));
+            api = api.register(browser.clone());
+            (browser, queue.to_string())
+        }
+        None => (fast.clone(), fast_queue.clone()),
+    };
+    app.add_component(TaskPublisher {
+        fast,
+        browser,
+        fast_queue,
+        browser_queue,
+    });
+    let apalis_api = api.build();
     let router = Router::new()
         .nest("/apalis", apalis_api)
         .nest_service("/apalis/ui", ServeUI::new())
@@ -54,84 +129,359 @@ pub fn add_storage(app: &mut AppBuilder, monitor: Monitor) -> Monitor {
     monitor
 }
 
+/// 由已有实例引发的派发
+#[derive(Debug, Clone, Copy)]
+pub enum DispatchOrigin {
+    /// 上游任务实例结束后触发
+    Upstream {
+        instance_id: i64,
+        /// worker 是否以上游实例的采集记录作为输入
+        pass_records: bool,
+    },
+    /// 重试失败的实例
+    Retry {
+        parent_instance_id: i64,
+        attempt: i32,
+    },
+}
+
+/// 按任务的调度类型推送到对应队列，返回 apalis 任务ID。
+///
+/// 队列负载只有 `task_id`（与 autowds-instance 约定），触发来源写入 `task_dispatch`，
+/// 实例创建时由数据库触发器按先进先出认领并写入 `task_instance.trigger_source`。
+pub async fn publish_task(
+    db: &DbConn,
+    publisher: &mut TaskPublisher,
+    quota: &QuotaService,
+    task: &scraper_task::Model,
+    trigger_source: TriggerSource,
+    user_id: Option<i64>,
+    origin: Option<DispatchOrigin>,
+) -> anyhow::Result<String> {
+    let task_id = task.id;
+    let schedule_type = task.schedule_type();
+    let job_id = TaskId::new(RandomId::default());
+    let mut dispatch = task_dispatch::ActiveModel {
+        task_id: Set(task_id),
+        job_id: Set(job_id.to_string()),
+        queue: Set(Some(publisher.queue(&schedule_type).to_string())),
+        trigger_source: Set(trigger_source),
+        user_id: Set(user_id),
+        ..Default::default()
+    };
+    match origin {
+        Some(DispatchOrigin::Upstream {
+            instance_id,
+            pass_records,
+        }) => {
+            dispatch.upstream_instance_id = Set(Some(instance_id));
+            dispatch.pass_records = Set(pass_records);
+        }
+        Some(DispatchOrigin::Retry {
+            parent_instance_id,
+            attempt,
+        }) => {
+            dispatch.parent_instance_id = Set(Some(parent_instance_id));
+            dispatch.attempt = Set(attempt);
+        }
+        None => {}
+    }
+    // 先记录再入队，避免 worker 先于记录写入实例而认领不到；超出任务所有者的运行配额时不入队
+    let dispatch = quota.reserve_dispatch(task.user_id, dispatch).await?;
+
+    let job = TaskBuilder::new(task_id)
+        .with_task_id(job_id.clone())
+        .build();
+    if let Err(e) = publisher.storage(&schedule_type).push_task(job).await {
+        if let Err(e) = TaskDispatch::delete_by_id(dispatch.id).exec(db).await {
+            tracing::warn!("删除未入队的派发记录#{}失败: {e:?}", dispatch.id);
+        }
+        return Err(anyhow::anyhow!(
+            "publish task#{task_id} to redis failed: {e:?}"
+        ));
+    }
+    Ok(job_id.to_string())
+}
+
+/// 定时触发的锁时长，需覆盖各副本间的时钟偏差
+const FIRE_LOCK_TTL_SECONDS: u64 = 600;
+/// 推算本次触发对应的调度时间时允许的时钟偏差
+const FIRE_TOLERANCE_SECONDS: i64 = 30;
+
 pub async fn dispatch_task(
+    Component(db): Component<DbConn>,
+    Component(mut redis): Component<Redis>,
     Component(mut publisher): Component<TaskPublisher>,
+    Component(quota): Component<QuotaService>,
     Data(task_id): Data<i64>,
 ) {
-    match publisher.push(task_id).await {
-        Ok(r) => {
-            tracing::info!("dispatch task success: {r:?}")
+    dispatch_cron_task(&db, &mut redis, &mut publisher, &quota, task_id).await
+}
+
+/// 每个副本都注册了全部调度，同一次触发以「任务ID + 调度时间」抢锁，只有一个副本入队。
+/// 某个副本宕机后其余副本照常触发，无需选主。
+///
+/// 调度变更在其他副本上要到下次对齐后才生效，期间以数据库中的任务为准：
+/// 已删除、已暂停或与当前 cron 不符的触发直接忽略。
+async fn dispatch_cron_task(
+    db: &DbConn,
+    redis: &mut Redis,
+    publisher: &mut TaskPublisher,
+    quota: &QuotaService,
+    task_id: i64,
+) {
+    let task = match ScraperTask::find_by_id(task_id).one(db).await {
+        Ok(Some(task)) => task,
+        Ok(None) => {
+            tracing::warn!("dispatch task skipped: task#{task_id} not found");
+            return;
+        }
+        Err(e) => {
+            tracing::error!("find task#{task_id} failed: {e:?}");
+            return;
+        }
+    };
+    if task.deleted || task.schedule_state != ScheduleState::Active {
+        tracing::debug!("task#{task_id} is deleted or paused, skip stale schedule");
+        return;
+    }
+    let Some(fire_at) = scheduled_fire_time(&task) else {
+        tracing::debug!("task#{task_id} fired off its current cron, skip stale schedule");
+        return;
+    };
+    let key = format!("task:fire:{task_id}:{fire_at}");
+    match lock::try_acquire(redis, &key, FIRE_LOCK_TTL_SECONDS).await {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::debug!("task#{task_id} fired at {fire_at} is dispatched by another replica");
+            return;
+        }
+        Err(e) => {
+            tracing::error!("acquire fire lock of task#{task_id} failed: {e:?}");
+            return;
+        }
+    }
+    match publish_task(db, publisher, quota, &task, TriggerSource::Cron, None, None).await {
+        Ok(job_id) => {
+            tracing::info!("dispatch task success: task_id={task_id}, job_id={job_id}")
+        }
+        Err(e) if e.is::<QuotaExceeded>() => {
+            tracing::warn!("skip task#{task_id} of user#{}: {e}", task.user_id)
         }
         Err(e) => {
-            tracing::error!("publish to redis failed: {e:?}")
+            tracing::error!("dispatch task failed: {e:?}")
         }
     }
 }
 
-/// 启动时从数据库恢复所有活跃的 cron 调度
-/// 因为 SimpleJobCode 的闭包存储在内存中，服务重启后丢失
-/// 需要重新注册闭包才能正常触发任务
+/// 本次触发对应的调度时间（秒级时间戳）。
+///
+/// 各副本触发时刻有细微偏差，按当前 cron 推算出同一个调度时间作为锁键；
+/// 当前 cron 在此刻附近没有触发点（旧 cron 的调度）或推算失败时返回 `None`。
+fn scheduled_fire_time(task: &scraper_task::Model) -> Option<i64> {
+    let now = Utc::now();
+    let tolerance = Duration::seconds(FIRE_TOLERANCE_SECONDS);
+    let schedule = task.data.as_ref()?.schedule.as_ref()?;
+    let fire_at = *schedule.next_fire_times(now - tolerance, 1).ok()?.first()?;
+    ((fire_at.with_timezone(&Utc) - now).abs() <= tolerance).then_some(fire_at.timestamp())
+}
+
+/// 取消信号保留时长，覆盖 worker 轮询的间隔
+const CANCEL_SIGNAL_TTL_SECONDS: u64 = 24 * 3600;
+
+/// 通知 worker 停止实例：写入 `task:{task_id}:{instance_id}:cancel` 供轮询，并在同名频道发布消息
+pub async fn signal_cancel(
+    redis: &mut Redis,
+    task_id: i64,
+    instance_id: i64,
+) -> anyhow::Result<()> {
+    let key = format!("task:{task_id}:{instance_id}:cancel");
+    redis::pipe()
+        .cmd("SET")
+        .arg(&key)
+        .arg(1)
+        .arg("EX")
+        .arg(CANCEL_SIGNAL_TTL_SECONDS)
+        .ignore()
+        .cmd("PUBLISH")
+        .arg(&key)
+        .arg(instance_id)
+        .ignore()
+        .query_async::<()>(redis)
+        .await
+        .with_context(|| format!("publish cancel signal {key} failed"))
+}
+
+/// 构建任务的 cron 调度：配置了时区时按该时区触发，否则按服务器本地时间
+pub fn build_cron_job(
+    app: Arc<App>,
+    task_id: i64,
+    schedule: &ScheduleData,
+) -> anyhow::Result<tokio_cron_scheduler::Job> {
+    let cron = schedule.cron.trim();
+    let Some(tz) = schedule.tz().map_err(|e| anyhow::anyhow!("{e:?}"))? else {
+        return Ok(Job::cron_with_data(cron, task_id)
+            .run(dispatch_task)
+            .build(app));
+    };
+    tokio_cron_scheduler::Job::new_async_tz(cron, tz, move |_uuid, _sched| {
+        let app = app.clone();
+        Box::pin(async move {
+            let db = app.get_expect_component::<DbConn>();
+            let mut redis = app.get_expect_component::<Redis>();
+            let mut publisher = app.get_expect_component::<TaskPublisher>();
+            let quota = app.get_expect_component::<QuotaService>();
+            dispatch_cron_task(&db, &mut redis, &mut publisher, &quota, task_id).await
+        })
+    })
+    .with_context(|| format!("创建任务#{task_id}的调度失败"))
+}
+
+/// 本副本调度器中已注册的任务调度：任务ID -> (调度ID, 注册时的调度配置)。
+/// 调度器只在内存中，调度ID各副本不同，不写入数据库
+static LOCAL_SCHEDULES: LazyLock<Mutex<HashMap<i64, (JobId, ScheduleData)>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+/// 各副本按该间隔从数据库对齐调度，其他副本上的新增、暂停、恢复、修改在该时间内生效
+const SCHEDULE_RECONCILE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// 按任务当前状态对齐本副本的调度：未删除、未暂停且配置了调度时注册，调度配置变化时重新注册，
+/// 否则移除
+pub async fn sync_schedule(
+    sched: &JobScheduler,
+    app: Arc<App>,
+    task: &scraper_task::Model,
+) -> anyhow::Result<()> {
+    let desired = match task.schedule_state {
+        ScheduleState::Active if !task.deleted => {
+            task.data.as_ref().and_then(|d| d.schedule.clone())
+        }
+        _ => None,
+    };
+    let mut schedules = LOCAL_SCHEDULES.lock().await;
+    if schedules.get(&task.id).map(|(_, s)| s) == desired.as_ref() {
+        return Ok(());
+    }
+    if let Some((job_id, _)) = schedules.remove(&task.id) {
+        if let Err(e) = sched.remove(&job_id).await {
+            tracing::warn!("移除调度任务失败: {e:?}, job_id={job_id}");
+        }
+    }
+    if let Some(schedule) = desired {
+        let job = build_cron_job(app, task.id, &schedule)?;
+        let job_id = sched.add(job).await.context("添加调度失败")?;
+        schedules.insert(task.id, (job_id, schedule));
+    }
+    Ok(())
+}
+
+/// 移除本副本上任务的调度
+pub async fn unschedule(sched: &JobScheduler, task_id: i64) {
+    if let Some((job_id, _)) = LOCAL_SCHEDULES.lock().await.remove(&task_id) {
+        if let Err(e) = sched.remove(&job_id).await {
+            tracing::warn!("移除调度任务失败: {e:?}, job_id={job_id}");
+        }
+    }
+}
+
+/// 暂停调度：从调度器移除 cron 任务，任务及 `data.schedule` 配置保持不变
+pub async fn pause_schedule(
+    db: &DbConn,
+    sched: &JobScheduler,
+    task: &scraper_task::Model,
+) -> anyhow::Result<()> {
+    scraper_task::ActiveModel {
+        id: Set(task.id),
+        schedule_state: Set(ScheduleState::Paused),
+        ..Default::default()
+    }
+    .update(db)
+    .await
+    .context("pause task schedule failed")?;
+    unschedule(sched, task.id).await;
+    Ok(())
+}
+
+/// 恢复调度：按 `data.schedule` 重新注册 cron 任务，未配置调度时只恢复状态
+pub async fn resume_schedule(
+    db: &DbConn,
+    sched: &JobScheduler,
+    app: Arc<App>,
+    task: &scraper_task::Model,
+) -> anyhow::Result<()> {
+    let task = scraper_task::ActiveModel {
+        id: Set(task.id),
+        schedule_state: Set(ScheduleState::Active),
+        ..Default::default()
+    }
+    .update(db)
+    .await
+    .context("resume task schedule failed")?;
+    sync_schedule(sched, app, &task).await
+}
+
+/// 启动时从数据库恢复所有活跃（未暂停）的 cron 调度，之后定期对齐。
+///
+/// 调度器只在内存中，每个副本都注册全部调度，由 [`dispatch_cron_task`] 抢锁去重；
+/// 其他副本上的调度变更、副本宕机后的接管都靠定期对齐，无需选主。
 pub fn recover_task_schedules(
     app: Arc<App>,
 ) -> Box<dyn std::future::Future<Output = summer::error::Result<String>> + Send> {
     Box::new(async move {
-        let db = app.get_expect_component::<DbConn>();
-        let sched = app.get_expect_component::<JobScheduler>();
+        let count = reconcile_schedules(&app).await?;
+        tracing::info!("恢复任务调度，共 {count} 个活跃任务");
 
-        let tasks = ScraperTask::find()
-            .filter(scraper_task::Column::JobId.is_not_null())
-            .filter(scraper_task::Column::Deleted.eq(false))
-            .all(&db)
-            .await
-            .context("query active tasks failed")?;
-
-        tracing::info!("开始恢复任务调度，共 {} 个活跃任务", tasks.len());
-
-        for task in tasks {
-            let Some(data) = &task.data else {
-                continue;
-            };
-            let Some(ref schedule_data) = data.schedule else {
-                continue;
-            };
-
-            // 先移除旧的调度（闭包已丢失，元数据可能在 Postgres 中残留）
-            if let Some(old_job_id) = task.job_id {
-                if let Err(e) = sched.remove(&old_job_id).await {
-                    tracing::warn!("移除旧调度任务失败: {e:?}, job_id={old_job_id}");
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(SCHEDULE_RECONCILE_INTERVAL).await;
+                if let Err(e) = reconcile_schedules(&app).await {
+                    tracing::error!("对齐任务调度失败: {e:?}");
                 }
             }
+        });
 
-            // 重新注册调度
-            let job = Job::cron_with_data(&schedule_data.cron, task.id)
-                .run(dispatch_task)
-                .build(app.clone());
-
-            match sched.add(job).await {
-                Ok(new_job_id) => {
-                    // 更新数据库中的 job_id
-                    scraper_task::ActiveModel {
-                        id: Set(task.id),
-                        job_id: Set(Some(new_job_id)),
-                        ..Default::default()
-                    }
-                    .save(&db)
-                    .await
-                    .context("update task job_id failed")?;
-
-                    tracing::info!(
-                        "恢复任务调度: task_id={}, old_job_id={:?}, new_job_id={}",
-                        task.id,
-                        task.job_id,
-                        new_job_id
-                    );
-                }
-                Err(e) => {
-                    tracing::error!("恢复任务调度失败: task_id={}, error={:?}", task.id, e);
+        Ok("task schedules recovered".to_string())
+    })
+}
+
+/// 按数据库对齐本副本的全部调度，返回活跃调度数
+async fn reconcile_schedules(app: &Arc<App>) -> anyhow::Result<usize> {
+    let db = app.get_expect_component::<DbConn>();
+    let sched = app.get_expect_component::<JobScheduler>();
+
+    let tasks = ScraperTask::find()
+        .filter(scraper_task::Column::Deleted.eq(false))
+        .filter(scraper_task::Column::ScheduleState.eq(ScheduleState::Active))
+        .filter(Expr::cust("data->'schedule' IS NOT NULL"))
+        .all(&db)
+        .await
+        .context("query active tasks failed")?;
+
+    let active: HashSet<i64> = tasks.iter().map(|t| t.id).collect();
+    for task in &tasks {
+        if let Err(e) = sync_schedule(&sched, app.clone(), task).await {
+            tracing::error!("注册任务调度失败: task_id={}, error={e:?}", task.id);
+        }
+    }
+
+    // 查询之后可能有任务刚被恢复，移除前按最新状态重新判断
+    let stale = LOCAL_SCHEDULES
+        .lock()
+        .await
+        .keys()
+        .filter(|id| !active.contains(id))
+        .copied()
+        .collect::<Vec<_>>();
+    for task_id in stale {
+        match ScraperTask::find_by_id(task_id).one(&db).await {
+            Ok(Some(task)) => {
+                if let Err(e) = sync_schedule(&sched, app.clone(), &task).await {
+                    tracing::error!("对齐任务调度失败: task_id={task_id}, error={e:?}");
                 }
             }
+            Ok(None) => unschedule(&sched, task_id).await,
+            Err(e) => tracing::error!("find task#{task_id} failed: {e:?}"),
         }
+    }
 
-        Ok("task schedules recovered".to_string())
-    })
+    Ok(active.len())
 }
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+
+use crate::model::prelude::{ScraperTask, TaskDependency, TaskDispatch};
+use crate::model::sea_orm_active_enums::{ScheduleState, TriggerSource};
+use crate::model::{scraper_task, task_dependency, task_dispatch, task_instance};
+use crate::service::quota::{QuotaExceeded, QuotaService};
+use crate::task::{publish_task, DispatchOrigin, TaskPublisher};
+use chrono::{Duration, Local};
+use sea_orm::{ColumnTrait, DbConn, EntityTrait, QueryFilter};
+use summer::extractor::Component;
+use summer::tracing;
+use summer_job::cron;
+
+/// 每轮最多处理的结束实例数
+const CLAIM_BATCH: u64 = 100;
+/// 只处理最近结束的实例，避免上线时把历史实例全部触发一遍
+const CLAIM_WINDOW_HOURS: i64 = 24;
+
+#[cron("*/10 * * * * *")] // 每10秒执行一次
+async fn dispatch_downstream_tasks(
+    Component(db): Component<DbConn>,
+    Component(mut publisher): Component<TaskPublisher>,
+    Component(quota): Component<QuotaService>,
+) {
+    let after = Local::now().naive_local() - Duration::hours(CLAIM_WINDOW_HOURS);
+    let instances = match task_instance::Entity::claim_finished(&db, after, CLAIM_BATCH).await {
+        Ok(instances) => instances,
+        Err(e) => {
+            tracing::error!("认领已结束的任务实例失败: {e:?}");
+            return;
+        }
+    };
+
+    for instance in instances {
+        if !dispatch_downstream(&db, &mut publisher, &quota, &instance).await {
+            // 部分下游触发失败时撤销认领，已触发的下游按派发记录跳过，不会重复触发
+            if let Err(e) = task_instance::Entity::release_chain(&db, instance.id).await {
+                tracing::error!("撤销实例#{}的下游触发认领失败: {e:?}", instance.id);
+            }
+        }
+    }
+}
+
+/// 触发实例的所有下游任务，全部成功（或无需触发）时返回 `true`
+async fn dispatch_downstream(
+    db: &DbConn,
+    publisher: &mut TaskPublisher,
+    quota: &QuotaService,
+    instance: &task_instance::Model,
+) -> bool {
+    let dispatched: HashSet<i64> = match TaskDispatch::find()
+        .filter(task_dispatch::Column::UpstreamInstanceId.eq(instance.id))
+        .all(db)
+        .await
+    {
+        Ok(dispatches) => dispatches.into_iter().map(|d| d.task_id).collect(),
+        Err(e) => {
+            tracing::error!("查询实例#{}已触发的下游失败: {e:?}", instance.id);
+            return false;
+        }
+    };
+    let dependencies = match TaskDependency::find()
+        .filter(task_dependency::Column::UpstreamTaskId.eq(instance.task_id))
+        .filter(task_dependency::Column::TriggerOn.eq(instance.status))
+        .all(db)
+        .await
+    {
+        Ok(dependencies) => dependencies,
+        Err(e) => {
+            tracing::error!("查询任务#{}的下游依赖失败: {e:?}", instance.task_id);
+            return false;
+        }
+    };
+
+    let mut succeed = true;
+    for dependency in dependencies {
+        let downstream_id = dependency.downstream_task_id;
+        if dispatched.contains(&downstream_id) {
+            continue;
+        }
+        // 下游任务已删除或已暂停调度时不再自动触发
+        let downstream = match ScraperTask::find_by_id(downstream_id)
+            .filter(scraper_task::Column::Deleted.eq(false))
+            .filter(scraper_task::Column::ScheduleState.eq(ScheduleState::Active))
+            .one(db)
+            .await
+        {
+            Ok(Some(downstream)) => downstream,
+            Ok(None) => continue,
+            Err(e) => {
+                tracing::error!("查询下游任务#{downstream_id}失败: {e:?}");
+                succeed = false;
+                continue;
+            }
+        };
+
+        let upstream = DispatchOrigin::Upstream {
+            instance_id: instance.id,
+            pass_records: dependency.pass_records,
+        };
+        match publish_task(
+            db,
+            publisher,
+            quota,
+            &downstream,
+            TriggerSource::Upstream,
+            Some(dependency.user_id),
+            Some(upstream),
+        )
+        .await
+        {
+            Ok(job_id) => tracing::info!(
+                "实例#{}({:?})触发下游任务#{downstream_id}: job_id={job_id}",
+                instance.id,
+                instance.status
+            ),
+            Err(e) if e.is::<QuotaExceeded>() => {
+                tracing::warn!("实例#{}暂不触发下游任务#{downstream_id}: {e}", instance.id);
+                succeed = false;
+            }
+            Err(e) => {
+                tracing::error!(
+                    "实例#{}触发下游任务#{downstream_id}失败: {e:?}",
+                    instance.id
+                );
+                succeed = false;
+            }
+        }
+    }
+    succeed
+}
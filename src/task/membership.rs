@@ -0,0 +1,109 @@
+use crate::{
+    config::{mail::Email, pay::PayConfig},
+    service::user::UserService,
+    utils::{lock, mail},
+    views::user::MembershipExpiringEmailTemplate,
+};
+use chrono::{Duration, Local};
+use summer::extractor::{Component, Config};
+use summer::tracing;
+use summer_job::cron;
+use summer_mail::Mailer;
+use summer_redis::Redis;
+
+/// 到期前第几天发送提醒
+const REMIND_DAYS: [i64; 3] = [7, 3, 1];
+/// 提醒去重键的有效期，覆盖最早一次提醒到到期的时间
+const REMIND_KEY_TTL_SECONDS: u64 = 8 * 24 * 3600;
+/// 提醒邮件发送失败时的最多尝试次数及重试间隔
+const SEND_ATTEMPTS: u32 = 3;
+const SEND_RETRY_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[cron("0 0 9 * * *")] // 每天9点执行
+async fn check_membership_expiry(
+    Component(user_service): Component<UserService>,
+    Component(mut redis): Component<Redis>,
+    Component(mailer): Component<Mailer>,
+    Config(email): Config<Email>,
+    Config(pay_config): Config<PayConfig>,
+) {
+    tracing::info!("开始检查会员到期");
+
+    let now = Local::now().naive_local();
+    for days in REMIND_DAYS {
+        let from = now + Duration::days(days - 1);
+        let to = now + Duration::days(days);
+        let users = match user_service.find_expiring_members(from, to).await {
+            Ok(users) => users,
+            Err(e) => {
+                tracing::error!("查询{days}天内到期的会员失败: {e:?}");
+                continue;
+            }
+        };
+
+        for user in users {
+            let Some(expired_at) = user.vip_expired_at else {
+                continue;
+            };
+            // 同一到期时间每个提醒节点只发一次，多实例/任务重跑时不重复发送
+            let key = format!(
+                "membership:remind:{}:{}:{days}",
+                user.id,
+                expired_at.and_utc().timestamp()
+            );
+            match lock::try_acquire(&mut redis, &key, REMIND_KEY_TTL_SECONDS).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    tracing::error!("会员到期提醒去重失败: {e:?}");
+                    continue;
+                }
+            }
+
+            let edition = user.edition.to_string();
+            let template = MembershipExpiringEmailTemplate {
+                name: &user.name,
+                edition: &edition,
+                days,
+                expired_at: expired_at.format("%Y-%m-%d %H:%M").to_string(),
+                renew_url: &pay_config.renew_url,
+            };
+            let subject = format!("您的 AutoWDS 会员将在{days}天后到期");
+            // 提醒窗口按天滚动，下次任务运行时已错过该节点，发送失败在本次运行内重试
+            for attempt in 1..=SEND_ATTEMPTS {
+                match mail::send_mail(&mailer, &email.from, &user.email, &subject, &template).await
+                {
+                    Ok(_) => break,
+                    Err(e) if attempt < SEND_ATTEMPTS => {
+                        tracing::warn!(
+                            "发送会员到期提醒给用户 {} 失败（第{attempt}次），稍后重试: {e:?}",
+                            user.id
+                        );
+                        tokio::time::sleep(SEND_RETRY_DELAY).await;
+                    }
+                    Err(e) => {
+                        tracing::error!("发送会员到期提醒给用户 {} 失败: {e:?}", user.id);
+                    }
+                }
+            }
+        }
+    }
+
+    // 主动降级已到期会员，不再等用户访问接口时才触发
+    match user_service.find_expired_members().await {
+        Ok(users) => {
+            tracing::info!("找到 {} 个已到期的会员", users.len());
+            for user in users {
+                let user_id = user.id;
+                if let Err(e) = user_service.refresh_user_membership(user).await {
+                    tracing::error!("会员 {user_id} 到期降级失败: {e:?}");
+                }
+            }
+        }
+        Err(e) => {
+            tracing::error!("查询已到期会员失败: {e:?}");
+        }
+    }
+
+    tracing::info!("会员到期检查完成");
+}
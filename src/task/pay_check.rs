@@ -1,42 +1,40 @@
-use crate::{model::sea_orm_active_enums::PayFrom, service::pay::PayOrderService};
-use chrono::{Duration, Local};
+use crate::{
+    model::sea_orm_active_enums::OrderStatus,
+    service::{pay::PayOrderService, pay_notify::PayNotifyService, user::UserService},
+};
+use chrono::Local;
 use summer::extractor::Component;
 use summer::tracing;
 use summer_job::cron;
 
 #[cron("0 */5 * * * *")] // 每5分钟执行一次
-async fn check_pending_orders(Component(pay_service): Component<PayOrderService>) {
+async fn check_pending_orders(
+    Component(pay_service): Component<PayOrderService>,
+    Component(user_service): Component<UserService>,
+) {
     tracing::info!("开始检查待确认的支付订单");
 
-    // 查询30分钟前创建但未确认的订单
-    let check_time = Local::now().naive_local() - Duration::minutes(30);
+    // 查询有效期内创建但未确认的订单，超时的订单交给 close_expired_orders 处理
+    let check_time = Local::now().naive_local() - pay_service.order_ttl();
 
     match pay_service.find_wait_confirm_after(check_time).await {
         Ok(orders) => {
             tracing::info!("找到 {} 个待确认订单", orders.len());
 
             for order in orders {
-                match order.pay_from {
-                    PayFrom::Alipay => {
-                        if let Err(e) = pay_service.query_alipay_order(order.clone()).await {
-                            tracing::error!("查询支付宝订单 {} 状态失败: {}", order.id, e);
-                        } else {
-                            tracing::info!("已更新支付宝订单 {} 状态", order.id);
+                let order_id = order.id;
+                match pay_service.query_order(order).await {
+                    Ok(order) => {
+                        tracing::info!("已更新订单 {} 状态: {:?}", order_id, order.status);
+                        // 回调可能丢失，查单确认支付后同样开通会员（已履约的订单会被跳过）
+                        if let Err(e) = user_service.fulfil_paid_order(&order).await {
+                            tracing::error!("订单 {} 开通会员失败: {:?}", order_id, e);
+                        } else if order.status != OrderStatus::Created {
+                            pay_service.publish_status(&order).await;
                         }
                     }
-                    PayFrom::Wechat => {
-                        if let Err(e) = pay_service.query_wechat_order(order.clone()).await {
-                            tracing::error!("查询微信订单 {} 状态失败: {}", order.id, e);
-                        } else {
-                            tracing::info!("已更新微信订单 {} 状态", order.id);
-                        }
-                    }
-                    PayFrom::Paddle => {
-                        if let Err(e) = pay_service.query_paddle_order(order.clone()).await {
-                            tracing::error!("查询 Paddle 订单 {} 状态失败: {}", order.id, e);
-                        } else {
-                            tracing::info!("已更新 Paddle 订单 {} 状态", order.id);
-                        }
+                    Err(e) => {
+                        tracing::error!("查询订单 {} 状态失败: {}", order_id, e);
                     }
                 }
             }
@@ -48,3 +46,61 @@ async fn check_pending_orders(Component(pay_service): Component<PayOrderService>
 
     tracing::info!("支付订单状态检查完成");
 }
+
+#[cron("30 * * * * *")] // 每分钟执行一次
+async fn close_expired_orders(
+    Component(pay_service): Component<PayOrderService>,
+    Component(user_service): Component<UserService>,
+) {
+    let expire_time = Local::now().naive_local() - pay_service.order_ttl();
+
+    let orders = match pay_service.find_expired_created(expire_time).await {
+        Ok(orders) => orders,
+        Err(e) => {
+            tracing::error!("查询超时订单失败: {}", e);
+            return;
+        }
+    };
+    if orders.is_empty() {
+        return;
+    }
+    tracing::info!("找到 {} 个超时未支付订单", orders.len());
+
+    for order in orders {
+        let order_id = order.id;
+        match pay_service.close_expired_order(order).await {
+            Ok(order) if order.status == OrderStatus::Paid => {
+                // 关单过程中支付到账，按支付成功处理
+                tracing::info!("超时订单 {} 已支付，开通会员", order_id);
+                if let Err(e) = user_service.fulfil_paid_order(&order).await {
+                    tracing::error!("超时订单 {} 开通会员失败: {:?}", order_id, e);
+                } else {
+                    pay_service.publish_status(&order).await;
+                }
+            }
+            Ok(order) => {
+                tracing::info!("超时订单 {} 当前状态: {:?}", order_id, order.status);
+                pay_service.publish_status(&order).await;
+            }
+            Err(e) => {
+                tracing::error!("关闭超时订单 {} 失败: {:#}", order_id, e);
+            }
+        }
+    }
+}
+
+#[cron("15 */2 * * * *")] // 每2分钟执行一次
+async fn retry_failed_notify_events(
+    Component(notify_service): Component<PayNotifyService>,
+    Component(pay_service): Component<PayOrderService>,
+    Component(user_service): Component<UserService>,
+) {
+    match notify_service
+        .retry_failed(&pay_service, &user_service)
+        .await
+    {
+        Ok(0) => {}
+        Ok(count) => tracing::info!("已重试 {} 个失败的支付回调事件", count),
+        Err(e) => tracing::error!("重试支付回调事件失败: {:#}", e),
+    }
+}
@@ -0,0 +1,106 @@
+use crate::model::prelude::ScraperTask;
+use crate::model::sea_orm_active_enums::TriggerSource;
+use crate::model::task_instance;
+use crate::service::quota::{QuotaExceeded, QuotaService};
+use crate::task::{publish_task, DispatchOrigin, TaskPublisher};
+use chrono::{Duration, Local};
+use sea_orm::{DbConn, EntityTrait};
+use summer::extractor::Component;
+use summer::tracing;
+use summer_job::cron;
+
+/// 每轮最多检查的失败实例数
+const RETRY_BATCH: u64 = 100;
+/// 只重试最近失败的实例，单次等待上限远小于该窗口
+const RETRY_WINDOW_HOURS: i64 = 24;
+
+#[cron("*/15 * * * * *")] // 每15秒执行一次
+async fn retry_failed_instances(
+    Component(db): Component<DbConn>,
+    Component(mut publisher): Component<TaskPublisher>,
+    Component(quota): Component<QuotaService>,
+) {
+    let now = Local::now().naive_local();
+    let after = now - Duration::hours(RETRY_WINDOW_HOURS);
+    let instances = match task_instance::Entity::find_retry_pending(&db, after, RETRY_BATCH).await {
+        Ok(instances) => instances,
+        Err(e) => {
+            tracing::error!("查询待重试的任务实例失败: {e:?}");
+            return;
+        }
+    };
+
+    for instance in instances {
+        let task = match ScraperTask::find_by_id(instance.task_id).one(&db).await {
+            Ok(task) => task,
+            Err(e) => {
+                tracing::error!("查询任务#{}失败: {e:?}", instance.task_id);
+                continue;
+            }
+        };
+        let task = task.filter(|t| !t.deleted);
+        let policy = task
+            .as_ref()
+            .and_then(|t| t.data.as_ref())
+            .and_then(|d| d.retry.clone())
+            .filter(|p| {
+                instance.attempt < p.max_attempts as i32
+                    && p.matches(instance.error_message.as_deref())
+            });
+        let (Some(task), Some(policy)) = (task, policy) else {
+            // 不需要重试，标记后不再检查
+            if let Err(e) = task_instance::Entity::mark_retry_checked(&db, instance.id).await {
+                tracing::error!("{e:?}");
+            }
+            continue;
+        };
+
+        let attempt = instance.attempt + 1;
+        let due = instance.modified + Duration::seconds(policy.backoff(attempt as u32) as i64);
+        if due > now {
+            continue;
+        }
+        // 标记作为多实例间的认领，超出配额或派发失败时撤销标记，下一轮再判定
+        match task_instance::Entity::mark_retry_checked(&db, instance.id).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                tracing::error!("{e:?}");
+                continue;
+            }
+        }
+
+        let origin = DispatchOrigin::Retry {
+            parent_instance_id: instance.id,
+            attempt,
+        };
+        match publish_task(
+            &db,
+            &mut publisher,
+            &quota,
+            &task,
+            TriggerSource::Retry,
+            None,
+            Some(origin),
+        )
+        .await
+        {
+            Ok(job_id) => tracing::info!(
+                "重试失败实例#{}(第{attempt}次): task_id={}, job_id={job_id}",
+                instance.id,
+                instance.task_id
+            ),
+            Err(e) => {
+                if e.is::<QuotaExceeded>() {
+                    tracing::warn!("实例#{}暂不重试: {e}", instance.id);
+                } else {
+                    tracing::error!("重试失败实例#{}失败: {e:?}", instance.id);
+                }
+                if let Err(e) = task_instance::Entity::release_retry_checked(&db, instance.id).await
+                {
+                    tracing::error!("{e:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use crate::model::prelude::ScraperTask;
+use crate::model::scraper_task::{self, DEFAULT_INSTANCE_TIMEOUT};
+use crate::model::task_instance;
+use crate::service::task_log::TaskLogService;
+use chrono::{Duration, Local};
+use itertools::Itertools;
+use sea_orm::{ColumnTrait, DbConn, EntityTrait, QueryFilter};
+use summer::extractor::Component;
+use summer::tracing;
+use summer_job::cron;
+use summer_redis::Redis;
+
+/// 超过该时间未上报心跳视为 worker 已失联（秒）
+const HEARTBEAT_TIMEOUT_SECONDS: i64 = 300;
+
+/// 把 worker 崩溃后遗留在 `running` 的实例标记为失败。
+///
+/// 上报过心跳的实例按心跳判断失联，所有实例都受任务的最长运行时间限制。
+#[cron("0 * * * * *")] // 每分钟执行一次
+async fn fail_stuck_instances(
+    Component(db): Component<DbConn>,
+    Component(mut redis): Component<Redis>,
+) {
+    let instances = match task_instance::Entity::find_running(&db).await {
+        Ok(instances) => instances,
+        Err(e) => {
+            tracing::error!("查询运行中的任务实例失败: {e:?}");
+            return;
+        }
+    };
+    if instances.is_empty() {
+        return;
+    }
+
+    let task_ids = instances.iter().map(|i| i.task_id).unique().collect_vec();
+    let timeouts: HashMap<i64, u32> = match ScraperTask::find()
+        .filter(scraper_task::Column::Id.is_in(task_ids))
+        .all(&db)
+        .await
+    {
+        Ok(tasks) => tasks
+            .into_iter()
+            .map(|t| {
+                let timeout = t
+                    .data
+                    .map_or(DEFAULT_INSTANCE_TIMEOUT, |d| d.instance_timeout());
+                (t.id, timeout)
+            })
+            .collect(),
+        Err(e) => {
+            tracing::error!("查询运行中实例的任务失败: {e:?}");
+            return;
+        }
+    };
+
+    let now = Local::now().naive_local();
+    for instance in instances {
+        let timeout = timeouts
+            .get(&instance.task_id)
+            .copied()
+            .unwrap_or(DEFAULT_INSTANCE_TIMEOUT);
+        let message = match instance.heartbeat {
+            Some(heartbeat) if now - heartbeat > Duration::seconds(HEARTBEAT_TIMEOUT_SECONDS) => {
+                format!("心跳超时：worker 超过{HEARTBEAT_TIMEOUT_SECONDS}秒未上报心跳，可能已崩溃")
+            }
+            _ if now - instance.created > Duration::seconds(timeout as i64) => {
+                format!("运行超时：超过任务最长运行时间{timeout}秒")
+            }
+            _ => continue,
+        };
+
+        match task_instance::Entity::fail_if_running(&db, instance.id, &message).await {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) => {
+                tracing::error!("{e:?}");
+                continue;
+            }
+        }
+        tracing::warn!(
+            "任务#{}的实例#{}已标记为失败: {message}",
+            instance.task_id,
+            instance.id
+        );
+        if let Err(e) =
+            TaskLogService::publish_logs_eof(&mut redis, instance.task_id, instance.id).await
+        {
+            tracing::error!("{e:?}");
+        }
+    }
+}
@@ -223,6 +223,70 @@ pub fn decode(token: &str) -> Result<Claims> {
     Ok(token_data.claims)
 }
 
+/// Worker 访问令牌载荷（与用户登录 JWT 分离，避免混用）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkerClaims {
+    pub worker_id: i64,
+    pub purpose: String,
+    /// 签发时的密钥指纹，重置密钥后旧令牌随之失效
+    pub secret_version: String,
+    exp: u64,
+}
+
+const WORKER_PURPOSE: &str = "worker";
+/// Worker 令牌有效期（秒）
+pub const WORKER_TOKEN_TTL_SECONDS: u64 = 60 * 60;
+
+impl WorkerClaims {
+    pub fn new(worker_id: i64, secret_version: String) -> Self {
+        Self {
+            worker_id,
+            purpose: WORKER_PURPOSE.to_string(),
+            secret_version,
+            exp: jsonwebtoken::get_current_timestamp() + WORKER_TOKEN_TTL_SECONDS,
+        }
+    }
+}
+
+impl<S> FromRequestParts<S> for WorkerClaims
+where
+    S: Send + Sync,
+{
+    type Rejection = WebError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let TypedHeader(Authorization(bearer)) = parts
+            .extract::<TypedHeader<Authorization<Bearer>>>()
+            .await
+            .map_err(|_| KnownWebError::unauthorized("invalid token"))?;
+        decode_worker(bearer.token())
+    }
+}
+
+pub fn encode_worker(worker_id: i64, secret_version: String) -> Result<String> {
+    let claims = WorkerClaims::new(worker_id, secret_version);
+    let header = Header::new(Algorithm::RS256);
+    let token = jsonwebtoken::encode::<WorkerClaims>(&header, &claims, &ENCODE_KEY)
+        .map_err(|_| KnownWebError::internal_server_error("Token created error"))?;
+    Ok(token)
+}
+
+pub fn decode_worker(token: &str) -> Result<WorkerClaims> {
+    let validation = Validation::new(Algorithm::RS256);
+    let token_data = jsonwebtoken::decode::<WorkerClaims>(token, &DECODE_KEY, &validation)
+        .map_err(|e| {
+            tracing::error!("{:?}", e);
+            KnownWebError::unauthorized("invalid token")
+        })?;
+    if token_data.claims.purpose != WORKER_PURPOSE {
+        Err(KnownWebError::unauthorized("invalid token"))?;
+    }
+    Ok(token_data.claims)
+}
+
 /// 营销邮件退订 JWT 载荷（与用户登录 JWT 分离，避免混用）
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MarketingUnsubscribeClaims {
@@ -0,0 +1,18 @@
+use anyhow::Context;
+use summer_redis::{redis, Redis};
+
+/// `SET key 1 NX EX ttl`，返回 `true` 表示本次抢占成功。
+///
+/// 用于多实例部署时定时任务去重，键过期后自动释放。
+pub async fn try_acquire(redis: &mut Redis, key: &str, ttl_seconds: u64) -> anyhow::Result<bool> {
+    let reply = redis::cmd("SET")
+        .arg(key)
+        .arg(1)
+        .arg("NX")
+        .arg("EX")
+        .arg(ttl_seconds)
+        .query_async::<Option<String>>(redis)
+        .await
+        .with_context(|| format!("set nx {key} to redis failed"))?;
+    Ok(reply.is_some())
+}
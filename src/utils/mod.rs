@@ -1,4 +1,7 @@
+pub mod crypto;
 pub mod jwt;
+pub mod lock;
 pub mod mail;
 pub mod rand;
+pub mod rate_limit;
 pub mod validate_code;
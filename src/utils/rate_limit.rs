@@ -0,0 +1,30 @@
+use anyhow::Context;
+use summer_redis::{redis, Redis};
+
+/// 固定窗口计数：`INCR key`，窗口内第一次计数时设置过期时间，返回窗口内的累计次数。
+pub async fn incr_window(redis: &mut Redis, key: &str, window_seconds: u64) -> anyhow::Result<u64> {
+    let count = redis::cmd("INCR")
+        .arg(key)
+        .query_async::<u64>(redis)
+        .await
+        .with_context(|| format!("incr {key} to redis failed"))?;
+    if count == 1 {
+        redis::cmd("EXPIRE")
+            .arg(key)
+            .arg(window_seconds)
+            .query_async::<()>(redis)
+            .await
+            .with_context(|| format!("expire {key} to redis failed"))?;
+    }
+    Ok(count)
+}
+
+/// 读取窗口内的累计次数，窗口未开始或已过期时为 0
+pub async fn window_count(redis: &mut Redis, key: &str) -> anyhow::Result<u64> {
+    let count = redis::cmd("GET")
+        .arg(key)
+        .query_async::<Option<u64>>(redis)
+        .await
+        .with_context(|| format!("get {key} from redis failed"))?;
+    Ok(count.unwrap_or_default())
+}
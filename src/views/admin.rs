@@ -1,8 +1,10 @@
 use crate::model::{
     account_user, scraper_task,
-    sea_orm_active_enums::{ProductEdition, TemplateTopic},
-    task_template,
+    sea_orm_active_enums::{ProductEdition, ScheduleState, TemplateTopic},
+    task_template, worker,
 };
+use chrono::{NaiveDate, NaiveDateTime};
+use sea_orm::FromQueryResult;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use validator::Validate;
@@ -88,6 +90,8 @@ pub struct AdjustCreditsReq {
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct UpdateUserEditionReq {
     pub edition: ProductEdition,
+    /// 会员到期时间，为空时保持不变；会员已到期时开通付费版本必须指定
+    pub vip_expired_at: Option<NaiveDateTime>,
     #[validate(length(min = 1, max = 200, message = "描述长度必须在1-200字符之间"))]
     pub description: String,
 }
@@ -131,6 +135,8 @@ impl From<scraper_task::Model> for TaskResp {
     fn from(task: scraper_task::Model) -> Self {
         let status = if task.deleted {
             "completed"
+        } else if task.schedule_state == ScheduleState::Paused {
+            "paused"
         } else if task.data.is_some() {
             "running"
         } else {
@@ -233,6 +239,7 @@ pub struct UpdateTemplateReq {
 pub struct TaskStatisticsResp {
     pub pending: i64,
     pub running: i64,
+    pub paused: i64,
     pub completed: i64,
     pub failed: i64,
     pub deleted: i64,
@@ -244,3 +251,63 @@ pub struct StatisticsOverviewResp {
     pub task_count: i64,
     pub template_count: i64,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct TrialStatisticsQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+}
+
+/// 试用转化统计，按试用开通时间筛选
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct TrialStatisticsResp {
+    /// 开通试用人数
+    pub started: i64,
+    /// 试用中（未到期且未付费）
+    pub active: i64,
+    /// 试用后付费人数
+    pub converted: i64,
+    /// 转化率：付费人数 / 已结束或已付费的试用人数
+    pub conversion_rate: f64,
+    /// 从开通试用到付费的平均天数
+    pub avg_days_to_convert: Option<f64>,
+}
+
+// ==================== Worker 相关 ====================
+
+#[derive(Debug, Serialize)]
+pub struct WorkerResp {
+    pub id: i64,
+    pub name: String,
+    pub enabled: bool,
+    pub last_seen: Option<String>,
+    pub created_at: String,
+}
+
+impl From<worker::Model> for WorkerResp {
+    fn from(worker: worker::Model) -> Self {
+        Self {
+            id: worker.id,
+            name: worker.name,
+            enabled: worker.enabled,
+            last_seen: worker
+                .last_seen
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()),
+            created_at: worker.created.format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWorkerReq {
+    #[validate(length(min = 1, max = 64, message = "worker名称长度必须在1-64字符之间"))]
+    pub name: String,
+}
+
+/// worker 凭证，`secret` 只在创建或重置时返回一次
+#[derive(Debug, Serialize)]
+pub struct WorkerCredentialResp {
+    pub id: i64,
+    pub name: String,
+    pub secret: String,
+}
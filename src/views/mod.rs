@@ -2,10 +2,13 @@ pub mod admin;
 pub mod data_clean;
 pub mod marketing;
 pub mod pay;
+pub mod proxy;
 pub mod statistics;
 pub mod store;
 pub mod task;
+pub mod task_bundle;
 pub mod task_instance_capture;
 pub mod template;
 pub mod token;
 pub mod user;
+pub mod worker;
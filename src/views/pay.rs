@@ -1,5 +1,16 @@
-use crate::model::sea_orm_active_enums::{OrderLevel, PayFrom};
-use serde::Serialize;
+use crate::model::{
+    account_user, pay_order,
+    sea_orm_active_enums::{
+        InvoiceStatus, NotifyStatus, OrderKind, OrderLevel, OrderStatus, PayFrom, ProductEdition,
+    },
+};
+use askama::Template;
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use sea_orm::{prelude::DateTime, FromQueryResult};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use validator::Validate;
 
 #[derive(Debug, Serialize)]
 pub struct PayStatusResponse {
@@ -10,3 +21,334 @@ pub struct PayStatusResponse {
     pub created: String,
     pub confirm: Option<String>,
 }
+
+/// # 支付方式
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, Display,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum PayMode {
+    /// # 扫码支付（支付宝当面付预下单、微信 Native）
+    #[default]
+    Native,
+    /// # PC 网页跳转收银台（支付宝 `page.pay`）
+    Page,
+    /// # 手机网页跳转收银台（支付宝 `wap.pay`）
+    Wap,
+    /// # 微信外的手机浏览器（微信 H5）
+    H5,
+    /// # 微信内网页（微信 JSAPI），需要用户 openid
+    Jsapi,
+}
+
+/// # 下单后前端需要执行的支付动作
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PaymentAction {
+    /// # 展示二维码
+    QrCode { code_url: String },
+    /// # 跳转到收银台
+    Redirect { url: String },
+    /// # 调起微信 JSAPI 支付，`params` 原样传给 `WeixinJSBridge.invoke('getBrandWCPayRequest')`
+    Jsapi { params: serde_json::Value },
+}
+
+impl PaymentAction {
+    /// 二维码内容或跳转地址
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            PaymentAction::QrCode { code_url } => Some(code_url),
+            PaymentAction::Redirect { url } => Some(url),
+            PaymentAction::Jsapi { .. } => None,
+        }
+    }
+}
+
+/// 订单状态变更事件，经 Redis pub/sub 广播并作为 SSE `status` 事件推送给前端
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct PayStatusEvent {
+    pub order_id: i64,
+    pub status: OrderStatus,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PayNotifyEventQuery {
+    pub provider: Option<PayFrom>,
+    pub status: Option<NotifyStatus>,
+    pub order_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RefundOrderReq {
+    /// 退款金额（分），为空时全额退款；Paddle 订单只能全额退款
+    #[validate(range(min = 1, message = "退款金额必须大于0"))]
+    pub amount: Option<i32>,
+    #[validate(length(min = 1, max = 80, message = "退款原因长度必须在1-80字符之间"))]
+    pub reason: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UpgradeQuoteQuery {
+    pub edition: ProductEdition,
+    pub level: OrderLevel,
+}
+
+/// # 升级报价
+///
+/// 当前版本未使用的时长按原价折算为抵扣金额，抵扣后至少支付 1 元，
+/// 超出部分按新版本价格折算为赠送时长。
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UpgradeQuote {
+    /// 当前版本
+    pub from_edition: ProductEdition,
+    /// 当前版本的计费周期，用于折算剩余时长
+    pub from_level: OrderLevel,
+    /// 升级后的版本
+    pub to_edition: ProductEdition,
+    /// 升级后的计费周期
+    pub level: OrderLevel,
+    /// 当前版本剩余时长（秒）
+    pub remaining_seconds: i64,
+    /// 新版本价格（分）
+    pub price: i32,
+    /// 剩余时长折算的抵扣金额（分）
+    pub credit: i32,
+    /// 应付金额（分）
+    pub amount: i32,
+    /// 抵扣后剩余价值折算的新版本时长（秒）
+    pub bonus_seconds: i64,
+    /// 按当前时间支付后的预计到期时间
+    pub expired_at: DateTime,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PayOrderQuery {
+    pub status: Option<OrderStatus>,
+}
+
+/// # 订单
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PayOrderResp {
+    pub id: i64,
+    pub kind: OrderKind,
+    pub level: OrderLevel,
+    pub edition: ProductEdition,
+    pub upgrade_from: Option<ProductEdition>,
+    pub pay_from: PayFrom,
+    pub status: OrderStatus,
+    /// 订单金额（分）
+    pub amount: i32,
+    pub created: DateTime,
+    pub confirm: Option<DateTime>,
+}
+
+impl From<pay_order::Model> for PayOrderResp {
+    fn from(order: pay_order::Model) -> Self {
+        Self {
+            id: order.id,
+            kind: order.kind,
+            level: order.level,
+            edition: order.edition,
+            upgrade_from: order.upgrade_from,
+            pay_from: order.pay_from,
+            status: order.status,
+            amount: order.amount,
+            created: order.created,
+            confirm: order.confirm,
+        }
+    }
+}
+
+/// 支付收据，浏览器中可直接打印或另存为 PDF
+#[derive(Template)]
+#[template(path = "pay/receipt.html")]
+pub struct PayReceiptTemplate {
+    pub receipt_no: String,
+    pub user_name: String,
+    pub user_email: String,
+    pub product: String,
+    pub pay_from: String,
+    pub amount: String,
+    pub status: String,
+    pub created: String,
+    pub paid_at: String,
+}
+
+impl PayReceiptTemplate {
+    pub fn new(receipt_no: String, order: &pay_order::Model, user: &account_user::Model) -> Self {
+        let product = match order.kind {
+            OrderKind::Purchase => format!("{} {}会员", order.edition, order.level.title()),
+            OrderKind::Upgrade => format!(
+                "{} 升级至 {} {}会员",
+                order.upgrade_from.as_ref().unwrap_or(&order.edition),
+                order.edition,
+                order.level.title()
+            ),
+        };
+        let time_fmt = "%Y-%m-%d %H:%M:%S";
+        Self {
+            receipt_no,
+            user_name: user.name.clone(),
+            user_email: user.email.clone(),
+            product,
+            pay_from: order.pay_from.title().to_string(),
+            amount: format_amount(order.amount),
+            status: order.status.title().to_string(),
+            created: order.created.format(time_fmt).to_string(),
+            paid_at: order
+                .confirm
+                .map(|t| t.format(time_fmt).to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// # 发票申请
+#[derive(Debug, Deserialize, JsonSchema, Validate)]
+pub struct InvoiceApplyReq {
+    /// 发票抬头
+    #[validate(length(min = 1, max = 128, message = "发票抬头长度必须在1-128字符之间"))]
+    pub title: String,
+    /// 纳税人识别号，个人抬头可不填
+    #[validate(length(min = 15, max = 20, message = "纳税人识别号长度必须在15-20字符之间"))]
+    pub tax_id: Option<String>,
+    /// 接收发票的邮箱
+    #[validate(email(message = "邮箱格式不正确"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InvoiceRequestQuery {
+    pub status: Option<InvoiceStatus>,
+    pub user_id: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct IssueInvoiceReq {
+    #[validate(length(min = 1, max = 64, message = "发票号码长度必须在1-64字符之间"))]
+    pub invoice_no: String,
+    /// 电子发票下载地址
+    #[validate(url(message = "发票下载地址格式不正确"))]
+    pub invoice_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct RejectInvoiceReq {
+    #[validate(length(min = 1, max = 256, message = "驳回原因长度必须在1-256字符之间"))]
+    pub reason: String,
+}
+
+#[derive(Template)]
+#[template(path = "mail/invoice.html")]
+pub struct InvoiceEmailTemplate<'a> {
+    pub tip: &'a str,
+    pub title: &'a str,
+    pub amount: String,
+    pub invoice_no: Option<&'a str>,
+    pub invoice_url: Option<&'a str>,
+    pub remark: Option<&'a str>,
+}
+
+/// 金额（分）格式化为 `¥29.00`
+pub fn format_amount(cents: i32) -> String {
+    format!("¥{}.{:02}", cents / 100, cents % 100)
+}
+
+/// 统计粒度
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Day,
+    Week,
+    Month,
+}
+
+impl Granularity {
+    /// PostgreSQL `date_trunc` 的时间单位
+    pub fn unit(&self) -> &'static str {
+        match self {
+            Granularity::Day => "day",
+            Granularity::Week => "week",
+            Granularity::Month => "month",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Granularity::Day => "天",
+            Granularity::Week => "周",
+            Granularity::Month => "月",
+        }
+    }
+
+    /// 单次统计允许的最大天数，避免生成过多的统计周期
+    pub fn max_days(&self) -> i64 {
+        match self {
+            Granularity::Day => 366,
+            Granularity::Week => 366 * 3,
+            Granularity::Month => 366 * 10,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FinanceReportQuery {
+    pub start_date: Option<NaiveDate>,
+    pub end_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub granularity: Granularity,
+}
+
+/// 单个统计周期的收入指标，金额单位均为分
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct RevenueBucket {
+    pub bucket: DateTime,
+    /// 已支付订单总收入（不含已退款订单，部分退款的订单扣除已退金额）
+    pub revenue: i64,
+    /// 首次购买收入
+    pub new_revenue: i64,
+    /// 续费收入
+    pub renewal_revenue: i64,
+    /// 升级补差价收入
+    pub upgrade_revenue: i64,
+    pub order_count: i64,
+    pub paying_users: i64,
+    /// 会员到期未续费的用户数
+    pub churned: i64,
+    /// 周期末的月度经常性收入
+    pub mrr: i64,
+}
+
+/// 按支付渠道/版本汇总的收入
+#[derive(Debug, Serialize, FromQueryResult)]
+pub struct RevenueBreakdown {
+    pub key: String,
+    pub order_count: i64,
+    pub revenue: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FinanceSummary {
+    pub revenue: i64,
+    pub new_revenue: i64,
+    pub renewal_revenue: i64,
+    pub upgrade_revenue: i64,
+    pub paying_users: i64,
+    /// 每付费用户平均收入
+    pub arpu: i64,
+    pub churned: i64,
+    pub mrr: i64,
+    pub arr: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FinanceReport {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub summary: FinanceSummary,
+    pub series: Vec<RevenueBucket>,
+    pub by_provider: Vec<RevenueBreakdown>,
+    pub by_edition: Vec<RevenueBreakdown>,
+}
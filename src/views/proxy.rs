@@ -0,0 +1,118 @@
+use crate::model::{
+    proxy, proxy_pool,
+    sea_orm_active_enums::{ProxyProtocol, ProxyRotation},
+};
+use schemars::JsonSchema;
+use sea_orm::prelude::DateTime;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+/// # 新增/修改代理池
+#[derive(Debug, Deserialize, Validate, JsonSchema)]
+pub struct ProxyPoolReq {
+    /// # 名称
+    #[validate(length(min = 1, max = 64, message = "名称长度须在1-64字符之间"))]
+    pub name: String,
+    /// # 轮换策略
+    pub rotation: ProxyRotation,
+    /// # 是否为平台代理池，仅管理员可创建
+    #[serde(default)]
+    pub platform: bool,
+}
+
+/// # 代理池
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ProxyPoolResp {
+    pub id: i32,
+    pub name: String,
+    pub rotation: ProxyRotation,
+    /// # 平台代理池所有用户可用
+    pub platform: bool,
+    pub created: DateTime,
+    pub modified: DateTime,
+}
+
+impl From<proxy_pool::Model> for ProxyPoolResp {
+    fn from(pool: proxy_pool::Model) -> Self {
+        Self {
+            platform: pool.is_platform(),
+            id: pool.id,
+            name: pool.name,
+            rotation: pool.rotation,
+            created: pool.created,
+            modified: pool.modified,
+        }
+    }
+}
+
+/// # 新增/修改代理
+#[derive(Debug, Deserialize, Validate, JsonSchema)]
+pub struct ProxyReq {
+    pub protocol: ProxyProtocol,
+    /// # 主机名或IP，不含协议和端口
+    #[validate(
+        length(min = 1, max = 255, message = "主机长度须在1-255字符之间"),
+        custom(function = validate_host)
+    )]
+    pub host: String,
+    #[validate(range(min = 1, max = 65535, message = "端口须在1-65535之间"))]
+    pub port: i32,
+    #[validate(length(max = 128, message = "用户名过长"))]
+    pub username: Option<String>,
+    /// # 密码，修改时为空表示保留原密码
+    #[validate(length(max = 256, message = "密码过长"))]
+    pub password: Option<String>,
+    /// # 是否启用，默认启用
+    pub enabled: Option<bool>,
+}
+
+fn validate_host(host: &str) -> Result<(), ValidationError> {
+    if host.contains("://") || host.chars().any(char::is_whitespace) {
+        return Err(ValidationError::new("host").with_message("主机不能包含协议或空白字符".into()));
+    }
+    Ok(())
+}
+
+/// # 代理
+///
+/// 不返回密码
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ProxyResp {
+    pub id: i64,
+    pub pool_id: i32,
+    pub protocol: ProxyProtocol,
+    pub host: String,
+    pub port: i32,
+    pub username: Option<String>,
+    /// # 是否已设置密码
+    pub has_password: bool,
+    pub enabled: bool,
+    /// # 最近一次健康检查时间
+    pub last_checked: Option<DateTime>,
+    pub last_check_ok: Option<bool>,
+    pub last_latency_ms: Option<i32>,
+    pub last_error: Option<String>,
+    pub created: DateTime,
+    pub modified: DateTime,
+}
+
+impl From<proxy::Model> for ProxyResp {
+    fn from(p: proxy::Model) -> Self {
+        Self {
+            has_password: p.password_cipher.is_some(),
+            id: p.id,
+            pool_id: p.pool_id,
+            protocol: p.protocol,
+            host: p.host,
+            port: p.port,
+            username: p.username,
+            enabled: p.enabled,
+            last_checked: p.last_checked,
+            last_check_ok: p.last_check_ok,
+            last_latency_ms: p.last_latency_ms,
+            last_error: p.last_error,
+            created: p.created,
+            modified: p.modified,
+        }
+    }
+}
@@ -9,8 +9,10 @@ pub struct TaskStatistics {
     pub total: i64,
     /// # 未部署任务数（没有调度配置）
     pub undeployed: i64,
-    /// # 调度中任务数（有调度配置）
+    /// # 调度中任务数（有调度配置且未暂停）
     pub scheduled: i64,
+    /// # 已暂停任务数
+    pub paused: i64,
     /// # 调度结束任务数（已删除）
     pub completed: i64,
 }
@@ -1,9 +1,12 @@
+use chrono::{DateTime, FixedOffset};
 use schemars::JsonSchema;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use validator::Validate;
 
 use crate::model::scraper_task::ScraperTaskData;
+use crate::model::sea_orm_active_enums::{InstanceStatus, ScheduleState, TriggerSource};
+use crate::model::{task_dependency, task_dispatch};
 
 /// # 任务查询请求
 #[derive(Debug, Deserialize, Validate, JsonSchema)]
@@ -41,4 +44,120 @@ pub struct ScraperUpdateTaskReq {
 
     #[serde(default)]
     pub rule: Value,
+
+    /// # 规则修改说明，记录到修订历史
+    #[validate(length(max = 256, message = "修改说明不能超过256字符"))]
+    #[serde(default)]
+    pub comment: Option<String>,
+}
+
+/// # 修改规则请求参数
+#[derive(Debug, Deserialize, Validate, JsonSchema)]
+pub struct UpdateRuleQuery {
+    /// # 规则修改说明，记录到修订历史
+    #[validate(length(max = 256, message = "修改说明不能超过256字符"))]
+    pub comment: Option<String>,
+}
+
+/// # 规则对比请求
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RuleDiffQuery {
+    /// # 起始修订号
+    pub from: i32,
+    /// # 目标修订号，为空时与当前规则对比
+    pub to: Option<i32>,
+}
+
+/// # 规则对比结果
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RuleDiffResp {
+    pub from: i32,
+    pub to: i32,
+    /// # 从 `from` 到 `to` 的 JSON Patch（RFC 6902）
+    #[schemars(with = "Value")]
+    pub patch: json_patch::Patch,
+}
+
+/// # 回滚规则请求
+#[derive(Debug, Default, Deserialize, Validate, JsonSchema)]
+pub struct RollbackRuleReq {
+    /// # 回滚说明，为空时自动生成
+    #[validate(length(max = 256, message = "回滚说明不能超过256字符"))]
+    pub comment: Option<String>,
+}
+
+/// # 立即运行任务请求
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RunTaskQuery {
+    /// # 触发来源，页面调用传 `Manual`（默认），脚本等接口调用传 `Api`
+    pub source: Option<TriggerSource>,
+}
+
+/// # 立即运行任务结果
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RunTaskResp {
+    pub task_id: i64,
+    /// # 队列中的任务ID
+    pub job_id: String,
+    pub trigger_source: TriggerSource,
+    /// # 本小时剩余的手动运行次数
+    pub remaining: u64,
+}
+
+/// # 调度预览请求
+#[derive(Debug, Deserialize, Validate, JsonSchema)]
+pub struct SchedulePreviewQuery {
+    /// # 预览次数，默认5次
+    #[validate(range(min = 1, max = 50, message = "预览次数须在1-50之间"))]
+    pub count: Option<usize>,
+    /// # 待预览的 cron 表达式，为空时使用任务已保存的配置
+    pub cron: Option<String>,
+    /// # 待预览的 IANA 时区，为空时使用任务已保存的配置
+    pub timezone: Option<String>,
+}
+
+/// # 调度预览结果
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SchedulePreviewResp {
+    pub cron: String,
+    /// # 为空表示按服务器本地时间
+    pub timezone: Option<String>,
+    /// # 后续触发时间（带时区偏移）
+    pub fire_times: Vec<DateTime<FixedOffset>>,
+}
+
+/// # 新增下游依赖
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AddDependencyReq {
+    /// # 下游任务
+    pub downstream_task_id: i64,
+    /// # 上游实例以该状态结束时触发，`Success` 或 `Failed`
+    pub trigger_on: InstanceStatus,
+    /// # 是否把上游实例的采集记录作为下游输入
+    #[serde(default)]
+    pub pass_records: bool,
+}
+
+/// # 依赖链中的任务
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ChainTask {
+    pub id: i64,
+    pub name: String,
+    pub schedule_state: ScheduleState,
+}
+
+/// # 任务依赖链
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TaskChainResp {
+    pub tasks: Vec<ChainTask>,
+    pub dependencies: Vec<task_dependency::Model>,
+}
+
+/// # 实例的触发链路
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct InstanceChainResp {
+    /// # 创建该实例的派发记录，上游触发时包含上游实例
+    pub dispatch: Option<task_dispatch::Model>,
+    /// # 该实例结束后触发的下游派发
+    pub downstream: Vec<task_dispatch::Model>,
 }
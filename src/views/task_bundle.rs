@@ -0,0 +1,127 @@
+use chrono::NaiveDateTime;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use validator::Validate;
+
+use crate::model::scraper_task::ScraperTaskData;
+use crate::model::sea_orm_active_enums::ScheduleState;
+
+/// # 任务导出包
+///
+/// 可在账号或环境之间迁移任务，包内的任务ID仅用于包内引用，导入时重新分配。
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct TaskBundle {
+    /// # 导出包格式版本
+    pub schema_version: u32,
+    /// # 导出时间
+    pub exported_at: NaiveDateTime,
+    /// # 导出服务的版本
+    #[serde(default)]
+    pub app_version: Option<String>,
+    pub tasks: Vec<BundleTask>,
+}
+
+/// # 导出包中的任务
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BundleTask {
+    /// # 导出时的任务ID
+    pub id: i64,
+    pub name: String,
+    /// # 任务规则定义
+    pub rule: Value,
+    /// # 调度与数据质量等任务级配置
+    #[serde(default)]
+    pub data: Option<ScraperTaskData>,
+    /// # 导出时的调度状态
+    pub schedule_state: ScheduleState,
+    /// # 导出时的规则修订号
+    #[serde(default)]
+    pub rule_revision: i32,
+    pub created: NaiveDateTime,
+    /// # 任务数据集上保存的清洗流程
+    #[serde(default)]
+    pub pipelines: Vec<BundlePipeline>,
+}
+
+/// # 导出包中的清洗流程
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct BundlePipeline {
+    pub name: String,
+    /// # 清洗流程定义，原样保存
+    pub definition: Value,
+}
+
+/// # 导出任务请求
+#[derive(Debug, Deserialize, Validate, JsonSchema)]
+pub struct TaskExportReq {
+    /// # 待导出的任务ID
+    #[validate(length(min = 1, max = 50, message = "每次可导出1-50个任务"))]
+    pub ids: Vec<i64>,
+}
+
+/// # 任务重名时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportConflictStrategy {
+    /// # 存在重名任务时不导入，只返回冲突列表
+    #[default]
+    Abort,
+    /// # 跳过重名任务
+    Skip,
+    /// # 重命名后导入
+    Rename,
+}
+
+/// # 导入任务请求
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TaskImportReq {
+    pub bundle: TaskBundle,
+    #[serde(default)]
+    pub on_conflict: ImportConflictStrategy,
+    /// # 只校验并返回导入计划，不保存
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// # 导入结果
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TaskImportResp {
+    pub dry_run: bool,
+    pub imported: Vec<ImportedTask>,
+    pub conflicts: Vec<ImportConflict>,
+    /// # 引用的代理池不可用、已回退到系统默认代理的任务
+    pub proxy_conflicts: Vec<ProxyPoolConflict>,
+}
+
+/// # 已导入（或计划导入）的任务
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ImportedTask {
+    /// # 导出包中的任务ID
+    pub source_id: i64,
+    /// # 新任务ID，预演时为空
+    pub task_id: Option<i64>,
+    pub name: String,
+    /// # 配置了调度的任务导入后处于暂停状态，确认后再恢复
+    pub schedule_state: ScheduleState,
+    pub pipelines: usize,
+}
+
+/// # 重名冲突
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ImportConflict {
+    pub source_id: i64,
+    pub name: String,
+    /// # 已存在的同名任务
+    pub existing_task_id: i64,
+    pub resolution: ImportConflictStrategy,
+}
+
+/// # 代理池冲突
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ProxyPoolConflict {
+    pub source_id: i64,
+    pub name: String,
+    /// # 导出包中引用的代理池
+    pub proxy_id: i32,
+}
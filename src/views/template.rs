@@ -116,3 +116,14 @@ impl ListTemplateResp {
         resp
     }
 }
+
+/// # 从模板创建任务
+#[derive(Debug, Deserialize, Validate, JsonSchema)]
+pub struct InstantiateTemplateReq {
+    /// # 任务名，为空时使用模板名
+    #[validate(length(min = 1, max = 80, message = "任务名长度必须在1-80字符之间"))]
+    pub name: Option<String>,
+    /// # 参数值，按模板的参数定义校验
+    #[serde(default)]
+    pub params: serde_json::Map<String, Value>,
+}
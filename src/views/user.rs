@@ -81,6 +81,31 @@ pub struct SetNameReq {
     pub name: String,
 }
 
+/// # 开通试用请求
+#[derive(Debug, Validate, Deserialize, JsonSchema)]
+pub struct StartTrialReq {
+    /// # 设备指纹，由前端根据浏览器特征生成
+    #[validate(length(min = 8, max = 128, message = "设备指纹长度必须在8-128字符之间"))]
+    pub fingerprint: String,
+}
+
+/// # 试用状态
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TrialResp {
+    /// # 是否开放试用
+    pub enable: bool,
+    /// # 当前账号是否可以试用
+    pub eligible: bool,
+    /// # 试用的版本
+    pub edition: ProductEdition,
+    /// # 试用天数
+    pub days: i64,
+    /// # 已开通试用的到期时间
+    pub expired_at: Option<DateTime>,
+    /// # 试用后是否已付费
+    pub converted: bool,
+}
+
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct UserResp {
     pub id: i64,
@@ -122,6 +147,16 @@ pub struct ValidateCodeEmailTemplate<'a> {
     /// # 验证码
     pub code: &'a str,
 }
+#[derive(Template)]
+#[template(path = "mail/membership_expiring.html")]
+pub struct MembershipExpiringEmailTemplate<'a> {
+    pub name: &'a str,
+    pub edition: &'a str,
+    pub days: i64,
+    pub expired_at: String,
+    pub renew_url: &'a str,
+}
+
 #[derive(Debug, Serialize, JsonSchema)]
 pub struct CreditLogResp {
     pub id: i64,
@@ -151,3 +186,52 @@ pub struct CheckInResp {
     pub credits: i32,
     pub balance: i32,
 }
+
+/// # 单项配额
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct QuotaItem {
+    /// # 已使用
+    pub used: u64,
+    /// # 上限，为空表示不限
+    pub limit: Option<u64>,
+}
+
+impl QuotaItem {
+    pub fn new(used: u64, limit: u64) -> Self {
+        Self {
+            used,
+            limit: (limit != u64::MAX).then_some(limit),
+        }
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.limit.is_some_and(|limit| self.used >= limit)
+    }
+}
+
+/// # 运行配额使用情况
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct QuotaResp {
+    pub edition: ProductEdition,
+    /// # 正在运行和排队中的实例
+    pub running: QuotaItem,
+    /// # 今日运行次数
+    pub daily_runs: QuotaItem,
+    /// # 本月采集记录数
+    pub monthly_records: QuotaItem,
+}
+
+impl QuotaResp {
+    /// 再运行一次是否会超出配额，超出时返回原因
+    pub fn exceeded(&self) -> Option<&'static str> {
+        if self.running.exhausted() {
+            Some("同时运行的任务数已达当前版本上限")
+        } else if self.daily_runs.exhausted() {
+            Some("今日运行次数已达当前版本上限")
+        } else if self.monthly_records.exhausted() {
+            Some("本月采集记录数已达当前版本上限")
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use validator::Validate;
+
+use crate::model::scraper_task::ScraperTaskData;
+use crate::model::sea_orm_active_enums::{InstanceStatus, ProxyProtocol, ProxyRotation};
+use crate::model::{task_dispatch, task_instance};
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct WorkerTokenReq {
+    pub worker_id: i64,
+    #[validate(length(min = 1, max = 128, message = "密钥不能为空"))]
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkerTokenResp {
+    pub token: String,
+    /// 令牌有效期（秒）
+    pub expires_in: u64,
+}
+
+/// worker 从队列取到任务后创建实例
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateInstanceReq {
+    pub task_id: i64,
+    /// 队列任务ID，用于认领对应的派发记录；为空时按先进先出认领。
+    /// 对应的运行已取消时返回错误，worker 应丢弃该队列任务
+    #[validate(length(max = 64, message = "job_id过长"))]
+    pub job_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkerInstanceResp {
+    pub instance: task_instance::Model,
+    /// 实例认领的派发记录，包含触发来源、上游实例等运行参数
+    pub dispatch: Option<task_dispatch::Model>,
+}
+
+/// 运行进度，同时作为心跳
+#[derive(Debug, Deserialize, Validate)]
+pub struct HeartbeatReq {
+    #[validate(range(min = 0, message = "页数不能为负数"))]
+    pub pages: i32,
+    #[validate(range(min = 0, message = "记录数不能为负数"))]
+    pub records: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeartbeatResp {
+    /// 实例当前状态，不再是 `Running` 时 worker 应停止执行
+    pub status: InstanceStatus,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct FinishInstanceReq {
+    /// `Success` 或 `Failed`
+    pub status: InstanceStatus,
+    #[validate(range(min = 0, message = "记录数不能为负数"))]
+    pub data_count: i32,
+    #[validate(length(max = 500, message = "log_key过长"))]
+    pub log_key: Option<String>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkerTaskResp {
+    pub id: i64,
+    pub name: String,
+    pub rule: Value,
+    pub rule_revision: i32,
+    pub data: Option<ScraperTaskData>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkerProxy {
+    pub id: i64,
+    pub protocol: ProxyProtocol,
+    pub host: String,
+    pub port: i32,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkerProxyPoolResp {
+    pub id: i32,
+    pub rotation: ProxyRotation,
+    pub proxies: Vec<WorkerProxy>,
+}
+
+/// worker 上报的代理健康检查结果
+#[derive(Debug, Deserialize, Validate)]
+pub struct ProxyHealthReq {
+    pub ok: bool,
+    #[validate(range(min = 0, message = "延迟不能为负数"))]
+    pub latency_ms: Option<i32>,
+    #[validate(length(max = 500, message = "错误信息过长"))]
+    pub error: Option<String>,
+}